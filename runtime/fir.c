@@ -0,0 +1,102 @@
+// Runtime support for programs compiled through LLVM IR.
+//
+// Build a program with `clang out.ll runtime/fir.c -o out`, or with
+// `llc -relocation-model=pic out.ll` and `cc out.s runtime/fir.c -o out`.
+
+#include <stdbool.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+void fir_print_int(int value) { printf("%d", value); }
+
+void fir_print_float(double value) { printf("%g", value); }
+
+void fir_print_bool(bool value) { fputs(value ? "true" : "false", stdout); }
+
+void fir_print_char(char value) { putchar(value); }
+
+void fir_print_str(const char *value) { fputs(value, stdout); }
+
+void fir_print_newline(void) { putchar('\n'); }
+
+// A failed assertion reports like the bytecode VM and exits with 101, like a
+// Rust program that panics.
+
+void fir_assert(bool condition, const char *source) {
+    if (!condition) {
+        fflush(stdout);
+        fprintf(stderr, "Assertion failed: `%s`\n", source);
+        exit(101);
+    }
+}
+
+static void fir_assert_eq_failed(const char *lhs, const char *rhs) {
+    fflush(stdout);
+    fprintf(stderr, "Assertion failed: `%s == %s`\n", lhs, rhs);
+}
+
+void fir_assert_eq_int(int lhs, int rhs, const char *lhs_source, const char *rhs_source) {
+    if (lhs != rhs) {
+        fir_assert_eq_failed(lhs_source, rhs_source);
+        fprintf(stderr, "  left: %d\n right: %d\n", lhs, rhs);
+        exit(101);
+    }
+}
+
+void fir_assert_eq_float(double lhs, double rhs, const char *lhs_source, const char *rhs_source) {
+    if (lhs != rhs) {
+        fir_assert_eq_failed(lhs_source, rhs_source);
+        fprintf(stderr, "  left: %g\n right: %g\n", lhs, rhs);
+        exit(101);
+    }
+}
+
+void fir_assert_eq_bool(bool lhs, bool rhs, const char *lhs_source, const char *rhs_source) {
+    if (lhs != rhs) {
+        fir_assert_eq_failed(lhs_source, rhs_source);
+        fprintf(stderr, "  left: %s\n right: %s\n", lhs ? "true" : "false", rhs ? "true" : "false");
+        exit(101);
+    }
+}
+
+void fir_assert_eq_char(char lhs, char rhs, const char *lhs_source, const char *rhs_source) {
+    if (lhs != rhs) {
+        fir_assert_eq_failed(lhs_source, rhs_source);
+        fprintf(stderr, "  left: %c\n right: %c\n", lhs, rhs);
+        exit(101);
+    }
+}
+
+void fir_assert_eq_str(const char *lhs, const char *rhs, const char *lhs_source,
+                       const char *rhs_source) {
+    if (strcmp(lhs, rhs) != 0) {
+        fir_assert_eq_failed(lhs_source, rhs_source);
+        fprintf(stderr, "  left: \"%s\"\n right: \"%s\"\n", lhs, rhs);
+        exit(101);
+    }
+}
+
+char *fir_str_concat(const char *lhs, const char *rhs) {
+    size_t lhs_len = strlen(lhs);
+    size_t rhs_len = strlen(rhs);
+    char *result = malloc(lhs_len + rhs_len + 1);
+    if (result == NULL) {
+        fputs("fir: out of memory\n", stderr);
+        exit(1);
+    }
+    memcpy(result, lhs, lhs_len);
+    memcpy(result + lhs_len, rhs, rhs_len + 1);
+    return result;
+}
+
+bool fir_str_eq(const char *lhs, const char *rhs) { return strcmp(lhs, rhs) == 0; }
+
+int fir_pow_int(int base, int exp) {
+    int result = 1;
+    while (exp > 0) {
+        result *= base;
+        exp--;
+    }
+    return result;
+}
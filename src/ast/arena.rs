@@ -0,0 +1,151 @@
+//! Arenas for the statements and expressions of function bodies.
+//!
+//! A node is stored in the arena of its body and referred to by an `Id`,
+//! which is an index into the arena that also knows the kind of node it is
+//! for. Passes that work out something about every node can keep it in a
+//! table indexed by `Id::index` instead of in the tree.
+//!
+//! Nodes are added after their children, so the id of a node is greater
+//! than the ids of its children.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::{Index, IndexMut};
+
+use crate::error::Error;
+
+use super::Spanned;
+
+pub struct Id<T> {
+    index: u32,
+    node: PhantomData<fn() -> T>,
+}
+
+impl<T> Id<T> {
+    pub fn index(self) -> usize {
+        self.index as usize
+    }
+}
+
+// Derived impls would only exist for `T`s with them.
+impl<T> Clone for Id<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Id<T> {}
+
+impl<T> PartialEq for Id<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for Id<T> {}
+
+impl<T> Hash for Id<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for Id<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.index)
+    }
+}
+
+/// Nodes, or the errors they failed to parse with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arena<T> {
+    nodes: Vec<Result<Spanned<T>, Error>>,
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Self { nodes: Vec::new() }
+    }
+}
+
+impl<T> Arena<T> {
+    pub fn alloc(&mut self, node: Result<Spanned<T>, Error>) -> Id<T> {
+        self.nodes.push(node);
+        Id {
+            index: (self.nodes.len() - 1) as u32,
+            node: PhantomData,
+        }
+    }
+}
+
+impl<T> Index<Id<T>> for Arena<T> {
+    type Output = Result<Spanned<T>, Error>;
+
+    fn index(&self, id: Id<T>) -> &Self::Output {
+        &self.nodes[id.index()]
+    }
+}
+
+impl<T> IndexMut<Id<T>> for Arena<T> {
+    fn index_mut(&mut self, id: Id<T>) -> &mut Self::Output {
+        &mut self.nodes[id.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use super::*;
+    use crate::ast::{Expr, Item, Stmt};
+    use crate::cst;
+
+    #[test]
+    fn ids_are_indices_in_allocation_order() {
+        let mut arena = Arena::default();
+        let a = arena.alloc(Ok(Spanned::new("a", 0..1)));
+        let b = arena.alloc(Ok(Spanned::new("b", 2..3)));
+        assert_eq!((a.index(), b.index()), (0, 1));
+        assert_ne!(a, b);
+        assert_eq!(arena[b].as_ref().unwrap().node, "b");
+        arena[a] = Ok(Spanned::new("c", 0..1));
+        assert_eq!(arena[a].as_ref().unwrap().node, "c");
+    }
+
+    #[test]
+    fn children_come_before_their_parents() {
+        let items = cst::lower::items(&cst::parse(
+            "fn main() {\n    if (1 + 2 * 3 > 4) {\n        print(-5, f(6));\n    }\n}\n",
+        ));
+        let Ok(Spanned {
+            node: Item::Function { body, .. },
+            ..
+        }) = &items[0]
+        else {
+            panic!("{:?}", items);
+        };
+        assert_eq!(body.exprs.nodes.len(), 12);
+        for index in 0..body.exprs.nodes.len() {
+            let id = Id {
+                index: index as u32,
+                node: PhantomData,
+            };
+            let children = match &body.expr(id).unwrap().node {
+                Expr::FnCall { args, .. } => args.clone(),
+                Expr::PrefixOp { expr, .. } | Expr::PostfixOp { expr, .. } => vec![*expr],
+                Expr::InfixOp { lhs, rhs, .. } => vec![*lhs, *rhs],
+                _ => Vec::new(),
+            };
+            assert!(
+                children.iter().all(|child| child.index() < index),
+                "{:?}",
+                id
+            );
+        }
+        let Stmt::IfStmt { condition, .. } = body.stmt(body.block[0]).unwrap().node else {
+            panic!("{:?}", body);
+        };
+        assert_eq!(condition.index(), 6);
+    }
+}
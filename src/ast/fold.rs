@@ -0,0 +1,438 @@
+//! Rewriting the AST.
+//!
+//! A `Folder` takes each node by value and returns the node to put in its
+//! place, so a pass can replace a node by one of a different kind, or drop
+//! statements from a block. Statements and expressions are taken out of
+//! the body they are in while they are folded and put back under the same
+//! id; a folder can add new nodes to the body to refer to. The `fold_*`
+//! functions fold the children of a node, which the default methods do.
+
+use std::mem;
+
+use logos::Span;
+
+use crate::error::Error;
+
+use super::{
+    Arena, Attr, Attrs, Body, Expr, Id, Ident, Item, Lit, Member, Spanned, Stmt, StmtId, Type,
+};
+
+pub trait Folder: Sized {
+    fn fold_item(&mut self, item: Spanned<Item>) -> Spanned<Item> {
+        fold_item(self, item)
+    }
+
+    fn fold_type(&mut self, ty: Spanned<Type>) -> Spanned<Type> {
+        fold_type(self, ty)
+    }
+
+    /// The statements of a function body, an `if` or a block, which make a
+    /// scope.
+    fn fold_block(&mut self, body: &mut Body, stmts: Vec<StmtId>) -> Vec<StmtId> {
+        fold_block(self, body, stmts)
+    }
+
+    fn fold_stmt(&mut self, body: &mut Body, stmt: Spanned<Stmt>) -> Spanned<Stmt> {
+        fold_stmt(self, body, stmt)
+    }
+
+    fn fold_expr(&mut self, body: &mut Body, expr: Spanned<Expr>) -> Spanned<Expr> {
+        fold_expr(self, body, expr)
+    }
+
+    fn fold_error(&mut self, err: Error) -> Error {
+        err
+    }
+}
+
+/// Takes node `id` out of `arena`, leaving `empty` with the same span in
+/// its place until it is put back.
+fn take<T>(arena: &mut Arena<T>, id: Id<T>, empty: T) -> Result<Spanned<T>, Error> {
+    let span = match &arena[id] {
+        Ok(node) => node.span.clone(),
+        Err(err) => err.span(),
+    };
+    mem::replace(&mut arena[id], Ok(Spanned::new(empty, span)))
+}
+
+fn type_<F: Folder>(
+    folder: &mut F,
+    ty: Result<Spanned<Type>, Error>,
+) -> Result<Spanned<Type>, Error> {
+    match ty {
+        Ok(ty) => Ok(folder.fold_type(ty)),
+        Err(err) => Err(folder.fold_error(err)),
+    }
+}
+
+fn stmt<F: Folder>(folder: &mut F, body: &mut Body, id: StmtId) {
+    let empty = Stmt::Block { stmts: Vec::new() };
+    let stmt = match take(&mut body.stmts, id, empty) {
+        Ok(stmt) => Ok(folder.fold_stmt(body, stmt)),
+        Err(err) => Err(folder.fold_error(err)),
+    };
+    body.stmts[id] = stmt;
+}
+
+fn expr<F: Folder>(folder: &mut F, body: &mut Body, id: Id<Expr>) {
+    let expr = match take(&mut body.exprs, id, Expr::Literal(Lit::Bool(false))) {
+        Ok(expr) => Ok(folder.fold_expr(body, expr)),
+        Err(err) => Err(folder.fold_error(err)),
+    };
+    body.exprs[id] = expr;
+}
+
+fn typed<F: Folder>(
+    folder: &mut F,
+    parameters: Vec<(super::Ident, Result<Spanned<Type>, Error>)>,
+) -> Vec<(super::Ident, Result<Spanned<Type>, Error>)> {
+    parameters
+        .into_iter()
+        .map(|(name, ty)| (name, type_(folder, ty)))
+        .collect()
+}
+
+pub fn fold_item<F: Folder>(folder: &mut F, item: Spanned<Item>) -> Spanned<Item> {
+    let node = match item.node {
+        Item::Struct {
+            doc,
+            attrs,
+            name,
+            members,
+        } => Item::Struct {
+            doc,
+            attrs,
+            name: type_(folder, name),
+            members: members
+                .into_iter()
+                .map(|member| Member {
+                    ty: type_(folder, member.ty),
+                    ..member
+                })
+                .collect(),
+        },
+        Item::Function {
+            doc,
+            attrs,
+            name,
+            parameters,
+            return_type,
+            mut body,
+        } => {
+            let parameters = typed(folder, parameters);
+            let return_type = return_type.map(|return_type| type_(folder, return_type));
+            let block = mem::take(&mut body.block);
+            body.block = folder.fold_block(&mut body, block);
+            Item::Function {
+                doc,
+                attrs,
+                name,
+                parameters,
+                return_type,
+                body,
+            }
+        }
+    };
+    Spanned::new(node, item.span)
+}
+
+pub fn fold_type<F: Folder>(folder: &mut F, ty: Spanned<Type>) -> Spanned<Type> {
+    let Type { name, generics } = ty.node;
+    let generics = generics
+        .into_iter()
+        .map(|generic| type_(folder, generic))
+        .collect();
+    Spanned::new(Type { name, generics }, ty.span)
+}
+
+pub fn fold_block<F: Folder>(folder: &mut F, body: &mut Body, stmts: Vec<StmtId>) -> Vec<StmtId> {
+    for id in &stmts {
+        stmt(folder, body, *id);
+    }
+    stmts
+}
+
+pub fn fold_stmt<F: Folder>(folder: &mut F, body: &mut Body, stmt: Spanned<Stmt>) -> Spanned<Stmt> {
+    let node = match stmt.node {
+        Stmt::Let { value, .. } | Stmt::Assignment { value, .. } => {
+            expr(folder, body, value);
+            stmt.node
+        }
+        Stmt::IfStmt {
+            condition,
+            body: then,
+            else_stmt,
+        } => {
+            expr(folder, body, condition);
+            let then = folder.fold_block(body, then);
+            if let Some(else_stmt) = else_stmt {
+                self::stmt(folder, body, else_stmt);
+            }
+            Stmt::IfStmt {
+                condition,
+                body: then,
+                else_stmt,
+            }
+        }
+        Stmt::Block { stmts } => Stmt::Block {
+            stmts: folder.fold_block(body, stmts),
+        },
+        Stmt::Expr { expr: value } => {
+            expr(folder, body, value);
+            stmt.node
+        }
+        Stmt::Return { value } => {
+            if let Some(value) = value {
+                expr(folder, body, value);
+            }
+            stmt.node
+        }
+        Stmt::Error(err) => Stmt::Error(folder.fold_error(err)),
+    };
+    Spanned::new(node, stmt.span)
+}
+
+pub fn fold_expr<F: Folder>(folder: &mut F, body: &mut Body, expr: Spanned<Expr>) -> Spanned<Expr> {
+    match &expr.node {
+        Expr::Literal(_) | Expr::Ident(_) => {}
+        Expr::FnCall { args, .. } => {
+            for arg in args {
+                self::expr(folder, body, *arg);
+            }
+        }
+        Expr::PrefixOp { expr: operand, .. } | Expr::PostfixOp { expr: operand, .. } => {
+            self::expr(folder, body, *operand)
+        }
+        Expr::InfixOp { lhs, rhs, .. } => {
+            self::expr(folder, body, *lhs);
+            self::expr(folder, body, *rhs);
+        }
+        Expr::Member { expr: operand, .. } => self::expr(folder, body, *operand),
+        Expr::StructLit { fields, .. } => {
+            for (_, value) in fields {
+                self::expr(folder, body, *value);
+            }
+        }
+    }
+    expr
+}
+
+/// Moves every span in what it folds by a number of bytes, see
+/// `Spanned::moved`.
+pub struct Move(pub isize);
+
+impl Move {
+    fn span(&self, span: Span) -> Span {
+        (span.start as isize + self.0) as usize..(span.end as isize + self.0) as usize
+    }
+
+    fn ident(&self, ident: Ident) -> Ident {
+        Spanned::new(ident.node, self.span(ident.span))
+    }
+
+    fn attrs(&self, attrs: Attrs) -> Attrs {
+        attrs
+            .into_iter()
+            .map(|attr| {
+                let Attr { name, args } = attr.node;
+                let args = args
+                    .into_iter()
+                    .map(|arg| Spanned::new(arg.node, self.span(arg.span)))
+                    .collect();
+                let name = self.ident(name);
+                Spanned::new(Attr { name, args }, self.span(attr.span))
+            })
+            .collect()
+    }
+}
+
+impl Folder for Move {
+    fn fold_item(&mut self, item: Spanned<Item>) -> Spanned<Item> {
+        let item = fold_item(self, item);
+        let node = match item.node {
+            Item::Struct {
+                doc,
+                attrs,
+                name,
+                members,
+            } => Item::Struct {
+                doc,
+                attrs: self.attrs(attrs),
+                name,
+                members: members
+                    .into_iter()
+                    .map(|member| Member {
+                        attrs: self.attrs(member.attrs),
+                        name: self.ident(member.name),
+                        ..member
+                    })
+                    .collect(),
+            },
+            Item::Function {
+                doc,
+                attrs,
+                name,
+                parameters,
+                return_type,
+                mut body,
+            } => {
+                body.attrs = mem::take(&mut body.attrs)
+                    .into_iter()
+                    .map(|(id, attrs)| (id, self.attrs(attrs)))
+                    .collect();
+                Item::Function {
+                    doc,
+                    attrs: self.attrs(attrs),
+                    name: self.ident(name),
+                    parameters: parameters
+                        .into_iter()
+                        .map(|(name, ty)| (self.ident(name), ty))
+                        .collect(),
+                    return_type,
+                    body,
+                }
+            }
+        };
+        Spanned::new(node, self.span(item.span))
+    }
+
+    fn fold_type(&mut self, ty: Spanned<Type>) -> Spanned<Type> {
+        let ty = fold_type(self, ty);
+        let Type { name, generics } = ty.node;
+        let name = self.ident(name);
+        Spanned::new(Type { name, generics }, self.span(ty.span))
+    }
+
+    fn fold_stmt(&mut self, body: &mut Body, stmt: Spanned<Stmt>) -> Spanned<Stmt> {
+        let stmt = fold_stmt(self, body, stmt);
+        let node = match stmt.node {
+            Stmt::Let { var_name, value } => Stmt::Let {
+                var_name: self.ident(var_name),
+                value,
+            },
+            Stmt::Assignment { var_name, value } => Stmt::Assignment {
+                var_name: self.ident(var_name),
+                value,
+            },
+            node => node,
+        };
+        Spanned::new(node, self.span(stmt.span))
+    }
+
+    fn fold_expr(&mut self, body: &mut Body, expr: Spanned<Expr>) -> Spanned<Expr> {
+        let expr = fold_expr(self, body, expr);
+        let node = match expr.node {
+            Expr::Ident(name) => Expr::Ident(self.ident(name)),
+            Expr::FnCall { fn_name, args } => Expr::FnCall {
+                fn_name: self.ident(fn_name),
+                args,
+            },
+            Expr::Member { expr, member } => Expr::Member {
+                expr,
+                member: self.ident(member),
+            },
+            Expr::StructLit {
+                struct_name,
+                fields,
+            } => Expr::StructLit {
+                struct_name: self.ident(struct_name),
+                fields: fields
+                    .into_iter()
+                    .map(|(member, value)| (self.ident(member), value))
+                    .collect(),
+            },
+            node => node,
+        };
+        Spanned::new(node, self.span(expr.span))
+    }
+
+    fn fold_error(&mut self, err: Error) -> Error {
+        err.moved(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::print, cst, lexer::TokenKind};
+
+    /// Drops `print` statements and writes `-e` as `0 - e`.
+    struct Quiet;
+
+    impl Folder for Quiet {
+        fn fold_block(&mut self, body: &mut Body, stmts: Vec<StmtId>) -> Vec<StmtId> {
+            let mut stmts = fold_block(self, body, stmts);
+            stmts.retain(|id| match &body.stmts[*id] {
+                Ok(stmt) => match stmt.node {
+                    Stmt::Expr { expr } => !matches!(
+                        &body.exprs[expr],
+                        Ok(Spanned { node: Expr::FnCall { fn_name, .. }, .. })
+                            if fn_name.as_str() == "print"
+                    ),
+                    _ => true,
+                },
+                Err(_) => true,
+            });
+            stmts
+        }
+
+        fn fold_expr(&mut self, body: &mut Body, expr: Spanned<Expr>) -> Spanned<Expr> {
+            let expr = fold_expr(self, body, expr);
+            let node = match expr.node {
+                Expr::PrefixOp {
+                    op: TokenKind::Minus,
+                    expr: operand,
+                } => {
+                    let zero = Spanned::new(Expr::Literal(Lit::Int(0)), expr.span.clone());
+                    Expr::InfixOp {
+                        op: TokenKind::Minus,
+                        lhs: body.exprs.alloc(Ok(zero)),
+                        rhs: operand,
+                    }
+                }
+                node => node,
+            };
+            Spanned::new(node, expr.span)
+        }
+    }
+
+    #[test]
+    fn rewrites_a_program() {
+        let items = cst::lower::items(&cst::parse(
+            "fn main() {
+    let a = -1;
+    print(a);
+    if (a < 0) {
+        print(-a);
+        a = -(a * 2);
+    }
+}
+",
+        ));
+        let items: Vec<_> = items
+            .into_iter()
+            .map(|item| item.map(|item| Quiet.fold_item(item)))
+            .collect();
+        assert_eq!(
+            print::source(&items),
+            "fn main() {
+    let a = 0 - 1;
+    if (a < 0) {
+        a = 0 - a * 2;
+    }
+}
+"
+        );
+    }
+
+    #[test]
+    fn moves_every_span() {
+        let text = "fn f(x: int) -> int {\n    return -x;\n}\n";
+        let items = cst::lower::items(&cst::parse(text));
+        let item = items[0].clone().unwrap();
+        let moved = item.clone().moved(10).moved(-10);
+        assert_eq!(moved, item);
+        let moved = item.moved(3);
+        assert_eq!(moved.span, 3..text.len() + 2);
+    }
+}
@@ -0,0 +1,538 @@
+//! The AST as JSON, for tools outside of Rust.
+//!
+//! The format is described in `spec/ast-json.md`. It is versioned by
+//! `VERSION`, which changes whenever the AST does in a way that old readers
+//! or writers would get wrong.
+//!
+//! Function bodies are written as trees, with every statement and
+//! expression in the place of its id, so the format does not depend on how
+//! bodies are stored. A statement with attributes is wrapped in an
+//! `Attributed` node that holds them.
+
+use logos::Logos;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ast::{self, Attrs, Body, Doc, ExprId, Ident, Lit, Member, Spanned, StmtId, Type},
+    error::{codes, Error},
+    lexer::TokenKind,
+};
+
+pub const VERSION: u32 = 1;
+
+type Items = Vec<Result<Spanned<ast::Item>, Error>>;
+
+#[derive(Serialize)]
+struct File {
+    version: u32,
+    items: Vec<Result<Spanned<Item>, Error>>,
+}
+
+/// Only the version, to check it before the items are read.
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct OwnedFile {
+    items: Vec<Result<Spanned<Item>, Error>>,
+}
+
+/// `ast::Item` with its body as a tree.
+#[derive(Serialize, Deserialize)]
+enum Item {
+    Struct {
+        doc: Doc,
+        attrs: Attrs,
+        name: Result<Spanned<Type>, Error>,
+        members: Vec<Member>,
+    },
+    Function {
+        doc: Doc,
+        attrs: Attrs,
+        name: Ident,
+        parameters: Vec<(Ident, Result<Spanned<Type>, Error>)>,
+        return_type: Option<Result<Spanned<Type>, Error>>,
+        body: Vec<Result<Spanned<Stmt>, Error>>,
+    },
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Serialize, Deserialize)]
+enum Stmt {
+    Let {
+        var_name: Ident,
+        value: Box<Result<Spanned<Expr>, Error>>,
+    },
+    Assignment {
+        var_name: Ident,
+        value: Box<Result<Spanned<Expr>, Error>>,
+    },
+    IfStmt {
+        condition: Box<Result<Spanned<Expr>, Error>>,
+        body: Vec<Result<Spanned<Stmt>, Error>>,
+        else_stmt: Option<Box<Result<Spanned<Stmt>, Error>>>,
+    },
+    Block {
+        stmts: Vec<Result<Spanned<Stmt>, Error>>,
+    },
+    Expr {
+        expr: Box<Result<Spanned<Expr>, Error>>,
+    },
+    Return {
+        value: Option<Box<Result<Spanned<Expr>, Error>>>,
+    },
+    Error(Error),
+    /// Not in `ast::Stmt`, where the attributes are in `Body::attrs`.
+    Attributed {
+        attrs: Attrs,
+        stmt: Box<Result<Spanned<Stmt>, Error>>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+enum Expr {
+    Literal(Lit),
+    Ident(Ident),
+    FnCall {
+        fn_name: Ident,
+        args: Vec<Result<Spanned<Expr>, Error>>,
+    },
+    PrefixOp {
+        #[serde(with = "op")]
+        op: TokenKind,
+        expr: Box<Result<Spanned<Expr>, Error>>,
+    },
+    InfixOp {
+        #[serde(with = "op")]
+        op: TokenKind,
+        lhs: Box<Result<Spanned<Expr>, Error>>,
+        rhs: Box<Result<Spanned<Expr>, Error>>,
+    },
+    PostfixOp {
+        #[serde(with = "op")]
+        op: TokenKind,
+        expr: Box<Result<Spanned<Expr>, Error>>,
+    },
+    Member {
+        expr: Box<Result<Spanned<Expr>, Error>>,
+        member: Ident,
+    },
+    StructLit {
+        struct_name: Ident,
+        fields: Vec<(Ident, Result<Spanned<Expr>, Error>)>,
+    },
+}
+
+fn tree<T, U>(
+    node: &Result<Spanned<T>, Error>,
+    convert: impl FnOnce(&T) -> U,
+) -> Result<Spanned<U>, Error> {
+    match node {
+        Ok(node) => Ok(Spanned::new(convert(&node.node), node.span.clone())),
+        Err(err) => Err(err.clone()),
+    }
+}
+
+fn item_tree(item: &ast::Item) -> Item {
+    match item {
+        ast::Item::Struct {
+            doc,
+            attrs,
+            name,
+            members,
+        } => Item::Struct {
+            doc: doc.clone(),
+            attrs: attrs.clone(),
+            name: name.clone(),
+            members: members.clone(),
+        },
+        ast::Item::Function {
+            doc,
+            attrs,
+            name,
+            parameters,
+            return_type,
+            body,
+        } => Item::Function {
+            doc: doc.clone(),
+            attrs: attrs.clone(),
+            name: name.clone(),
+            parameters: parameters.clone(),
+            return_type: return_type.clone(),
+            body: stmt_trees(body, &body.block),
+        },
+    }
+}
+
+fn stmt_trees(body: &Body, stmts: &[StmtId]) -> Vec<Result<Spanned<Stmt>, Error>> {
+    stmts.iter().map(|stmt| stmt_tree(body, *stmt)).collect()
+}
+
+fn stmt_tree(body: &Body, stmt: StmtId) -> Result<Spanned<Stmt>, Error> {
+    let expr = |expr: &ExprId| Box::new(expr_tree(body, *expr));
+    let attrs = body.attrs(stmt);
+    let tree = tree(&body[stmt], |stmt| match stmt {
+        ast::Stmt::Let { var_name, value } => Stmt::Let {
+            var_name: var_name.clone(),
+            value: expr(value),
+        },
+        ast::Stmt::Assignment { var_name, value } => Stmt::Assignment {
+            var_name: var_name.clone(),
+            value: expr(value),
+        },
+        ast::Stmt::IfStmt {
+            condition,
+            body: then,
+            else_stmt,
+        } => Stmt::IfStmt {
+            condition: expr(condition),
+            body: stmt_trees(body, then),
+            else_stmt: else_stmt.map(|else_stmt| Box::new(stmt_tree(body, else_stmt))),
+        },
+        ast::Stmt::Block { stmts } => Stmt::Block {
+            stmts: stmt_trees(body, stmts),
+        },
+        ast::Stmt::Expr { expr: value } => Stmt::Expr { expr: expr(value) },
+        ast::Stmt::Return { value } => Stmt::Return {
+            value: value.as_ref().map(expr),
+        },
+        ast::Stmt::Error(err) => Stmt::Error(err.clone()),
+    });
+    if attrs.is_empty() {
+        return tree;
+    }
+    let span = match &tree {
+        Ok(tree) => tree.span.clone(),
+        Err(err) => err.span(),
+    };
+    Ok(Spanned::new(
+        Stmt::Attributed {
+            attrs: attrs.to_vec(),
+            stmt: Box::new(tree),
+        },
+        span,
+    ))
+}
+
+fn expr_tree(body: &Body, expr: ExprId) -> Result<Spanned<Expr>, Error> {
+    let operand = |expr: &ExprId| Box::new(expr_tree(body, *expr));
+    tree(&body[expr], |expr| match expr {
+        ast::Expr::Literal(lit) => Expr::Literal(lit.clone()),
+        ast::Expr::Ident(name) => Expr::Ident(name.clone()),
+        ast::Expr::FnCall { fn_name, args } => Expr::FnCall {
+            fn_name: fn_name.clone(),
+            args: args.iter().map(|arg| expr_tree(body, *arg)).collect(),
+        },
+        ast::Expr::PrefixOp { op, expr } => Expr::PrefixOp {
+            op: *op,
+            expr: operand(expr),
+        },
+        ast::Expr::InfixOp { op, lhs, rhs } => Expr::InfixOp {
+            op: *op,
+            lhs: operand(lhs),
+            rhs: operand(rhs),
+        },
+        ast::Expr::PostfixOp { op, expr } => Expr::PostfixOp {
+            op: *op,
+            expr: operand(expr),
+        },
+        ast::Expr::Member { expr, member } => Expr::Member {
+            expr: operand(expr),
+            member: member.clone(),
+        },
+        ast::Expr::StructLit {
+            struct_name,
+            fields,
+        } => Expr::StructLit {
+            struct_name: struct_name.clone(),
+            fields: fields
+                .iter()
+                .map(|(member, value)| (member.clone(), expr_tree(body, *value)))
+                .collect(),
+        },
+    })
+}
+
+fn item_node(item: Item) -> ast::Item {
+    match item {
+        Item::Struct {
+            doc,
+            attrs,
+            name,
+            members,
+        } => ast::Item::Struct {
+            doc,
+            attrs,
+            name,
+            members,
+        },
+        Item::Function {
+            doc,
+            attrs,
+            name,
+            parameters,
+            return_type,
+            body: stmts,
+        } => {
+            let mut body = Body::default();
+            body.block = stmt_nodes(&mut body, stmts);
+            ast::Item::Function {
+                doc,
+                attrs,
+                name,
+                parameters,
+                return_type,
+                body,
+            }
+        }
+    }
+}
+
+fn stmt_nodes(body: &mut Body, stmts: Vec<Result<Spanned<Stmt>, Error>>) -> Vec<StmtId> {
+    stmts
+        .into_iter()
+        .map(|stmt| stmt_node(body, stmt))
+        .collect()
+}
+
+/// Adds `stmt` to `body`, after its children.
+fn stmt_node(body: &mut Body, stmt: Result<Spanned<Stmt>, Error>) -> StmtId {
+    let stmt = match stmt {
+        Ok(Spanned {
+            node: Stmt::Attributed { attrs, stmt },
+            ..
+        }) => {
+            let id = stmt_node(body, *stmt);
+            body.attrs.insert(id, attrs);
+            return id;
+        }
+        stmt => stmt,
+    };
+    let stmt = stmt.map(|stmt| {
+        let node = match stmt.node {
+            Stmt::Let { var_name, value } => ast::Stmt::Let {
+                var_name,
+                value: expr_node(body, *value),
+            },
+            Stmt::Assignment { var_name, value } => ast::Stmt::Assignment {
+                var_name,
+                value: expr_node(body, *value),
+            },
+            Stmt::IfStmt {
+                condition,
+                body: then,
+                else_stmt,
+            } => ast::Stmt::IfStmt {
+                condition: expr_node(body, *condition),
+                body: stmt_nodes(body, then),
+                else_stmt: else_stmt.map(|else_stmt| stmt_node(body, *else_stmt)),
+            },
+            Stmt::Block { stmts } => ast::Stmt::Block {
+                stmts: stmt_nodes(body, stmts),
+            },
+            Stmt::Expr { expr } => ast::Stmt::Expr {
+                expr: expr_node(body, *expr),
+            },
+            Stmt::Return { value } => ast::Stmt::Return {
+                value: value.map(|value| expr_node(body, *value)),
+            },
+            Stmt::Error(err) => ast::Stmt::Error(err),
+            Stmt::Attributed { .. } => unreachable!("attributes are taken off above"),
+        };
+        Spanned::new(node, stmt.span)
+    });
+    body.stmts.alloc(stmt)
+}
+
+/// Adds `expr` to `body`, after its children.
+fn expr_node(body: &mut Body, expr: Result<Spanned<Expr>, Error>) -> ExprId {
+    let expr = expr.map(|expr| {
+        let node = match expr.node {
+            Expr::Literal(lit) => ast::Expr::Literal(lit),
+            Expr::Ident(name) => ast::Expr::Ident(name),
+            Expr::FnCall { fn_name, args } => ast::Expr::FnCall {
+                fn_name,
+                args: args.into_iter().map(|arg| expr_node(body, arg)).collect(),
+            },
+            Expr::PrefixOp { op, expr } => ast::Expr::PrefixOp {
+                op,
+                expr: expr_node(body, *expr),
+            },
+            Expr::InfixOp { op, lhs, rhs } => ast::Expr::InfixOp {
+                op,
+                lhs: expr_node(body, *lhs),
+                rhs: expr_node(body, *rhs),
+            },
+            Expr::PostfixOp { op, expr } => ast::Expr::PostfixOp {
+                op,
+                expr: expr_node(body, *expr),
+            },
+            Expr::Member { expr, member } => ast::Expr::Member {
+                expr: expr_node(body, *expr),
+                member,
+            },
+            Expr::StructLit {
+                struct_name,
+                fields,
+            } => ast::Expr::StructLit {
+                struct_name,
+                fields: fields
+                    .into_iter()
+                    .map(|(member, value)| (member, expr_node(body, value)))
+                    .collect(),
+            },
+        };
+        Spanned::new(node, expr.span)
+    });
+    body.exprs.alloc(expr)
+}
+
+fn error(msg: String) -> Error {
+    Error::new(msg, codes::E014, 0..0)
+}
+
+pub fn write(items: &[Result<Spanned<ast::Item>, Error>]) -> String {
+    let file = File {
+        version: VERSION,
+        items: items.iter().map(|item| tree(item, item_tree)).collect(),
+    };
+    serde_json::to_string_pretty(&file).unwrap()
+}
+
+pub fn read(text: &str) -> Result<Items, Error> {
+    let Version { version } = serde_json::from_str(text)
+        .map_err(|err| error(format!("Not an AST JSON file: {}", err)))?;
+    if version != VERSION {
+        return Err(error(format!(
+            "AST JSON version {} is not supported, only {} is",
+            version, VERSION
+        )));
+    }
+    let file: OwnedFile =
+        serde_json::from_str(text).map_err(|err| error(format!("Invalid AST JSON: {}", err)))?;
+    Ok(file
+        .items
+        .into_iter()
+        .map(|item| item.map(|item| Spanned::new(item_node(item.node), item.span)))
+        .collect())
+}
+
+/// Serializes an operator as its symbol, e.g. `"+"`, for `#[serde(with)]`.
+pub mod op {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use super::{Logos, TokenKind};
+
+    pub fn serialize<S: Serializer>(op: &TokenKind, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(op)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TokenKind, D::Error> {
+        let symbol = String::deserialize(deserializer)?;
+        let mut lexer = TokenKind::lexer(&symbol);
+        match (lexer.next(), lexer.next()) {
+            (
+                Some(
+                    op @ (TokenKind::Plus
+                    | TokenKind::Minus
+                    | TokenKind::Times
+                    | TokenKind::Slash
+                    | TokenKind::Caret
+                    | TokenKind::Eqq
+                    | TokenKind::Neq
+                    | TokenKind::And
+                    | TokenKind::Or
+                    | TokenKind::LAngle
+                    | TokenKind::Leq
+                    | TokenKind::RAngle
+                    | TokenKind::Geq
+                    | TokenKind::Bang),
+                ),
+                None,
+            ) => Ok(op),
+            _ => Err(de::Error::custom(format!(
+                "`{}` is not an operator",
+                symbol
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cst;
+
+    /// Checks that `text` reads back as it was written, and returns the
+    /// JSON.
+    fn round_trip(text: &str) -> String {
+        let items = cst::lower::items(&cst::parse(text));
+        let json = write(&items);
+        assert_eq!(read(&json).unwrap(), items, "{}", text);
+        json
+    }
+
+    fn round_trip_error(text: &str) {
+        assert!(round_trip(text).contains("\"Err\""), "no error in {}", text);
+    }
+
+    #[test]
+    fn program() {
+        let json = round_trip(
+            "/// A point.
+#[derive(Eq, Print, Hash, Clone)]
+struct Point {
+    /// Across.
+    #[deprecated(\"Use `y`\")]
+    x: int,
+    y: float,
+}
+
+#[inline(always)]
+fn add(a: int, b: int) -> int {
+    let c = a + b * 2 ^ 3 ^ 2;
+    if (c > 0 && !false) {
+        return -c;
+    } else if (c == 0) {
+        return 1 - (2 - 3);
+    } else {
+        c = c!;
+    }
+    {
+        print(\"a \\\"quoted\\\" \\\\ string\", 1.5);
+    }
+    #[allow(unused_variables)]
+    let unused = true;
+    return c;
+}
+
+#[test]
+fn adds() {
+    assert_eq(add(1, 2), 7);
+    return;
+}
+",
+        );
+        assert!(!json.contains("\"Err\""));
+    }
+
+    #[test]
+    fn items_that_failed_to_parse() {
+        round_trip_error("fn 9() {}\n\nfn main() {}\n");
+        round_trip_error("struct { x: int }\n");
+        round_trip_error("fn f(a: int -> int { return 1; }\n");
+        round_trip_error("fn main() {} }\n");
+        round_trip_error("let x = 3;\n");
+    }
+
+    #[test]
+    fn statements_and_expressions_that_failed_to_parse() {
+        round_trip_error("fn main() {\n    let 5 = 3;\n}\n");
+        round_trip_error("fn main() {\n    let x = ;\n    print(1 + );\n}\n");
+        round_trip_error("fn main() {\n    let x = 1\n    let y = 99999999999999999999999;\n}\n");
+        round_trip_error("fn main() {\n    if (x = 1) { }\n    * print(1);\n}\n");
+        round_trip_error("fn main() {\n    let x = \"abc;\n}\n");
+    }
+}
@@ -0,0 +1,252 @@
+//! The abstract syntax tree.
+//!
+//! Identifiers are interned `Symbol`s. The statements and expressions of a
+//! function are kept in the arenas of its `Body` and refer to each other by
+//! id, see `arena`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{Deref, Index};
+
+use logos::Span;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::Error, lexer::TokenKind};
+
+pub mod arena;
+pub mod fold;
+pub mod json;
+pub mod print;
+pub mod symbol;
+pub mod visit;
+pub mod visit_mut;
+
+pub use arena::{Arena, Id};
+pub use symbol::Symbol;
+
+use fold::Folder;
+
+/// A node together with the range of the source it was parsed from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Self {
+        Self { node, span }
+    }
+}
+
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.node
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Spanned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.node.fmt(f)
+    }
+}
+
+/// An identifier, e.g. the name of a variable, function or type.
+pub type Ident = Spanned<Symbol>;
+
+pub type StmtId = Id<Stmt>;
+pub type ExprId = Id<Expr>;
+
+/// The text of the `///` comments before an item or member, a line for
+/// each, see `lexer::doc_text`.
+pub type Doc = Option<String>;
+
+/// An attribute before an item, member or statement, e.g. `#[test]` or
+/// `#[allow(unused_variables)]`. Which there are is up to `attrs`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attr {
+    pub name: Ident,
+    pub args: Vec<Spanned<AttrArg>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttrArg {
+    Name(Symbol),
+    /// The text between the quotes, with escapes as written, as in
+    /// `Lit::Str`.
+    Str(String),
+}
+
+/// The attributes of an item, member or statement, in order.
+pub type Attrs = Vec<Spanned<Attr>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Struct {
+        doc: Doc,
+        attrs: Attrs,
+        name: Result<Spanned<Type>, Error>,
+        members: Vec<Member>,
+    },
+    Function {
+        doc: Doc,
+        attrs: Attrs,
+        name: Ident,
+        parameters: Vec<(Ident, Result<Spanned<Type>, Error>)>,
+        return_type: Option<Result<Spanned<Type>, Error>>,
+        body: Body,
+    },
+}
+
+impl Spanned<Item> {
+    /// The item with every span in it moved by `delta` bytes, like
+    /// `Error::moved`.
+    pub fn moved(self, delta: isize) -> Self {
+        fold::Move(delta).fold_item(self)
+    }
+}
+
+impl Item {
+    pub fn attrs(&self) -> &Attrs {
+        match self {
+            Item::Struct { attrs, .. } | Item::Function { attrs, .. } => attrs,
+        }
+    }
+
+    /// Whether the item is a function marked `#[test]`, for `fir test`.
+    pub fn is_test(&self) -> bool {
+        matches!(self, Item::Function { attrs, .. } if crate::attrs::has(attrs, "test"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Member {
+    pub doc: Doc,
+    pub attrs: Attrs,
+    pub name: Ident,
+    pub ty: Result<Spanned<Type>, Error>,
+}
+
+/// The statements and expressions of a function.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Body {
+    pub stmts: Arena<Stmt>,
+    pub exprs: Arena<Expr>,
+    /// The statements of the function itself, in order.
+    pub block: Vec<StmtId>,
+    /// The attributes of the statements that have any.
+    pub attrs: HashMap<StmtId, Attrs>,
+}
+
+impl Body {
+    /// The statement `id`, or the error it failed to parse with.
+    pub fn stmt(&self, id: StmtId) -> Result<&Spanned<Stmt>, Error> {
+        self.stmts[id].as_ref().map_err(Clone::clone)
+    }
+
+    /// The expression `id`, or the error it failed to parse with.
+    pub fn expr(&self, id: ExprId) -> Result<&Spanned<Expr>, Error> {
+        self.exprs[id].as_ref().map_err(Clone::clone)
+    }
+
+    /// The attributes of the statement `id`.
+    pub fn attrs(&self, id: StmtId) -> &[Spanned<Attr>] {
+        self.attrs.get(&id).map_or(&[], Vec::as_slice)
+    }
+}
+
+impl Index<StmtId> for Body {
+    type Output = Result<Spanned<Stmt>, Error>;
+
+    fn index(&self, id: StmtId) -> &Self::Output {
+        &self.stmts[id]
+    }
+}
+
+impl Index<ExprId> for Body {
+    type Output = Result<Spanned<Expr>, Error>;
+
+    fn index(&self, id: ExprId) -> &Self::Output {
+        &self.exprs[id]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Type {
+    pub name: Ident,
+    pub generics: Vec<Result<Spanned<Type>, Error>>,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Let {
+        var_name: Ident,
+        value: ExprId,
+    },
+    Assignment {
+        var_name: Ident,
+        value: ExprId,
+    },
+    IfStmt {
+        condition: ExprId,
+        body: Vec<StmtId>,
+        else_stmt: Option<StmtId>,
+    },
+    Block {
+        stmts: Vec<StmtId>,
+    },
+    Expr {
+        expr: ExprId,
+    },
+    Return {
+        value: Option<ExprId>,
+    },
+
+    #[allow(dead_code)]
+    Error(Error),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Lit),
+    Ident(Ident),
+    FnCall {
+        fn_name: Ident,
+        args: Vec<ExprId>,
+    },
+    PrefixOp {
+        op: TokenKind,
+        expr: ExprId,
+    },
+    InfixOp {
+        op: TokenKind,
+        lhs: ExprId,
+        rhs: ExprId,
+    },
+    PostfixOp {
+        op: TokenKind,
+        expr: ExprId,
+    },
+    /// `expr.member`.
+    Member {
+        expr: ExprId,
+        member: Ident,
+    },
+    /// `Point { x: 1, y: 2 }`, with the members in the order they are
+    /// written.
+    StructLit {
+        struct_name: Ident,
+        fields: Vec<(Ident, ExprId)>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Lit {
+    Int(usize),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+}
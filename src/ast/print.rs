@@ -0,0 +1,767 @@
+//! Printing the AST, as fir source and as S-expressions.
+//!
+//! The `Display` implementations write source that parses back to the same
+//! tree, up to spans: parentheses are only written where the binding powers
+//! of the parser would otherwise group an expression differently. A node
+//! that failed to parse is written as `<error>`, which does not parse.
+//! Statements and expressions are written as part of the function they are
+//! in, as they need its body; `expr_source` writes a single expression.
+//!
+//! `sexp` writes a file as S-expressions, which show the structure of the
+//! tree directly, for debugging the parser.
+
+use std::fmt::{self, Write};
+
+use crate::{cst::parser::Operator, error::Error, lexer::TokenKind};
+
+use super::{Attr, AttrArg, Body, Doc, Expr, ExprId, Item, Lit, Spanned, Stmt, StmtId, Type};
+
+const INDENT: &str = "    ";
+
+/// Where an expression is printed: the binding power it is parsed with, and
+/// the left binding power of the operator after it, or 0 if there is none.
+#[derive(Clone, Copy)]
+struct Context {
+    min: u8,
+    right: u8,
+}
+
+const TOP: Context = Context { min: 0, right: 0 };
+
+fn node<T: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    node: &Result<Spanned<T>, Error>,
+) -> fmt::Result {
+    match node {
+        Ok(node) => write!(f, "{}", node),
+        Err(_) => write!(f, "<error>"),
+    }
+}
+
+fn typed(
+    f: &mut fmt::Formatter<'_>,
+    (name, ty): &(super::Ident, Result<Spanned<Type>, Error>),
+) -> fmt::Result {
+    write!(f, "{}: ", name)?;
+    node(f, ty)
+}
+
+/// Writes `doc` as `///` comments, each on a line of its own at `indent`.
+fn doc(f: &mut fmt::Formatter<'_>, doc: &Doc, indent: usize) -> fmt::Result {
+    for line in doc.iter().flat_map(|doc| doc.lines()) {
+        let space = if line.is_empty() { "" } else { " " };
+        writeln!(f, "{}///{}{}", INDENT.repeat(indent), space, line)?;
+    }
+    Ok(())
+}
+
+/// Writes `attrs`, each on a line of its own at `indent`.
+fn attrs(f: &mut fmt::Formatter<'_>, attrs: &[Spanned<Attr>], indent: usize) -> fmt::Result {
+    for attr in attrs {
+        writeln!(f, "{}{}", INDENT.repeat(indent), attr)?;
+    }
+    Ok(())
+}
+
+fn block(f: &mut fmt::Formatter<'_>, body: &Body, stmts: &[StmtId], indent: usize) -> fmt::Result {
+    if stmts.is_empty() {
+        return write!(f, "{{}}");
+    }
+    writeln!(f, "{{")?;
+    for stmt in stmts {
+        attrs(f, body.attrs(*stmt), indent + 1)?;
+        write!(f, "{}", INDENT.repeat(indent + 1))?;
+        self::stmt(f, body, *stmt, indent + 1)?;
+        writeln!(f)?;
+    }
+    write!(f, "{}}}", INDENT.repeat(indent))
+}
+
+/// Writes `stmt`, whose first line is already indented by `indent`.
+fn stmt(f: &mut fmt::Formatter<'_>, body: &Body, stmt: StmtId, indent: usize) -> fmt::Result {
+    let stmt = match &body[stmt] {
+        Ok(stmt) => &stmt.node,
+        Err(_) => return write!(f, "<error>"),
+    };
+    match stmt {
+        Stmt::Let { var_name, value } => {
+            write!(f, "let {} = ", var_name)?;
+            expr(f, body, *value, TOP)?;
+            write!(f, ";")
+        }
+        Stmt::Assignment { var_name, value } => {
+            write!(f, "{} = ", var_name)?;
+            expr(f, body, *value, TOP)?;
+            write!(f, ";")
+        }
+        Stmt::IfStmt {
+            condition,
+            body: then,
+            else_stmt,
+        } => {
+            write!(f, "if (")?;
+            expr(f, body, *condition, TOP)?;
+            write!(f, ") ")?;
+            block(f, body, then, indent)?;
+            if let Some(else_stmt) = else_stmt {
+                write!(f, " else ")?;
+                self::stmt(f, body, *else_stmt, indent)?;
+            }
+            Ok(())
+        }
+        Stmt::Block { stmts } => block(f, body, stmts, indent),
+        Stmt::Expr { expr } => {
+            self::expr(f, body, *expr, TOP)?;
+            write!(f, ";")
+        }
+        Stmt::Return { value: None } => write!(f, "return;"),
+        Stmt::Return { value: Some(value) } => {
+            write!(f, "return ")?;
+            expr(f, body, *value, TOP)?;
+            write!(f, ";")
+        }
+        Stmt::Error(_) => write!(f, "<error>"),
+    }
+}
+
+fn expr(f: &mut fmt::Formatter<'_>, body: &Body, expr: ExprId, context: Context) -> fmt::Result {
+    let expr = match &body[expr] {
+        Ok(expr) => &expr.node,
+        Err(_) => return write!(f, "<error>"),
+    };
+    match expr {
+        Expr::Literal(lit) => write!(f, "{}", lit),
+        Expr::Ident(name) => write!(f, "{}", name),
+        Expr::FnCall { fn_name, args } => {
+            write!(f, "{}(", fn_name)?;
+            for (index, arg) in args.iter().enumerate() {
+                if index > 0 {
+                    write!(f, ", ")?;
+                }
+                self::expr(f, body, *arg, TOP)?;
+            }
+            write!(f, ")")
+        }
+        Expr::PrefixOp { op, expr } => {
+            let ((), right_binding_power) = op.prefix_binding_power();
+            // The operand would take the operator after it.
+            let parens = right_binding_power <= context.right;
+            let context = if parens { TOP } else { context };
+            open(f, parens)?;
+            write!(f, "{}", op)?;
+            self::expr(
+                f,
+                body,
+                *expr,
+                Context {
+                    min: right_binding_power,
+                    right: context.right,
+                },
+            )?;
+            close(f, parens)
+        }
+        Expr::InfixOp { op, lhs, rhs } => {
+            let (left_binding_power, right_binding_power) = op.infix_binding_power().unwrap();
+            let parens = left_binding_power < context.min || right_binding_power <= context.right;
+            let context = if parens { TOP } else { context };
+            open(f, parens)?;
+            self::expr(
+                f,
+                body,
+                *lhs,
+                Context {
+                    min: context.min,
+                    right: left_binding_power,
+                },
+            )?;
+            write!(f, " {} ", op)?;
+            self::expr(
+                f,
+                body,
+                *rhs,
+                Context {
+                    min: right_binding_power,
+                    right: context.right,
+                },
+            )?;
+            close(f, parens)
+        }
+        Expr::PostfixOp { op, expr } => {
+            let (left_binding_power, ()) = op.postfix_binding_power().unwrap();
+            let parens = left_binding_power < context.min;
+            let context = if parens { TOP } else { context };
+            open(f, parens)?;
+            self::expr(
+                f,
+                body,
+                *expr,
+                Context {
+                    min: context.min,
+                    right: left_binding_power,
+                },
+            )?;
+            write!(f, "{}", op)?;
+            close(f, parens)
+        }
+        Expr::Member { expr, member } => {
+            let (left_binding_power, ()) = TokenKind::Dot.postfix_binding_power().unwrap();
+            self::expr(
+                f,
+                body,
+                *expr,
+                Context {
+                    min: context.min,
+                    right: left_binding_power,
+                },
+            )?;
+            write!(f, ".{}", member)
+        }
+        Expr::StructLit {
+            struct_name,
+            fields,
+        } => {
+            if fields.is_empty() {
+                return write!(f, "{} {{}}", struct_name);
+            }
+            write!(f, "{} {{ ", struct_name)?;
+            for (index, (member, value)) in fields.iter().enumerate() {
+                if index > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}: ", member)?;
+                self::expr(f, body, *value, TOP)?;
+            }
+            write!(f, " }}")
+        }
+    }
+}
+
+fn open(f: &mut fmt::Formatter<'_>, parens: bool) -> fmt::Result {
+    if parens {
+        write!(f, "(")?;
+    }
+    Ok(())
+}
+
+fn close(f: &mut fmt::Formatter<'_>, parens: bool) -> fmt::Result {
+    if parens {
+        write!(f, ")")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Item::Struct {
+                doc: item_doc,
+                attrs: item_attrs,
+                name,
+                members,
+            } => {
+                doc(f, item_doc, 0)?;
+                attrs(f, item_attrs, 0)?;
+                write!(f, "struct ")?;
+                node(f, name)?;
+                writeln!(f, " {{")?;
+                for member in members {
+                    doc(f, &member.doc, 1)?;
+                    attrs(f, &member.attrs, 1)?;
+                    write!(f, "{}{}: ", INDENT, member.name)?;
+                    node(f, &member.ty)?;
+                    writeln!(f, ",")?;
+                }
+                write!(f, "}}")
+            }
+            Item::Function {
+                doc: item_doc,
+                attrs: item_attrs,
+                name,
+                parameters,
+                return_type,
+                body,
+            } => {
+                doc(f, item_doc, 0)?;
+                attrs(f, item_attrs, 0)?;
+                write!(f, "fn {}(", name)?;
+                for (index, parameter) in parameters.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    typed(f, parameter)?;
+                }
+                write!(f, ") ")?;
+                if let Some(return_type) = return_type {
+                    write!(f, "-> ")?;
+                    node(f, return_type)?;
+                    write!(f, " ")?;
+                }
+                block(f, body, &body.block, 0)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Attr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#[{}", self.name)?;
+        if !self.args.is_empty() {
+            write!(f, "(")?;
+            for (index, arg) in self.args.iter().enumerate() {
+                if index > 0 {
+                    write!(f, ", ")?;
+                }
+                match &arg.node {
+                    AttrArg::Name(name) => write!(f, "{}", name)?,
+                    AttrArg::Str(string) => write!(f, "\"{}\"", string)?,
+                }
+            }
+            write!(f, ")")?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.generics.is_empty() {
+            write!(f, "<")?;
+            for (index, generic) in self.generics.iter().enumerate() {
+                if index > 0 {
+                    write!(f, ", ")?;
+                }
+                node(f, generic)?;
+            }
+            write!(f, ">")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Lit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lit::Int(int) => write!(f, "{}", int),
+            // `Debug` keeps the `.0` of whole numbers, which would be
+            // integers otherwise.
+            Lit::Float(float) => write!(f, "{:?}", float),
+            // The text between the quotes is kept as it is in the source.
+            Lit::Str(string) => write!(f, "\"{}\"", string),
+            Lit::Bool(bool) => write!(f, "{}", bool),
+        }
+    }
+}
+
+/// The expression `id` of `body` as source, e.g. to show it in a message.
+pub fn expr_source(body: &Body, id: ExprId) -> String {
+    struct Source<'a>(&'a Body, ExprId);
+
+    impl fmt::Display for Source<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            expr(f, self.0, self.1, TOP)
+        }
+    }
+
+    Source(body, id).to_string()
+}
+
+/// The items of a file as source, separated by blank lines.
+pub fn source(items: &[Result<Spanned<Item>, Error>]) -> String {
+    let items: Vec<String> = items
+        .iter()
+        .map(|item| match item {
+            Ok(item) => item.to_string(),
+            Err(_) => "<error>".to_string(),
+        })
+        .collect();
+    let mut source = items.join("\n\n");
+    source.push('\n');
+    source
+}
+
+/// The items of a file as S-expressions, one per line, with the statements
+/// of functions on lines of their own.
+///
+/// Operators come first, as in `(+ a b)`, except for postfix ones, which
+/// come last, as in `(a !)`. Calls are `(call f a b)`, a missing return
+/// type is `()`, attributes are written as in source before the node they
+/// are on, as in `#[test] (fn f () ())`, and a node that failed to parse is
+/// `(error E001)`.
+pub fn sexp(items: &[Result<Spanned<Item>, Error>]) -> String {
+    let mut out = String::new();
+    for item in items {
+        sexp_item(&mut out, item);
+        out.push('\n');
+    }
+    out
+}
+
+fn sexp_error(out: &mut String, err: &Error) {
+    write!(out, "(error {})", err.code().code).unwrap();
+}
+
+fn sexp_attrs(out: &mut String, attrs: &[Spanned<Attr>]) {
+    for attr in attrs {
+        write!(out, "{} ", attr).unwrap();
+    }
+}
+
+fn sexp_item(out: &mut String, item: &Result<Spanned<Item>, Error>) {
+    let item = match item {
+        Ok(item) => &item.node,
+        Err(err) => return sexp_error(out, err),
+    };
+    sexp_attrs(out, item.attrs());
+    match item {
+        Item::Struct { name, members, .. } => {
+            out.push_str("(struct ");
+            sexp_type(out, name);
+            for member in members {
+                out.push(' ');
+                sexp_attrs(out, &member.attrs);
+                write!(out, "({} ", member.name).unwrap();
+                sexp_type(out, &member.ty);
+                out.push(')');
+            }
+            out.push(')');
+        }
+        Item::Function {
+            name,
+            parameters,
+            return_type,
+            body,
+            ..
+        } => {
+            write!(out, "(fn {} (", name).unwrap();
+            for (index, (parameter, ty)) in parameters.iter().enumerate() {
+                if index > 0 {
+                    out.push(' ');
+                }
+                write!(out, "({} ", parameter).unwrap();
+                sexp_type(out, ty);
+                out.push(')');
+            }
+            out.push_str(") ");
+            match return_type {
+                Some(return_type) => sexp_type(out, return_type),
+                None => out.push_str("()"),
+            }
+            sexp_stmts(out, body, &body.block, 1);
+            out.push(')');
+        }
+    }
+}
+
+fn sexp_type(out: &mut String, ty: &Result<Spanned<Type>, Error>) {
+    let ty = match ty {
+        Ok(ty) => &ty.node,
+        Err(err) => return sexp_error(out, err),
+    };
+    if ty.generics.is_empty() {
+        out.push_str(&ty.name);
+        return;
+    }
+    write!(out, "({}", ty.name).unwrap();
+    for generic in &ty.generics {
+        out.push(' ');
+        sexp_type(out, generic);
+    }
+    out.push(')');
+}
+
+/// Writes each statement on a new line, indented by `indent`.
+fn sexp_stmts(out: &mut String, body: &Body, stmts: &[StmtId], indent: usize) {
+    for stmt in stmts {
+        out.push('\n');
+        out.push_str(&"  ".repeat(indent));
+        sexp_attrs(out, body.attrs(*stmt));
+        sexp_stmt(out, body, *stmt, indent);
+    }
+}
+
+fn sexp_stmt(out: &mut String, body: &Body, stmt: StmtId, indent: usize) {
+    let stmt = match &body[stmt] {
+        Ok(stmt) => &stmt.node,
+        Err(err) => return sexp_error(out, err),
+    };
+    match stmt {
+        Stmt::Let { var_name, value } => {
+            write!(out, "(let {} ", var_name).unwrap();
+            sexp_expr(out, body, *value);
+            out.push(')');
+        }
+        Stmt::Assignment { var_name, value } => {
+            write!(out, "(set {} ", var_name).unwrap();
+            sexp_expr(out, body, *value);
+            out.push(')');
+        }
+        Stmt::IfStmt {
+            condition,
+            body: then,
+            else_stmt,
+        } => {
+            out.push_str("(if ");
+            sexp_expr(out, body, *condition);
+            out.push_str(&format!("\n{}(then", "  ".repeat(indent + 1)));
+            sexp_stmts(out, body, then, indent + 2);
+            out.push(')');
+            if let Some(else_stmt) = else_stmt {
+                out.push_str(&format!("\n{}(else ", "  ".repeat(indent + 1)));
+                sexp_stmt(out, body, *else_stmt, indent + 1);
+                out.push(')');
+            }
+            out.push(')');
+        }
+        Stmt::Block { stmts } => {
+            out.push_str("(block");
+            sexp_stmts(out, body, stmts, indent + 1);
+            out.push(')');
+        }
+        Stmt::Expr { expr } => sexp_expr(out, body, *expr),
+        Stmt::Return { value: None } => out.push_str("(return)"),
+        Stmt::Return { value: Some(value) } => {
+            out.push_str("(return ");
+            sexp_expr(out, body, *value);
+            out.push(')');
+        }
+        Stmt::Error(err) => sexp_error(out, err),
+    }
+}
+
+fn sexp_expr(out: &mut String, body: &Body, expr: ExprId) {
+    let expr = match &body[expr] {
+        Ok(expr) => &expr.node,
+        Err(err) => return sexp_error(out, err),
+    };
+    match expr {
+        Expr::Literal(lit) => write!(out, "{}", lit).unwrap(),
+        Expr::Ident(name) => out.push_str(name),
+        Expr::FnCall { fn_name, args } => {
+            write!(out, "(call {}", fn_name).unwrap();
+            for arg in args {
+                out.push(' ');
+                sexp_expr(out, body, *arg);
+            }
+            out.push(')');
+        }
+        Expr::PrefixOp { op, expr } => {
+            write!(out, "({} ", op).unwrap();
+            sexp_expr(out, body, *expr);
+            out.push(')');
+        }
+        Expr::InfixOp { op, lhs, rhs } => {
+            write!(out, "({} ", op).unwrap();
+            sexp_expr(out, body, *lhs);
+            out.push(' ');
+            sexp_expr(out, body, *rhs);
+            out.push(')');
+        }
+        Expr::PostfixOp { op, expr } => {
+            out.push('(');
+            sexp_expr(out, body, *expr);
+            write!(out, " {})", op).unwrap();
+        }
+        Expr::Member { expr, member } => {
+            out.push_str("(. ");
+            sexp_expr(out, body, *expr);
+            write!(out, " {})", member).unwrap();
+        }
+        Expr::StructLit {
+            struct_name,
+            fields,
+        } => {
+            write!(out, "(struct {}", struct_name).unwrap();
+            for (member, value) in fields {
+                write!(out, " ({} ", member).unwrap();
+                sexp_expr(out, body, *value);
+                out.push(')');
+            }
+            out.push(')');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{ast::json, cst};
+
+    /// The AST of `text` as JSON without its spans, which is all of it but
+    /// where it came from.
+    fn tree(text: &str) -> Value {
+        fn strip(value: &mut Value) {
+            match value {
+                Value::Object(fields) => {
+                    fields.remove("span");
+                    fields.values_mut().for_each(strip);
+                }
+                Value::Array(values) => values.iter_mut().for_each(strip),
+                _ => {}
+            }
+        }
+        let items = cst::lower::items(&cst::parse(text));
+        let mut value = serde_json::from_str(&json::write(&items)).unwrap();
+        strip(&mut value);
+        value
+    }
+
+    /// Printing the AST of `text` gives source that parses to it again.
+    fn round_trip(text: &str) {
+        let printed = source(&cst::lower::items(&cst::parse(text)));
+        assert!(!printed.contains("<error>"), "{} does not parse", text);
+        assert_eq!(
+            tree(&printed),
+            tree(text),
+            "{}\nprinted as\n{}",
+            text,
+            printed
+        );
+    }
+
+    fn body(stmts: &str) -> String {
+        format!("fn main() {{\n{}\n}}\n", stmts)
+    }
+
+    #[test]
+    fn associativity() {
+        round_trip(&body("print(2 ^ 3 ^ 2);"));
+        round_trip(&body("print((2 ^ 3) ^ 2);"));
+        round_trip(&body("print(1 - (2 - 3));"));
+        round_trip(&body("print(1 - 2 - 3);"));
+        assert_ne!(
+            tree(&body("print(1 - (2 - 3));")),
+            tree(&body("print(1 - 2 - 3);"))
+        );
+        round_trip(&body("print(-(1 + 2) * 3, !(true || false), -2 ^ 2);"));
+        round_trip(&body("print((1 + 2)!, 1 + 2!, 1 < 2 == true);"));
+    }
+
+    #[test]
+    fn string_escapes() {
+        round_trip(&body(
+            r#"print("a \"quoted\" word", "back \\ slash", "\\\"");"#,
+        ));
+    }
+
+    #[test]
+    fn literals() {
+        round_trip(&body(
+            "print(0, 2147483647, 1.5, 0.25, 1e3, 2.0, true, false);",
+        ));
+    }
+
+    #[test]
+    fn structs_and_members() {
+        round_trip(&body(
+            "let p = Point { x: 1 + 2, y: -p.y };\n    let e = Empty {};\n    print(p.x, (-p).x, f(p).y, Point { x: 1, y: 2 }.x);",
+        ));
+        // Without a `name:` after it, a `{` after a name starts a block.
+        round_trip(&body("let a = b\n    {\n        print(a);\n    }"));
+        assert_eq!(
+            sexp(&cst::lower::items(&cst::parse(&body(
+                "print(Point { x: 1, y: p.y });"
+            )))),
+            "(fn main () ()\n  (call print (struct Point (x 1) (y (. p y)))))\n"
+        );
+    }
+
+    #[test]
+    fn doc_comments_and_attributes() {
+        round_trip(
+            "/// A point.
+/// On a plane.
+#[derive(Eq, Print)]
+struct Point {
+    /// Across.
+    #[deprecated(\"Use `y`\")]
+    x: int,
+    y: int,
+}
+
+/// Adds.
+#[inline(always)]
+#[deprecated]
+fn add(a: int, b: int) -> int {
+    #[allow(unused_variables, shadowing)]
+    let c = a;
+    return a + b;
+}
+
+#[test]
+fn adds() {
+    assert_eq(add(1, 2), 3);
+}
+",
+        );
+    }
+
+    /// A xorshift generator, so the programs are the same on every run.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+
+        fn pick<'a>(&mut self, choices: &[&'a str]) -> &'a str {
+            choices[self.below(choices.len())]
+        }
+    }
+
+    fn random_expr(rng: &mut Rng, depth: usize) -> String {
+        let leaf = depth == 0 || rng.below(3) == 0;
+        match if leaf { rng.below(4) } else { 4 + rng.below(5) } {
+            0 => rng.below(100).to_string(),
+            1 => rng.pick(&["a", "b", "true", "false", "1.5"]).to_string(),
+            2 => rng.pick(&[r#""s""#, r#""\"q\"""#, r#""\\""#]).to_string(),
+            3 => "f()".to_string(),
+            4 | 5 => {
+                let op = rng.pick(&["+", "-", "*", "/", "^", "==", "!=", "<", ">=", "&&", "||"]);
+                let lhs = random_expr(rng, depth - 1);
+                let rhs = random_expr(rng, depth - 1);
+                format!("{} {} {}", lhs, op, rhs)
+            }
+            6 => format!("({})", random_expr(rng, depth - 1)),
+            7 => format!("{}{}", rng.pick(&["-", "!"]), random_expr(rng, depth - 1)),
+            _ => {
+                let args = (0..rng.below(3))
+                    .map(|_| random_expr(rng, depth - 1))
+                    .collect::<Vec<_>>();
+                format!("g({})", args.join(", "))
+            }
+        }
+    }
+
+    fn random_stmt(rng: &mut Rng, depth: usize) -> String {
+        match rng.below(if depth == 0 { 4 } else { 6 }) {
+            0 => format!("let x = {};", random_expr(rng, 3)),
+            1 => format!("a = {};", random_expr(rng, 3)),
+            2 => format!("print({});", random_expr(rng, 3)),
+            3 => format!("return {};", random_expr(rng, 3)),
+            4 => format!(
+                "if ({}) {{ {} }} else {{ {} }}",
+                random_expr(rng, 2),
+                random_stmt(rng, depth - 1),
+                random_stmt(rng, depth - 1)
+            ),
+            _ => format!("{{ {} }}", random_stmt(rng, depth - 1)),
+        }
+    }
+
+    #[test]
+    fn random_programs() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..500 {
+            let stmts: Vec<_> = (0..1 + rng.below(4))
+                .map(|_| random_stmt(&mut rng, 2))
+                .collect();
+            round_trip(&body(&stmts.join("\n")));
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! Interned strings, for identifiers and type names.
+//!
+//! A `Symbol` is the index of a string in a table of every string interned
+//! so far, so it is as cheap to copy, compare and hash as an integer, and a
+//! name is stored once however often it occurs. Interned strings are never
+//! freed; the table lives as long as the compiler does.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+#[derive(Default)]
+struct Interner {
+    symbols: HashMap<&'static str, Symbol>,
+    strings: Vec<&'static str>,
+}
+
+fn interner() -> MutexGuard<'static, Interner> {
+    static INTERNER: OnceLock<Mutex<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default).lock().unwrap()
+}
+
+impl Symbol {
+    pub fn intern(string: &str) -> Symbol {
+        let mut interner = interner();
+        if let Some(symbol) = interner.symbols.get(string) {
+            return *symbol;
+        }
+        let string: &'static str = Box::leak(string.into());
+        let symbol = Symbol(interner.strings.len() as u32);
+        interner.strings.push(string);
+        interner.symbols.insert(string, symbol);
+        symbol
+    }
+
+    pub fn as_str(self) -> &'static str {
+        interner().strings[self.0 as usize]
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Symbol, D::Error> {
+        Ok(Symbol::intern(&String::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal_strings_are_one_symbol() {
+        let point = Symbol::intern("point");
+        assert_eq!(Symbol::intern("point"), point);
+        assert_eq!(Symbol::intern(&["poi", "nt"].concat()), point);
+        assert_ne!(Symbol::intern("Point"), point);
+        assert_eq!(point.as_str(), "point");
+        assert!(std::ptr::eq(
+            point.as_str(),
+            Symbol::intern("point").as_str()
+        ));
+    }
+
+    #[test]
+    fn serializes_as_its_string() {
+        let symbol = Symbol::intern("main");
+        let json = serde_json::to_string(&symbol).unwrap();
+        assert_eq!(json, "\"main\"");
+        assert_eq!(serde_json::from_str::<Symbol>(&json).unwrap(), symbol);
+    }
+}
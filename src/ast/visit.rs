@@ -0,0 +1,146 @@
+//! Walking the AST by reference.
+//!
+//! A pass implements `Visitor` and overrides the methods for the nodes it
+//! cares about. The `walk_*` functions visit the children of a node, which
+//! the default methods do, so an override calls them to keep going below
+//! its node. Statements and expressions come with the body they are in and
+//! their id. Children that failed to parse go to `visit_error`.
+
+use crate::error::Error;
+
+use super::{Body, Expr, ExprId, Item, Spanned, Stmt, StmtId, Type};
+
+pub trait Visitor: Sized {
+    fn visit_item(&mut self, item: &Spanned<Item>) {
+        walk_item(self, item)
+    }
+
+    fn visit_type(&mut self, ty: &Spanned<Type>) {
+        walk_type(self, ty)
+    }
+
+    /// The statements of a function body, an `if` or a block, which make a
+    /// scope.
+    fn visit_block(&mut self, body: &Body, stmts: &[StmtId]) {
+        walk_block(self, body, stmts)
+    }
+
+    fn visit_stmt(&mut self, body: &Body, _id: StmtId, stmt: &Spanned<Stmt>) {
+        walk_stmt(self, body, stmt)
+    }
+
+    fn visit_expr(&mut self, body: &Body, _id: ExprId, expr: &Spanned<Expr>) {
+        walk_expr(self, body, expr)
+    }
+
+    fn visit_error(&mut self, _err: &Error) {}
+}
+
+fn visit_type<V: Visitor>(visitor: &mut V, ty: &Result<Spanned<Type>, Error>) {
+    match ty {
+        Ok(ty) => visitor.visit_type(ty),
+        Err(err) => visitor.visit_error(err),
+    }
+}
+
+fn visit_stmt<V: Visitor>(visitor: &mut V, body: &Body, id: StmtId) {
+    match &body[id] {
+        Ok(stmt) => visitor.visit_stmt(body, id, stmt),
+        Err(err) => visitor.visit_error(err),
+    }
+}
+
+fn visit_expr<V: Visitor>(visitor: &mut V, body: &Body, id: ExprId) {
+    match &body[id] {
+        Ok(expr) => visitor.visit_expr(body, id, expr),
+        Err(err) => visitor.visit_error(err),
+    }
+}
+
+pub fn walk_item<V: Visitor>(visitor: &mut V, item: &Spanned<Item>) {
+    match &item.node {
+        Item::Struct { name, members, .. } => {
+            visit_type(visitor, name);
+            for member in members {
+                visit_type(visitor, &member.ty);
+            }
+        }
+        Item::Function {
+            parameters,
+            return_type,
+            body,
+            ..
+        } => {
+            for (_, ty) in parameters {
+                visit_type(visitor, ty);
+            }
+            if let Some(return_type) = return_type {
+                visit_type(visitor, return_type);
+            }
+            visitor.visit_block(body, &body.block);
+        }
+    }
+}
+
+pub fn walk_type<V: Visitor>(visitor: &mut V, ty: &Spanned<Type>) {
+    for generic in &ty.generics {
+        visit_type(visitor, generic);
+    }
+}
+
+pub fn walk_block<V: Visitor>(visitor: &mut V, body: &Body, stmts: &[StmtId]) {
+    for stmt in stmts {
+        visit_stmt(visitor, body, *stmt);
+    }
+}
+
+pub fn walk_stmt<V: Visitor>(visitor: &mut V, body: &Body, stmt: &Spanned<Stmt>) {
+    match &stmt.node {
+        Stmt::Let { value, .. } | Stmt::Assignment { value, .. } => {
+            visit_expr(visitor, body, *value)
+        }
+        Stmt::IfStmt {
+            condition,
+            body: then,
+            else_stmt,
+        } => {
+            visit_expr(visitor, body, *condition);
+            visitor.visit_block(body, then);
+            if let Some(else_stmt) = else_stmt {
+                visit_stmt(visitor, body, *else_stmt);
+            }
+        }
+        Stmt::Block { stmts } => visitor.visit_block(body, stmts),
+        Stmt::Expr { expr } => visit_expr(visitor, body, *expr),
+        Stmt::Return { value } => {
+            if let Some(value) = value {
+                visit_expr(visitor, body, *value);
+            }
+        }
+        Stmt::Error(err) => visitor.visit_error(err),
+    }
+}
+
+pub fn walk_expr<V: Visitor>(visitor: &mut V, body: &Body, expr: &Spanned<Expr>) {
+    match &expr.node {
+        Expr::Literal(_) | Expr::Ident(_) => {}
+        Expr::FnCall { args, .. } => {
+            for arg in args {
+                visit_expr(visitor, body, *arg);
+            }
+        }
+        Expr::PrefixOp { expr, .. } | Expr::PostfixOp { expr, .. } => {
+            visit_expr(visitor, body, *expr)
+        }
+        Expr::InfixOp { lhs, rhs, .. } => {
+            visit_expr(visitor, body, *lhs);
+            visit_expr(visitor, body, *rhs);
+        }
+        Expr::Member { expr, .. } => visit_expr(visitor, body, *expr),
+        Expr::StructLit { fields, .. } => {
+            for (_, value) in fields {
+                visit_expr(visitor, body, *value);
+            }
+        }
+    }
+}
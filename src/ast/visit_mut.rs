@@ -0,0 +1,245 @@
+//! Walking the AST by mutable reference, to change it in place.
+//!
+//! `VisitorMut` is `Visitor` for passes that edit the nodes they visit,
+//! with the same methods and `walk_*` functions. Statements and expressions
+//! are visited by id, with the body they are in, so a visitor can replace
+//! them or add new nodes to the body. `visit_stmt` and `visit_expr` are only
+//! called for nodes that parsed; the others go to `visit_error`.
+
+use std::mem;
+
+use crate::error::Error;
+
+use super::{Body, Expr, ExprId, Item, Spanned, Stmt, StmtId, Type};
+
+pub trait VisitorMut: Sized {
+    fn visit_item(&mut self, item: &mut Spanned<Item>) {
+        walk_item(self, item)
+    }
+
+    fn visit_type(&mut self, ty: &mut Spanned<Type>) {
+        walk_type(self, ty)
+    }
+
+    /// The statements of a function body, an `if` or a block, which make a
+    /// scope. They are taken out of their node while they are visited.
+    fn visit_block(&mut self, body: &mut Body, stmts: &mut Vec<StmtId>) {
+        walk_block(self, body, stmts)
+    }
+
+    fn visit_stmt(&mut self, body: &mut Body, id: StmtId) {
+        walk_stmt(self, body, id)
+    }
+
+    fn visit_expr(&mut self, body: &mut Body, id: ExprId) {
+        walk_expr(self, body, id)
+    }
+
+    fn visit_error(&mut self, _err: &mut Error) {}
+}
+
+fn visit_type<V: VisitorMut>(visitor: &mut V, ty: &mut Result<Spanned<Type>, Error>) {
+    match ty {
+        Ok(ty) => visitor.visit_type(ty),
+        Err(err) => visitor.visit_error(err),
+    }
+}
+
+fn visit_stmt<V: VisitorMut>(visitor: &mut V, body: &mut Body, id: StmtId) {
+    match &mut body.stmts[id] {
+        Ok(_) => visitor.visit_stmt(body, id),
+        Err(err) => visitor.visit_error(err),
+    }
+}
+
+fn visit_expr<V: VisitorMut>(visitor: &mut V, body: &mut Body, id: ExprId) {
+    match &mut body.exprs[id] {
+        Ok(_) => visitor.visit_expr(body, id),
+        Err(err) => visitor.visit_error(err),
+    }
+}
+
+pub fn walk_item<V: VisitorMut>(visitor: &mut V, item: &mut Spanned<Item>) {
+    match &mut item.node {
+        Item::Struct { name, members, .. } => {
+            visit_type(visitor, name);
+            for member in members {
+                visit_type(visitor, &mut member.ty);
+            }
+        }
+        Item::Function {
+            parameters,
+            return_type,
+            body,
+            ..
+        } => {
+            for (_, ty) in parameters {
+                visit_type(visitor, ty);
+            }
+            if let Some(return_type) = return_type {
+                visit_type(visitor, return_type);
+            }
+            let mut block = mem::take(&mut body.block);
+            visitor.visit_block(body, &mut block);
+            body.block = block;
+        }
+    }
+}
+
+pub fn walk_type<V: VisitorMut>(visitor: &mut V, ty: &mut Spanned<Type>) {
+    for generic in &mut ty.node.generics {
+        visit_type(visitor, generic);
+    }
+}
+
+pub fn walk_block<V: VisitorMut>(visitor: &mut V, body: &mut Body, stmts: &mut Vec<StmtId>) {
+    for stmt in stmts {
+        visit_stmt(visitor, body, *stmt);
+    }
+}
+
+/// The statements of the block `block` picks out of statement `id`, taken
+/// out of it while they are visited.
+fn visit_nested_block<V: VisitorMut>(
+    visitor: &mut V,
+    body: &mut Body,
+    id: StmtId,
+    block: fn(&mut Stmt) -> &mut Vec<StmtId>,
+) {
+    let mut stmts = match &mut body.stmts[id] {
+        Ok(stmt) => mem::take(block(&mut stmt.node)),
+        Err(_) => return,
+    };
+    visitor.visit_block(body, &mut stmts);
+    if let Ok(stmt) = &mut body.stmts[id] {
+        *block(&mut stmt.node) = stmts;
+    }
+}
+
+pub fn walk_stmt<V: VisitorMut>(visitor: &mut V, body: &mut Body, id: StmtId) {
+    let stmt = match &mut body.stmts[id] {
+        Ok(stmt) => &mut stmt.node,
+        Err(err) => return visitor.visit_error(err),
+    };
+    match stmt {
+        Stmt::Let { value, .. } | Stmt::Assignment { value, .. } => {
+            let value = *value;
+            visit_expr(visitor, body, value);
+        }
+        Stmt::IfStmt {
+            condition,
+            else_stmt,
+            ..
+        } => {
+            let (condition, else_stmt) = (*condition, *else_stmt);
+            visit_expr(visitor, body, condition);
+            visit_nested_block(visitor, body, id, |stmt| match stmt {
+                Stmt::IfStmt { body, .. } => body,
+                _ => unreachable!(),
+            });
+            if let Some(else_stmt) = else_stmt {
+                visit_stmt(visitor, body, else_stmt);
+            }
+        }
+        Stmt::Block { .. } => visit_nested_block(visitor, body, id, |stmt| match stmt {
+            Stmt::Block { stmts } => stmts,
+            _ => unreachable!(),
+        }),
+        Stmt::Expr { expr } => {
+            let expr = *expr;
+            visit_expr(visitor, body, expr);
+        }
+        Stmt::Return { value } => {
+            if let Some(value) = *value {
+                visit_expr(visitor, body, value);
+            }
+        }
+        Stmt::Error(err) => visitor.visit_error(err),
+    }
+}
+
+pub fn walk_expr<V: VisitorMut>(visitor: &mut V, body: &mut Body, id: ExprId) {
+    let children = match &mut body.exprs[id] {
+        Ok(expr) => match &expr.node {
+            Expr::Literal(_) | Expr::Ident(_) => Vec::new(),
+            Expr::FnCall { args, .. } => args.clone(),
+            Expr::PrefixOp { expr, .. }
+            | Expr::PostfixOp { expr, .. }
+            | Expr::Member { expr, .. } => vec![*expr],
+            Expr::InfixOp { lhs, rhs, .. } => vec![*lhs, *rhs],
+            Expr::StructLit { fields, .. } => fields.iter().map(|(_, value)| *value).collect(),
+        },
+        Err(err) => return visitor.visit_error(err),
+    };
+    for child in children {
+        visit_expr(visitor, body, child);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{print, Lit, Symbol},
+        cst,
+    };
+
+    /// Renames `a` to `b` and doubles every integer.
+    struct Rename;
+
+    impl VisitorMut for Rename {
+        fn visit_stmt(&mut self, body: &mut Body, id: StmtId) {
+            if let Ok(stmt) = &mut body.stmts[id] {
+                if let Stmt::Let { var_name, .. } | Stmt::Assignment { var_name, .. } =
+                    &mut stmt.node
+                {
+                    if var_name.as_str() == "a" {
+                        var_name.node = Symbol::intern("b");
+                    }
+                }
+            }
+            walk_stmt(self, body, id)
+        }
+
+        fn visit_expr(&mut self, body: &mut Body, id: ExprId) {
+            if let Ok(expr) = &mut body.exprs[id] {
+                match &mut expr.node {
+                    Expr::Ident(name) if name.as_str() == "a" => name.node = Symbol::intern("b"),
+                    Expr::Literal(Lit::Int(int)) => *int *= 2,
+                    _ => {}
+                }
+            }
+            walk_expr(self, body, id)
+        }
+    }
+
+    #[test]
+    fn rewrites_a_program() {
+        let mut items = cst::lower::items(&cst::parse(
+            "fn main() {
+    let a = 1;
+    if a < 2 {
+        a = f(a, 3);
+    } else {
+        print(a + 4);
+    }
+}
+",
+        ));
+        for item in items.iter_mut().flatten() {
+            Rename.visit_item(item);
+        }
+        assert_eq!(
+            print::source(&items),
+            "fn main() {
+    let b = 2;
+    if (b < 4) {
+        b = f(b, 6);
+    } else {
+        print(b + 8);
+    }
+}
+"
+        );
+    }
+}
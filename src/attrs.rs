@@ -0,0 +1,214 @@
+//! Attributes: `#[name]` or `#[name(arg, ...)]` before an item, a struct
+//! member or a statement in a block.
+//!
+//! Every attribute has an entry in `ATTRS`, with what it can be put on and
+//! the arguments it takes, and `check` reports any that is unknown, out of
+//! place or given the wrong arguments. The parser takes any attribute, so
+//! that the checker reports them all in one place.
+
+use std::fmt;
+
+use logos::Span;
+
+use crate::{
+    ast::{self, Spanned},
+    error::{codes, Error},
+};
+
+/// What an attribute is put on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Function,
+    Struct,
+    Member,
+    Stmt,
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Function => write!(f, "functions"),
+            Target::Struct => write!(f, "structs"),
+            Target::Member => write!(f, "struct members"),
+            Target::Stmt => write!(f, "statements"),
+        }
+    }
+}
+
+/// The arguments an attribute takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Args {
+    None,
+    /// Nothing or one of these names.
+    OneOf(&'static [&'static str]),
+    /// One or more names, e.g. of lints.
+    Names,
+    /// One or more of these names.
+    SomeOf(&'static [&'static str]),
+    /// Nothing or a string, e.g. a note.
+    Str,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Known {
+    pub name: &'static str,
+    pub targets: &'static [Target],
+    pub args: Args,
+}
+
+const ALL: &[Target] = &[
+    Target::Function,
+    Target::Struct,
+    Target::Member,
+    Target::Stmt,
+];
+
+pub const ATTRS: &[Known] = &[
+    // A function `fir test` runs.
+    Known {
+        name: "test",
+        targets: &[Target::Function],
+        args: Args::None,
+    },
+    // A hint for the Rust backend.
+    Known {
+        name: "inline",
+        targets: &[Target::Function],
+        args: Args::OneOf(&["always", "never"]),
+    },
+    Known {
+        name: "deprecated",
+        targets: &[Target::Function, Target::Struct, Target::Member],
+        args: Args::Str,
+    },
+    // What a struct can do, see `types::Derive`.
+    Known {
+        name: "derive",
+        targets: &[Target::Struct],
+        args: Args::SomeOf(&["Eq", "Print", "Hash", "Clone"]),
+    },
+    // Turns the named lints off for what it is on, as `fir:allow` does.
+    Known {
+        name: "allow",
+        targets: ALL,
+        args: Args::Names,
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Known> {
+    ATTRS.iter().find(|known| known.name == name)
+}
+
+/// The attribute `name` among `attrs`.
+pub fn get<'a>(attrs: &'a [Spanned<ast::Attr>], name: &str) -> Option<&'a ast::Attr> {
+    attrs
+        .iter()
+        .map(|attr| &attr.node)
+        .find(|attr| attr.name.as_str() == name)
+}
+
+pub fn has(attrs: &[Spanned<ast::Attr>], name: &str) -> bool {
+    get(attrs, name).is_some()
+}
+
+/// The names `attr` is given, e.g. the lints of `#[allow(...)]`, with
+/// their spans.
+pub fn names(attr: &ast::Attr) -> impl Iterator<Item = (&ast::Symbol, &Span)> {
+    attr.args.iter().filter_map(|arg| match &arg.node {
+        ast::AttrArg::Name(name) => Some((name, &arg.span)),
+        ast::AttrArg::Str(_) => None,
+    })
+}
+
+/// Checks that `attrs` are known, can be put on `target`, are given the
+/// arguments they take and are not repeated.
+pub fn check(attrs: &[Spanned<ast::Attr>], target: Target) -> Result<(), Error> {
+    for (index, attr) in attrs.iter().enumerate() {
+        let name = &attr.name;
+        let known = find(name.as_str()).ok_or_else(|| {
+            Error::new(
+                format!("Unknown attribute `{}`", name),
+                codes::E020,
+                name.span.clone(),
+            )
+        })?;
+        if !known.targets.contains(&target) {
+            let mut targets = known.targets.iter().map(ToString::to_string);
+            let first = targets.next().unwrap();
+            let targets = targets.fold(first, |list, target| list + " and " + &target);
+            return Err(Error::new(
+                format!("`#[{}]` can not be put on {}", name, target),
+                codes::E020,
+                attr.span.clone(),
+            )
+            .with_note(format!("It can only be put on {}", targets), None));
+        }
+        if attrs[..index]
+            .iter()
+            .any(|other| other.name.as_str() == name.as_str())
+        {
+            return Err(Error::new(
+                format!("`#[{}]` is given twice", name),
+                codes::E020,
+                attr.span.clone(),
+            ));
+        }
+        args(attr, known)?;
+    }
+    Ok(())
+}
+
+fn args(attr: &Spanned<ast::Attr>, known: &Known) -> Result<(), Error> {
+    let name = &attr.name;
+    let quoted = |names: &[&str]| {
+        names
+            .iter()
+            .map(|name| format!("`{}`", name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let expected = match known.args {
+        Args::None if attr.args.is_empty() => return Ok(()),
+        Args::None => "no arguments".to_string(),
+        Args::OneOf(names) => match &attr.args[..] {
+            [] => return Ok(()),
+            [arg] if matches!(&arg.node, ast::AttrArg::Name(arg) if names.contains(&arg.as_str())) => {
+                return Ok(())
+            }
+            _ => format!("nothing or one of {}", quoted(names)),
+        },
+        Args::Names
+            if !attr.args.is_empty()
+                && attr
+                    .args
+                    .iter()
+                    .all(|arg| matches!(arg.node, ast::AttrArg::Name(_))) =>
+        {
+            return Ok(())
+        }
+        Args::Names => "one or more names".to_string(),
+        Args::SomeOf(names)
+            if !attr.args.is_empty()
+                && attr.args.iter().all(
+                    |arg| matches!(&arg.node, ast::AttrArg::Name(arg) if names.contains(&arg.as_str())),
+                ) =>
+        {
+            return Ok(())
+        }
+        Args::SomeOf(names) => format!("one or more of {}", quoted(names)),
+        Args::Str => match &attr.args[..] {
+            [] => return Ok(()),
+            [arg] if matches!(arg.node, ast::AttrArg::Str(_)) => return Ok(()),
+            _ => "nothing or a string".to_string(),
+        },
+    };
+    let span = match (attr.args.first(), attr.args.last()) {
+        (Some(first), Some(last)) => first.span.start..last.span.end,
+        _ => attr.span.clone(),
+    };
+    Err(Error::new(
+        format!("`#[{}]` takes {}", name, expected),
+        codes::E020,
+        span,
+    ))
+}
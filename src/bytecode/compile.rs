@@ -0,0 +1,232 @@
+use logos::Span;
+
+use crate::{
+    error::{codes, Error},
+    mir::{self, BinOp, Const, Operand, Rvalue, Stmt, Terminator, UnOp},
+    ownership,
+    types::{self, Ty},
+};
+
+use super::{Function, Instr, Module, Struct, Value};
+
+/// Compiles verified MIR to bytecode. Blocks are laid out in order, so jumps
+/// to the block that follows are left out.
+pub fn compile(program: &mir::Program) -> Result<Module, Error> {
+    let mut module = Module {
+        constants: Vec::new(),
+        structs: program
+            .structs
+            .iter()
+            .map(|(name, members)| Struct {
+                name: name.clone(),
+                members: members.iter().map(|(member, _)| member.clone()).collect(),
+            })
+            .collect(),
+        functions: Vec::new(),
+    };
+    for func in &program.functions {
+        let mut compiler = Compiler {
+            program,
+            constants: &mut module.constants,
+            code: Vec::new(),
+            spans: Vec::new(),
+            span: func.span.clone(),
+        };
+        compiler.function(func)?;
+        module.functions.push(Function {
+            name: func.name.clone(),
+            params: func.params as u32,
+            locals: func.locals.len() as u32,
+            returns: func.ret != Ty::Unit,
+            code: compiler.code,
+            spans: compiler.spans,
+        });
+    }
+    Ok(module)
+}
+
+struct Compiler<'a> {
+    program: &'a mir::Program,
+    constants: &'a mut Vec<Value>,
+    code: Vec<Instr>,
+    spans: Vec<Span>,
+    /// The span of the statement being compiled, or of the function for its
+    /// terminators.
+    span: Span,
+}
+
+impl<'a> Compiler<'a> {
+    fn emit(&mut self, instr: Instr) {
+        self.code.push(instr);
+        self.spans.push(self.span.clone());
+    }
+
+    fn function(&mut self, func: &mir::Function) -> Result<(), Error> {
+        let mut starts = Vec::new();
+        // Jumps are emitted with block ids and patched once every block's
+        // offset is known.
+        let mut jumps = Vec::new();
+        for (index, block) in func.blocks.iter().enumerate() {
+            starts.push(self.code.len() as u32);
+            for stmt in &block.stmts {
+                self.span = stmt.span.clone();
+                match &stmt.node {
+                    Stmt::Assign(local, rvalue) => {
+                        self.rvalue(rvalue)?;
+                        self.emit(Instr::Store(*local as u32));
+                    }
+                    Stmt::Eval(rvalue) => {
+                        if self.rvalue(rvalue)? {
+                            self.emit(Instr::Pop);
+                        }
+                    }
+                }
+            }
+            self.span = func.span.clone();
+            let next = index + 1;
+            match &block.terminator {
+                Terminator::Goto(target) if *target == next => {}
+                Terminator::Goto(target) => {
+                    jumps.push(self.code.len());
+                    self.emit(Instr::Jump(*target as u32));
+                }
+                Terminator::Branch {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    self.operand(condition);
+                    jumps.push(self.code.len());
+                    self.emit(Instr::JumpIfFalse(*otherwise as u32));
+                    if *then != next {
+                        jumps.push(self.code.len());
+                        self.emit(Instr::Jump(*then as u32));
+                    }
+                }
+                Terminator::Return(Some(value)) => {
+                    self.operand(value);
+                    self.emit(Instr::ReturnValue);
+                }
+                Terminator::Return(None) => self.emit(Instr::Return),
+                Terminator::Unreachable => self.emit(Instr::Trap),
+            }
+        }
+        for jump in jumps {
+            match &mut self.code[jump] {
+                Instr::Jump(target) | Instr::JumpIfFalse(target) => {
+                    *target = starts[*target as usize]
+                }
+                _ => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+
+    fn constant(&mut self, value: Value) -> u32 {
+        let index = match self.constants.iter().position(|other| *other == value) {
+            Some(index) => index,
+            None => {
+                self.constants.push(value);
+                self.constants.len() - 1
+            }
+        };
+        index as u32
+    }
+
+    fn operand(&mut self, operand: &Operand) {
+        let instr = match operand {
+            Operand::Local(local) => Instr::Load(*local as u32),
+            Operand::Ref(local) => Instr::Ref(*local as u32),
+            Operand::Const(constant) => {
+                let value = match constant {
+                    Const::Int(int) => Value::Int(*int as i32),
+                    Const::Float(float) => Value::Float(*float),
+                    Const::Bool(bool) => Value::Bool(*bool),
+                    Const::Str(string) => Value::Str(string.as_str().into()),
+                };
+                Instr::Const(self.constant(value))
+            }
+        };
+        self.emit(instr);
+    }
+
+    /// Pushes the code evaluating `rvalue`, and returns whether it leaves a
+    /// value on the stack.
+    fn rvalue(&mut self, rvalue: &Rvalue) -> Result<bool, Error> {
+        match rvalue {
+            Rvalue::Use(operand) => self.operand(operand),
+            Rvalue::Unary(op, operand) => {
+                self.operand(operand);
+                self.emit(match op {
+                    UnOp::Neg => Instr::Neg,
+                    UnOp::Not => Instr::Not,
+                });
+            }
+            Rvalue::Binary(op, lhs, rhs) => {
+                self.operand(lhs);
+                self.operand(rhs);
+                self.emit(match op {
+                    BinOp::Add => Instr::Add,
+                    BinOp::Sub => Instr::Sub,
+                    BinOp::Mul => Instr::Mul,
+                    BinOp::Div => Instr::Div,
+                    BinOp::Pow => Instr::Pow,
+                    BinOp::Eq => Instr::Eq,
+                    BinOp::Ne => Instr::Ne,
+                    BinOp::Lt => Instr::Lt,
+                    BinOp::Gt => Instr::Gt,
+                    BinOp::Le => Instr::Le,
+                    BinOp::Ge => Instr::Ge,
+                });
+            }
+            Rvalue::Call { func, args } if ownership::is_builtin(func) => {
+                for arg in args {
+                    self.operand(arg);
+                }
+                self.emit(match func.as_str() {
+                    "assert" => Instr::Assert,
+                    "assert_eq" => Instr::AssertEq,
+                    "hash" => Instr::Hash,
+                    _ => Instr::Print(args.len() as u32),
+                });
+                return Ok(types::builtin_ret(func) != Ty::Unit);
+            }
+            Rvalue::Call { func, args } => {
+                for arg in args {
+                    self.operand(arg);
+                }
+                let index = self
+                    .program
+                    .functions
+                    .iter()
+                    .position(|other| other.name == *func)
+                    .ok_or_else(|| {
+                        let msg = format!("Unknown function `{}`", func);
+                        Error::new(msg, codes::E036, self.span.clone())
+                    })?;
+                self.emit(Instr::Call(index as u32));
+                return Ok(self.program.functions[index].ret != Ty::Unit);
+            }
+            Rvalue::Struct { name, members } => {
+                for member in members {
+                    self.operand(member);
+                }
+                let index = self
+                    .program
+                    .structs
+                    .iter()
+                    .position(|(other, _)| other == name)
+                    .ok_or_else(|| {
+                        let msg = format!("Unknown struct `{}`", name);
+                        Error::new(msg, codes::E022, self.span.clone())
+                    })?;
+                self.emit(Instr::Struct(index as u32));
+            }
+            Rvalue::Member(operand, index) => {
+                self.operand(operand);
+                self.emit(Instr::Member(*index as u32));
+            }
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,391 @@
+//! The `.firc` file format.
+//!
+//! All numbers are little endian. A file starts with the magic `FIRC` and a
+//! `u16` version, followed by the constant pool, the structs and the
+//! functions:
+//!
+//! ```text
+//! constants: u32 count, then per constant a tag byte and its payload
+//!            (0 int: i32, 1 float: f64 bits, 2 bool: u8, 3 string: str)
+//! structs:   u32 count, then per struct its name (str) and members (u32
+//!            count, then the name of each member as a str)
+//! functions: u32 count, then per function its name (str), params (u32),
+//!            locals (u32), returns (u8), code (u32 count, then per
+//!            instruction an opcode byte and, if it has one, a u32 operand)
+//!            and spans (per instruction its start and end, two u32s)
+//! str:       u32 length, then that many bytes of UTF-8
+//! ```
+
+use crate::error::{codes, Error};
+
+use super::{Function, Instr, Module, Struct, Value};
+
+const MAGIC: &[u8; 4] = b"FIRC";
+const VERSION: u16 = 2;
+
+fn error(msg: String) -> Error {
+    Error::new(msg, codes::E007, 0..0)
+}
+
+pub fn write(module: &Module) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    out.extend(VERSION.to_le_bytes());
+
+    out.extend((module.constants.len() as u32).to_le_bytes());
+    for constant in &module.constants {
+        match constant {
+            Value::Int(int) => {
+                out.push(0);
+                out.extend(int.to_le_bytes());
+            }
+            Value::Float(float) => {
+                out.push(1);
+                out.extend(float.to_bits().to_le_bytes());
+            }
+            Value::Bool(bool) => {
+                out.push(2);
+                out.push(*bool as u8);
+            }
+            Value::Str(string) => {
+                out.push(3);
+                write_str(&mut out, string);
+            }
+            Value::Unit | Value::Ref(_) | Value::Struct(..) => unreachable!("not a constant"),
+        }
+    }
+
+    out.extend((module.structs.len() as u32).to_le_bytes());
+    for ty in &module.structs {
+        write_str(&mut out, &ty.name);
+        out.extend((ty.members.len() as u32).to_le_bytes());
+        for member in &ty.members {
+            write_str(&mut out, member);
+        }
+    }
+
+    out.extend((module.functions.len() as u32).to_le_bytes());
+    for func in &module.functions {
+        write_str(&mut out, &func.name);
+        out.extend(func.params.to_le_bytes());
+        out.extend(func.locals.to_le_bytes());
+        out.push(func.returns as u8);
+        out.extend((func.code.len() as u32).to_le_bytes());
+        for instr in &func.code {
+            let (opcode, operand) = encode(*instr);
+            out.push(opcode);
+            if let Some(operand) = operand {
+                out.extend(operand.to_le_bytes());
+            }
+        }
+        for span in &func.spans {
+            out.extend((span.start as u32).to_le_bytes());
+            out.extend((span.end as u32).to_le_bytes());
+        }
+    }
+    out
+}
+
+fn write_str(out: &mut Vec<u8>, string: &str) {
+    out.extend((string.len() as u32).to_le_bytes());
+    out.extend(string.as_bytes());
+}
+
+fn encode(instr: Instr) -> (u8, Option<u32>) {
+    match instr {
+        Instr::Const(index) => (0, Some(index)),
+        Instr::Load(local) => (1, Some(local)),
+        Instr::Store(local) => (2, Some(local)),
+        Instr::Ref(local) => (3, Some(local)),
+        Instr::Pop => (4, None),
+        Instr::Neg => (5, None),
+        Instr::Not => (6, None),
+        Instr::Add => (7, None),
+        Instr::Sub => (8, None),
+        Instr::Mul => (9, None),
+        Instr::Div => (10, None),
+        Instr::Pow => (11, None),
+        Instr::Eq => (12, None),
+        Instr::Ne => (13, None),
+        Instr::Lt => (14, None),
+        Instr::Gt => (15, None),
+        Instr::Le => (16, None),
+        Instr::Ge => (17, None),
+        Instr::Jump(target) => (18, Some(target)),
+        Instr::JumpIfFalse(target) => (19, Some(target)),
+        Instr::Call(func) => (20, Some(func)),
+        Instr::Print(count) => (21, Some(count)),
+        Instr::Return => (22, None),
+        Instr::ReturnValue => (23, None),
+        Instr::Trap => (24, None),
+        Instr::Assert => (25, None),
+        Instr::AssertEq => (26, None),
+        Instr::Hash => (27, None),
+        Instr::Struct(index) => (28, Some(index)),
+        Instr::Member(index) => (29, Some(index)),
+    }
+}
+
+/// Reads a module written by `write`, checking that every index in it is in
+/// bounds so the VM and disassembler can rely on them.
+pub fn read(bytes: &[u8]) -> Result<Module, Error> {
+    let mut reader = Reader { bytes, position: 0 };
+    if reader.take(4)? != MAGIC {
+        return Err(error("Not a `.firc` file".to_string()));
+    }
+    let version = u16::from_le_bytes(reader.array()?);
+    if version != VERSION {
+        return Err(error(format!(
+            "`.firc` version {} is not supported, expected {}",
+            version, VERSION
+        )));
+    }
+
+    let mut constants = Vec::new();
+    for _ in 0..reader.u32()? {
+        constants.push(match reader.u8()? {
+            0 => Value::Int(i32::from_le_bytes(reader.array()?)),
+            1 => Value::Float(f64::from_bits(u64::from_le_bytes(reader.array()?))),
+            2 => Value::Bool(reader.u8()? != 0),
+            3 => Value::Str(reader.str()?.into()),
+            tag => return Err(error(format!("Unknown constant tag {}", tag))),
+        });
+    }
+
+    let mut structs = Vec::new();
+    for _ in 0..reader.u32()? {
+        let name = reader.str()?;
+        let mut members = Vec::new();
+        for _ in 0..reader.u32()? {
+            members.push(reader.str()?);
+        }
+        structs.push(Struct { name, members });
+    }
+
+    let mut functions = Vec::new();
+    for _ in 0..reader.u32()? {
+        let name = reader.str()?;
+        let params = reader.u32()?;
+        let locals = reader.u32()?;
+        let returns = reader.u8()? != 0;
+        let mut code = Vec::new();
+        for _ in 0..reader.u32()? {
+            code.push(reader.instr()?);
+        }
+        let mut spans = Vec::new();
+        for _ in 0..code.len() {
+            spans.push(reader.u32()? as usize..reader.u32()? as usize);
+        }
+        functions.push(Function {
+            name,
+            params,
+            locals,
+            returns,
+            code,
+            spans,
+        });
+    }
+    if reader.position != bytes.len() {
+        return Err(error(
+            "Unexpected bytes after the last function".to_string(),
+        ));
+    }
+
+    let module = Module {
+        constants,
+        structs,
+        functions,
+    };
+    validate(&module)?;
+    Ok(module)
+}
+
+fn validate(module: &Module) -> Result<(), Error> {
+    for func in &module.functions {
+        if func.params > func.locals {
+            return Err(error(format!(
+                "`{}` has more parameters than locals",
+                func.name
+            )));
+        }
+        if func.code.is_empty() {
+            return Err(error(format!("`{}` has no code", func.name)));
+        }
+        if func.spans.iter().any(|span| span.start > span.end) {
+            return Err(error(format!(
+                "`{}` has a span that ends before it starts",
+                func.name
+            )));
+        }
+        for instr in &func.code {
+            let valid = match *instr {
+                Instr::Const(index) => (index as usize) < module.constants.len(),
+                Instr::Load(local) | Instr::Store(local) | Instr::Ref(local) => local < func.locals,
+                Instr::Jump(target) | Instr::JumpIfFalse(target) => {
+                    (target as usize) < func.code.len()
+                }
+                Instr::Call(callee) => (callee as usize) < module.functions.len(),
+                Instr::Struct(index) => (index as usize) < module.structs.len(),
+                _ => true,
+            };
+            if !valid {
+                return Err(error(format!(
+                    "`{}` contains `{:?}`, which is out of bounds",
+                    func.name, instr
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + count)
+            .ok_or_else(|| error("Unexpected end of `.firc` file".to_string()))?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn str(&mut self) -> Result<String, Error> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| error("A string is not valid UTF-8".to_string()))
+    }
+
+    fn instr(&mut self) -> Result<Instr, Error> {
+        let opcode = self.u8()?;
+        Ok(match opcode {
+            0 => Instr::Const(self.u32()?),
+            1 => Instr::Load(self.u32()?),
+            2 => Instr::Store(self.u32()?),
+            3 => Instr::Ref(self.u32()?),
+            4 => Instr::Pop,
+            5 => Instr::Neg,
+            6 => Instr::Not,
+            7 => Instr::Add,
+            8 => Instr::Sub,
+            9 => Instr::Mul,
+            10 => Instr::Div,
+            11 => Instr::Pow,
+            12 => Instr::Eq,
+            13 => Instr::Ne,
+            14 => Instr::Lt,
+            15 => Instr::Gt,
+            16 => Instr::Le,
+            17 => Instr::Ge,
+            18 => Instr::Jump(self.u32()?),
+            19 => Instr::JumpIfFalse(self.u32()?),
+            20 => Instr::Call(self.u32()?),
+            21 => Instr::Print(self.u32()?),
+            22 => Instr::Return,
+            23 => Instr::ReturnValue,
+            24 => Instr::Trap,
+            25 => Instr::Assert,
+            26 => Instr::AssertEq,
+            27 => Instr::Hash,
+            28 => Instr::Struct(self.u32()?),
+            29 => Instr::Member(self.u32()?),
+            opcode => return Err(error(format!("Unknown opcode {}", opcode))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode::{compile, vm};
+    use crate::db::Database;
+
+    fn module() -> Module {
+        let mut db = Database::default();
+        let file = db.add_file(
+            "struct Point {
+    x: int,
+    y: float,
+}
+
+fn norm(point: Point) -> float {
+    return point.y * point.y;
+}
+
+fn main() {
+    let point = Point { x: 3, y: 1.5 };
+    if (point.x > 2 && true) {
+        print(\"far \", norm(point));
+    }
+    assert_eq(point.x, 3);
+}
+"
+            .to_string(),
+        );
+        let program = db.mir(file, 0);
+        compile(program.as_ref().as_ref().unwrap()).unwrap()
+    }
+
+    fn run(module: &Module) -> String {
+        let mut out = Vec::new();
+        vm::run(module, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn modules_survive_a_round_trip() {
+        let module = module();
+        let read = read(&write(&module)).unwrap();
+        assert_eq!(read, module);
+        assert_eq!(run(&read), "far 2.25\n");
+    }
+
+    #[test]
+    fn truncated_files_are_errors() {
+        let bytes = write(&module());
+        for len in 0..bytes.len() {
+            let err = read(&bytes[..len]).unwrap_err();
+            assert_eq!(err.code().code, "E007", "{} bytes", len);
+        }
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(read(&longer).is_err());
+    }
+
+    #[test]
+    fn corrupted_files_are_errors_or_valid_modules() {
+        let bytes = write(&module());
+        assert!(read(b"FIRD\x02\x00").is_err());
+        let mut version = bytes.clone();
+        version[4] = 99;
+        let err = read(&version).unwrap_err();
+        assert!(err.msg().contains("version 99"), "{}", err.msg());
+
+        // Whatever byte is changed, reading gives an error or a module
+        // whose indices are in bounds, never a panic.
+        for position in 0..bytes.len() {
+            for value in [0x00, 0x7f, 0xff] {
+                let mut corrupted = bytes.clone();
+                corrupted[position] = value;
+                if let Ok(module) = read(&corrupted) {
+                    module.to_string();
+                }
+            }
+        }
+    }
+}
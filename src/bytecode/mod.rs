@@ -0,0 +1,209 @@
+//! A compact bytecode for a stack-based virtual machine.
+//!
+//! `compile` translates MIR to a `Module`, `vm::run` executes it, and
+//! `firc` reads and writes the `.firc` files compiled modules are cached in.
+//! The `Display` implementation of `Module` is the disassembly printed by
+//! `fir disasm`.
+
+use std::fmt;
+use std::rc::Rc;
+
+use logos::Span;
+
+pub mod compile;
+pub mod firc;
+pub mod vm;
+
+pub use compile::compile;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Float(f64),
+    Bool(bool),
+    Str(Rc<str>),
+    Unit,
+    /// The slot of a local in a frame further down the stack, held by
+    /// parameters that are `by_ref`. Never part of a constant pool.
+    Ref(usize),
+    /// A value of the struct `structs[index]` of the module, with its
+    /// members in declaration order. Never part of a constant pool either.
+    Struct(u32, Rc<[Value]>),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(int) => write!(f, "{}", int),
+            Value::Float(float) => write!(f, "{}", float),
+            Value::Bool(bool) => write!(f, "{}", bool),
+            Value::Str(string) => write!(f, "{}", string),
+            Value::Unit => write!(f, "()"),
+            Value::Ref(slot) => write!(f, "&slot {}", slot),
+            Value::Struct(index, members) => {
+                let members: Vec<String> = members.iter().map(Value::to_string).collect();
+                write!(f, "struct #{} {{ {} }}", index, members.join(", "))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instr {
+    /// Pushes a constant from the pool.
+    Const(u32),
+    /// Pushes the value of a local, following it if it is a `Ref`.
+    Load(u32),
+    /// Pops a value into a local, or into the slot the local refers to.
+    Store(u32),
+    /// Pushes a `Ref` to the slot of a local.
+    Ref(u32),
+    Pop,
+    Neg,
+    Not,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Jump(u32),
+    /// Pops a `bool` and jumps if it is `false`.
+    JumpIfFalse(u32),
+    /// Calls a function of the module with its arguments on the stack.
+    Call(u32),
+    /// Prints the given number of values from the stack and a newline.
+    Print(u32),
+    /// Pops the source of a condition and the condition, and stops with an
+    /// error showing the source if the condition is `false`.
+    Assert,
+    /// Pops the sources of two operands and the operands, and stops with an
+    /// error showing both if the operands are not equal.
+    AssertEq,
+    /// Pops a value and pushes its hash, an `int`.
+    Hash,
+    /// Pops the members of the struct `structs[index]` and pushes the struct.
+    Struct(u32),
+    /// Pops a struct and pushes its member `index`.
+    Member(u32),
+    Return,
+    /// Pops the result and returns it to the caller.
+    ReturnValue,
+    Trap,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    pub params: u32,
+    /// Number of local slots, including the parameters.
+    pub locals: u32,
+    /// Whether calls leave a result on the stack.
+    pub returns: bool,
+    pub code: Vec<Instr>,
+    /// The span of the source each instruction was compiled from, which is
+    /// where errors running it are reported.
+    pub spans: Vec<Span>,
+}
+
+/// A struct of the program, which its values need to be printed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Struct {
+    pub name: String,
+    pub members: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    pub constants: Vec<Value>,
+    pub structs: Vec<Struct>,
+    pub functions: Vec<Function>,
+}
+
+impl Module {
+    pub fn function(&self, name: &str) -> Option<usize> {
+        self.functions.iter().position(|func| func.name == name)
+    }
+
+    /// `value` as `print` writes it: a struct as `Point { x: 1, y: 2 }`,
+    /// like the `Display` the Rust backend derives for it.
+    pub fn show(&self, value: &Value) -> String {
+        match value {
+            Value::Struct(index, members) => {
+                let ty = &self.structs[*index as usize];
+                if members.is_empty() {
+                    return format!("{} {{}}", ty.name);
+                }
+                let members: Vec<String> = ty
+                    .members
+                    .iter()
+                    .zip(members.iter())
+                    .map(|(name, member)| format!("{}: {}", name, self.show(member)))
+                    .collect();
+                format!("{} {{ {} }}", ty.name, members.join(", "))
+            }
+            value => value.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "constants:")?;
+        for (index, constant) in self.constants.iter().enumerate() {
+            writeln!(f, "    #{:<4} {:?}", index, constant)?;
+        }
+        if !self.structs.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "structs:")?;
+            for (index, ty) in self.structs.iter().enumerate() {
+                writeln!(
+                    f,
+                    "    #{:<4} {} {{ {} }}",
+                    index,
+                    ty.name,
+                    ty.members.join(", ")
+                )?;
+            }
+        }
+        for func in &self.functions {
+            writeln!(f)?;
+            writeln!(
+                f,
+                "fn {} (params: {}, locals: {}{}):",
+                func.name,
+                func.params,
+                func.locals,
+                if func.returns { ", returns" } else { "" }
+            )?;
+            for (offset, instr) in func.code.iter().enumerate() {
+                let instr = match instr {
+                    Instr::Const(index) => {
+                        format!("const #{:<8}; {:?}", index, self.constants[*index as usize])
+                    }
+                    Instr::Load(local) => format!("load {}", local),
+                    Instr::Store(local) => format!("store {}", local),
+                    Instr::Ref(local) => format!("ref {}", local),
+                    Instr::Jump(target) => format!("jump {:04}", target),
+                    Instr::JumpIfFalse(target) => format!("jump_if_false {:04}", target),
+                    Instr::Call(func) => format!("call {}", self.functions[*func as usize].name),
+                    Instr::Print(count) => format!("print {}", count),
+                    Instr::AssertEq => "assert_eq".to_string(),
+                    Instr::Struct(index) => {
+                        format!("struct {}", self.structs[*index as usize].name)
+                    }
+                    Instr::Member(index) => format!("member {}", index),
+                    Instr::ReturnValue => "return_value".to_string(),
+                    instr => format!("{:?}", instr).to_lowercase(),
+                };
+                writeln!(f, "    {:04}  {}", offset, instr)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,365 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+
+use logos::Span;
+
+use crate::error::{
+    codes::{self, Code},
+    Error,
+};
+
+use super::{Instr, Module, Value};
+
+/// Calls nested deeper than this are assumed to recurse without end.
+const MAX_FRAMES: usize = 100_000;
+
+fn error(msg: String, code: &'static Code, span: &Span) -> Error {
+    Error::new(msg, code, span.clone())
+}
+
+struct Frame {
+    func: usize,
+    pc: usize,
+    /// Index of the frame's first local in `Vm::slots`.
+    base: usize,
+}
+
+struct Vm<'a, W> {
+    module: &'a Module,
+    out: &'a mut W,
+    stack: Vec<Value>,
+    /// The locals of every frame, one after the other.
+    slots: Vec<Value>,
+    frames: Vec<Frame>,
+    /// The span of the instruction being executed, from the span table of
+    /// its function.
+    span: Span,
+}
+
+/// Runs the `main` function of `module`, printing to `out`.
+pub fn run<W: Write>(module: &Module, out: &mut W) -> Result<(), Error> {
+    let main = module.function("main").ok_or_else(|| {
+        error(
+            "There is no `main` function".to_string(),
+            codes::E042,
+            &(0..0),
+        )
+    })?;
+    if module.functions[main].params != 0 {
+        return Err(error(
+            "`main` must not take parameters".to_string(),
+            codes::E043,
+            &(0..0),
+        ));
+    }
+    run_function(module, main, out)
+}
+
+/// Runs the function `func` of `module`, which takes no parameters, like
+/// `run` does with `main`.
+pub fn run_function<W: Write>(module: &Module, func: usize, out: &mut W) -> Result<(), Error> {
+    let mut vm = Vm {
+        module,
+        out,
+        stack: Vec::new(),
+        slots: Vec::new(),
+        frames: Vec::new(),
+        span: 0..0,
+    };
+    vm.call(func)?;
+    vm.execute()
+}
+
+impl<'a, W: Write> Vm<'a, W> {
+    /// Pops `count` values. Only modules read from a damaged `.firc` file
+    /// can run out of values.
+    fn pop_many(&mut self, count: u32) -> Result<Vec<Value>, Error> {
+        let count = count as usize;
+        if count > self.stack.len() {
+            return Err(error(
+                "The stack is empty".to_string(),
+                codes::E007,
+                &self.span,
+            ));
+        }
+        Ok(self.stack.split_off(self.stack.len() - count))
+    }
+
+    fn pop(&mut self) -> Result<Value, Error> {
+        Ok(self.pop_many(1)?.pop().unwrap())
+    }
+
+    fn call(&mut self, func: usize) -> Result<(), Error> {
+        if self.frames.len() == MAX_FRAMES {
+            return Err(error(
+                format!(
+                    "Stack overflow calling `{}`",
+                    self.module.functions[func].name
+                ),
+                codes::E006,
+                &self.span,
+            ));
+        }
+        let callee = &self.module.functions[func];
+        let base = self.slots.len();
+        let args = self.pop_many(callee.params)?;
+        self.slots.extend(args);
+        self.slots
+            .resize(base + callee.locals as usize, Value::Unit);
+        self.frames.push(Frame { func, pc: 0, base });
+        Ok(())
+    }
+
+    /// The slot a local of the current frame stands for, following `Ref`s.
+    fn slot(&self, local: u32) -> usize {
+        let mut slot = self.frames.last().unwrap().base + local as usize;
+        while let Value::Ref(target) = self.slots[slot] {
+            slot = target;
+        }
+        slot
+    }
+
+    fn execute(&mut self) -> Result<(), Error> {
+        let module = self.module;
+        while let Some(frame) = self.frames.last_mut() {
+            let func = &module.functions[frame.func];
+            let instr = *func.code.get(frame.pc).ok_or_else(|| {
+                error(
+                    format!("`{}` ran past the end of its code", func.name),
+                    codes::E007,
+                    &self.span,
+                )
+            })?;
+            self.span = func.spans[frame.pc].clone();
+            frame.pc += 1;
+            match instr {
+                Instr::Const(index) => self.stack.push(module.constants[index as usize].clone()),
+                Instr::Load(local) => {
+                    let value = self.slots[self.slot(local)].clone();
+                    self.stack.push(value);
+                }
+                Instr::Store(local) => {
+                    let slot = self.slot(local);
+                    self.slots[slot] = self.pop()?;
+                }
+                Instr::Ref(local) => {
+                    let slot = self.slot(local);
+                    self.stack.push(Value::Ref(slot));
+                }
+                Instr::Pop => {
+                    self.pop()?;
+                }
+                Instr::Neg => {
+                    let value = match self.pop()? {
+                        Value::Int(int) => Value::Int(int.wrapping_neg()),
+                        Value::Float(float) => Value::Float(-float),
+                        value => {
+                            return Err(error(
+                                format!("Can not negate `{}`", value),
+                                codes::E007,
+                                &self.span,
+                            ))
+                        }
+                    };
+                    self.stack.push(value);
+                }
+                Instr::Not => {
+                    let value = match self.pop()? {
+                        Value::Bool(bool) => Value::Bool(!bool),
+                        value => {
+                            return Err(error(
+                                format!("Can not negate `{}`", value),
+                                codes::E007,
+                                &self.span,
+                            ))
+                        }
+                    };
+                    self.stack.push(value);
+                }
+                Instr::Add
+                | Instr::Sub
+                | Instr::Mul
+                | Instr::Div
+                | Instr::Pow
+                | Instr::Eq
+                | Instr::Ne
+                | Instr::Lt
+                | Instr::Gt
+                | Instr::Le
+                | Instr::Ge => {
+                    let rhs = self.pop()?;
+                    let lhs = self.pop()?;
+                    let value = binary(instr, lhs, rhs, &self.span)?;
+                    self.stack.push(value);
+                }
+                Instr::Jump(target) => self.frames.last_mut().unwrap().pc = target as usize,
+                Instr::JumpIfFalse(target) => match self.pop()? {
+                    Value::Bool(true) => {}
+                    Value::Bool(false) => self.frames.last_mut().unwrap().pc = target as usize,
+                    value => {
+                        return Err(error(
+                            format!("Can not branch on `{}`", value),
+                            codes::E007,
+                            &self.span,
+                        ))
+                    }
+                },
+                Instr::Call(func) => self.call(func as usize)?,
+                Instr::Print(count) => {
+                    let values = self.pop_many(count)?;
+                    let mut line = String::new();
+                    for value in values {
+                        line.push_str(&module.show(&value));
+                    }
+                    writeln!(self.out, "{}", line).map_err(|err| {
+                        error(format!("Could not print: {}", err), codes::E046, &self.span)
+                    })?;
+                }
+                Instr::Assert => {
+                    let source = self.pop()?;
+                    if self.pop()? != Value::Bool(true) {
+                        return Err(error(
+                            format!("Assertion failed: `{}`", source),
+                            codes::E045,
+                            &self.span,
+                        ));
+                    }
+                }
+                Instr::AssertEq => {
+                    let values = self.pop_many(4)?;
+                    if values[0] != values[1] {
+                        return Err(error(
+                            format!(
+                                "Assertion failed: `{} == {}`\n  left: {}\n right: {}",
+                                values[2],
+                                values[3],
+                                quoted(module, &values[0]),
+                                quoted(module, &values[1])
+                            ),
+                            codes::E045,
+                            &self.span,
+                        ));
+                    }
+                }
+                Instr::Hash => {
+                    let mut hasher = DefaultHasher::new();
+                    hash(&self.pop()?, &mut hasher, &self.span)?;
+                    self.stack.push(Value::Int(hasher.finish() as i32));
+                }
+                Instr::Struct(index) => {
+                    let count = module.structs[index as usize].members.len() as u32;
+                    let members = self.pop_many(count)?;
+                    self.stack.push(Value::Struct(index, members.into()));
+                }
+                Instr::Member(index) => {
+                    let value = match self.pop()? {
+                        Value::Struct(_, members) if (index as usize) < members.len() => {
+                            members[index as usize].clone()
+                        }
+                        value => {
+                            return Err(error(
+                                format!("`{}` has no member {}", value, index),
+                                codes::E007,
+                                &self.span,
+                            ))
+                        }
+                    };
+                    self.stack.push(value);
+                }
+                Instr::Return | Instr::ReturnValue => {
+                    let frame = self.frames.pop().unwrap();
+                    self.slots.truncate(frame.base);
+                }
+                Instr::Trap => {
+                    return Err(error(
+                        format!("`{}` reached code that should be unreachable", func.name),
+                        codes::E007,
+                        &self.span,
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `value` as failed assertions show it, with strings in quotes.
+fn quoted(module: &Module, value: &Value) -> String {
+    match value {
+        Value::Str(string) => format!("{:?}", string),
+        value => module.show(value),
+    }
+}
+
+/// Feeds `value` to `state` the way a derived `Hash` does in Rust, so that
+/// `hash` gives what it does in the Rust backend.
+fn hash(value: &Value, state: &mut DefaultHasher, span: &Span) -> Result<(), Error> {
+    match value {
+        Value::Int(int) => int.hash(state),
+        Value::Bool(bool) => bool.hash(state),
+        Value::Str(string) => string.hash(state),
+        Value::Struct(_, members) => {
+            for member in members.iter() {
+                hash(member, state, span)?;
+            }
+        }
+        value => {
+            return Err(error(
+                format!("Can not hash `{}`", value),
+                codes::E007,
+                span,
+            ))
+        }
+    }
+    Ok(())
+}
+
+fn binary(instr: Instr, lhs: Value, rhs: Value, span: &Span) -> Result<Value, Error> {
+    Ok(match (instr, lhs, rhs) {
+        (Instr::Add, Value::Int(lhs), Value::Int(rhs)) => Value::Int(lhs.wrapping_add(rhs)),
+        (Instr::Sub, Value::Int(lhs), Value::Int(rhs)) => Value::Int(lhs.wrapping_sub(rhs)),
+        (Instr::Mul, Value::Int(lhs), Value::Int(rhs)) => Value::Int(lhs.wrapping_mul(rhs)),
+        (Instr::Div, Value::Int(_), Value::Int(0)) => {
+            return Err(error("Division by zero".to_string(), codes::E044, span))
+        }
+        (Instr::Div, Value::Int(lhs), Value::Int(rhs)) => Value::Int(lhs.wrapping_div(rhs)),
+        // Like the runtime of the LLVM backend, negative exponents give 1.
+        (Instr::Pow, Value::Int(lhs), Value::Int(rhs)) => {
+            Value::Int(lhs.wrapping_pow(rhs.max(0) as u32))
+        }
+        (Instr::Add, Value::Float(lhs), Value::Float(rhs)) => Value::Float(lhs + rhs),
+        (Instr::Sub, Value::Float(lhs), Value::Float(rhs)) => Value::Float(lhs - rhs),
+        (Instr::Mul, Value::Float(lhs), Value::Float(rhs)) => Value::Float(lhs * rhs),
+        (Instr::Div, Value::Float(lhs), Value::Float(rhs)) => Value::Float(lhs / rhs),
+        (Instr::Pow, Value::Float(lhs), Value::Float(rhs)) => Value::Float(lhs.powf(rhs)),
+        (Instr::Add, Value::Str(lhs), Value::Str(rhs)) => {
+            Value::Str(format!("{}{}", lhs, rhs).into())
+        }
+        (Instr::Eq, lhs, rhs) => Value::Bool(lhs == rhs),
+        (Instr::Ne, lhs, rhs) => Value::Bool(lhs != rhs),
+        (instr, lhs, rhs) => {
+            let ordering = match (&lhs, &rhs) {
+                (Value::Int(lhs), Value::Int(rhs)) => lhs.partial_cmp(rhs),
+                (Value::Float(lhs), Value::Float(rhs)) => lhs.partial_cmp(rhs),
+                _ => {
+                    return Err(error(
+                        format!(
+                            "`{:?}` can not be applied to `{}` and `{}`",
+                            instr, lhs, rhs
+                        ),
+                        codes::E007,
+                        span,
+                    ))
+                }
+            };
+            Value::Bool(match (instr, ordering) {
+                (_, None) => false,
+                (Instr::Lt, Some(ordering)) => ordering.is_lt(),
+                (Instr::Gt, Some(ordering)) => ordering.is_gt(),
+                (Instr::Le, Some(ordering)) => ordering.is_le(),
+                (_, Some(ordering)) => ordering.is_ge(),
+            })
+        }
+    })
+}
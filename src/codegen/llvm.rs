@@ -0,0 +1,397 @@
+//! Translation of verified MIR to textual LLVM IR.
+//!
+//! The output calls into the small C runtime in `runtime/fir.c` for printing,
+//! assertions and string handling, and can be built with
+//! `clang out.ll runtime/fir.c -o out`, or without clang with
+//! `llc -relocation-model=pic out.ll` and `cc out.s runtime/fir.c -o out`.
+//! It needs LLVM 14 or later. Pointers are typed, as LLVM 14 only reads
+//! those, and later versions read them as opaque `ptr`s.
+//!
+//! Every local lives in a stack slot, which LLVM's `mem2reg` turns into
+//! registers. Parameters that are `by_ref` are pointers to the caller's slot.
+
+use logos::Span;
+
+use crate::{
+    error::{codes, Error},
+    mir::{self, BinOp, Const, Operand, Rvalue, Stmt, Terminator, UnOp},
+    ownership,
+    types::Ty,
+};
+
+const RUNTIME: &str = "declare void @fir_print_int(i32)
+declare void @fir_print_float(double)
+declare void @fir_print_bool(i1)
+declare void @fir_print_char(i8)
+declare void @fir_print_str(i8*)
+declare void @fir_print_newline()
+declare void @fir_assert(i1, i8*)
+declare void @fir_assert_eq_int(i32, i32, i8*, i8*)
+declare void @fir_assert_eq_float(double, double, i8*, i8*)
+declare void @fir_assert_eq_bool(i1, i1, i8*, i8*)
+declare void @fir_assert_eq_char(i8, i8, i8*, i8*)
+declare void @fir_assert_eq_str(i8*, i8*, i8*, i8*)
+declare i8* @fir_str_concat(i8*, i8*)
+declare i1 @fir_str_eq(i8*, i8*)
+declare i32 @fir_pow_int(i32, i32)
+declare double @llvm.pow.f64(double, double)
+";
+
+pub fn emit(program: &mir::Program) -> Result<String, Error> {
+    let mut module = String::new();
+    for (name, members) in &program.structs {
+        let members = members
+            .iter()
+            .map(|(_, ty)| llvm_type(ty))
+            .collect::<Vec<_>>();
+        module.push_str(&format!("%{} = type {{ {} }}\n", name, members.join(", ")));
+    }
+    if !program.structs.is_empty() {
+        module.push('\n');
+    }
+    module.push_str(RUNTIME);
+
+    let mut strings = Vec::new();
+    let mut functions = String::new();
+    for func in &program.functions {
+        let mut lowering = FnLowering {
+            program,
+            func,
+            strings: &mut strings,
+            body: Vec::new(),
+            next: 0,
+            span: func.span.clone(),
+        };
+        functions.push('\n');
+        functions.push_str(&lowering.function()?);
+    }
+
+    for (index, string) in strings.iter().enumerate() {
+        let mut bytes = String::new();
+        for byte in string.bytes() {
+            if (byte.is_ascii_graphic() || byte == b' ') && byte != b'"' && byte != b'\\' {
+                bytes.push(byte as char);
+            } else {
+                bytes.push_str(&format!("\\{:02X}", byte));
+            }
+        }
+        module.push_str(&format!(
+            "@.str.{} = private unnamed_addr constant [{} x i8] c\"{}\\00\"\n",
+            index,
+            string.len() + 1,
+            bytes
+        ));
+    }
+    module.push_str(&functions);
+    Ok(module)
+}
+
+pub fn llvm_type(ty: &Ty) -> String {
+    match ty {
+        Ty::Int => "i32".to_string(),
+        Ty::Float => "double".to_string(),
+        Ty::Bool => "i1".to_string(),
+        Ty::Char => "i8".to_string(),
+        Ty::Str => "i8*".to_string(),
+        Ty::Unit => "void".to_string(),
+        Ty::Struct(name) => format!("%{}", name),
+    }
+}
+
+struct FnLowering<'a> {
+    program: &'a mir::Program,
+    func: &'a mir::Function,
+    strings: &'a mut Vec<String>,
+    body: Vec<String>,
+    next: usize,
+    /// The span of the statement being lowered.
+    span: Span,
+}
+
+impl<'a> FnLowering<'a> {
+    fn unsupported(&self, msg: String) -> Error {
+        Error::new(msg, codes::E041, self.span.clone())
+    }
+
+    fn temp(&mut self) -> String {
+        self.next += 1;
+        format!("%t{}", self.next)
+    }
+
+    fn inst(&mut self, inst: String) {
+        self.body.push(format!("  {}", inst));
+    }
+
+    fn is_main(&self) -> bool {
+        self.func.name == "main" && self.func.ret == Ty::Unit
+    }
+
+    fn function(&mut self) -> Result<String, Error> {
+        let mut params = Vec::new();
+        for (index, local) in self.func.locals.iter().enumerate() {
+            if index < self.func.params && local.by_ref {
+                params.push(format!("{}* %_{}", llvm_type(&local.ty), index));
+                continue;
+            }
+            self.inst(format!("%_{} = alloca {}", index, llvm_type(&local.ty)));
+            if index < self.func.params {
+                params.push(format!("{} %arg{}", llvm_type(&local.ty), index));
+                let ty = llvm_type(&local.ty);
+                self.inst(format!("store {} %arg{}, {}* %_{}", ty, index, ty, index));
+            }
+        }
+        self.inst("br label %bb0".to_string());
+
+        for (index, block) in self.func.blocks.iter().enumerate() {
+            self.body.push(format!("bb{}:", index));
+            for stmt in &block.stmts {
+                self.span = stmt.span.clone();
+                match &stmt.node {
+                    Stmt::Assign(local, rvalue) => {
+                        let value = self.rvalue(rvalue)?;
+                        let ty = llvm_type(&self.func.locals[*local].ty);
+                        self.inst(format!("store {} {}, {}* %_{}", ty, value, ty, local));
+                    }
+                    Stmt::Eval(rvalue) => {
+                        self.rvalue(rvalue)?;
+                    }
+                }
+            }
+            self.terminator(&block.terminator)?;
+        }
+
+        let ret = if self.is_main() {
+            "i32".to_string()
+        } else {
+            llvm_type(&self.func.ret)
+        };
+        let mut function = format!(
+            "define {} @{}({}) {{\nentry:\n",
+            ret,
+            self.func.name,
+            params.join(", ")
+        );
+        for line in &self.body {
+            function.push_str(line);
+            function.push('\n');
+        }
+        function.push_str("}\n");
+        Ok(function)
+    }
+
+    fn terminator(&mut self, terminator: &Terminator) -> Result<(), Error> {
+        let inst = match terminator {
+            Terminator::Goto(target) => format!("br label %bb{}", target),
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let condition = self.operand(condition)?;
+                format!(
+                    "br i1 {}, label %bb{}, label %bb{}",
+                    condition, then, otherwise
+                )
+            }
+            Terminator::Return(Some(value)) => {
+                let ty = llvm_type(&self.func.operand_ty(value));
+                format!("ret {} {}", ty, self.operand(value)?)
+            }
+            Terminator::Return(None) if self.is_main() => "ret i32 0".to_string(),
+            Terminator::Return(None) => "ret void".to_string(),
+            Terminator::Unreachable => "unreachable".to_string(),
+        };
+        self.inst(inst);
+        Ok(())
+    }
+
+    /// The LLVM value of `operand`. Storage passed to `by_ref` parameters is
+    /// the pointer to the local's slot.
+    fn operand(&mut self, operand: &Operand) -> Result<String, Error> {
+        Ok(match operand {
+            Operand::Local(local) => {
+                let value = self.temp();
+                let ty = llvm_type(&self.func.locals[*local].ty);
+                self.inst(format!("{} = load {}, {}* %_{}", value, ty, ty, local));
+                value
+            }
+            Operand::Ref(local) => format!("%_{}", local),
+            Operand::Const(Const::Int(int)) => int.to_string(),
+            Operand::Const(Const::Float(float)) => format!("0x{:016X}", float.to_bits()),
+            Operand::Const(Const::Bool(bool)) => bool.to_string(),
+            Operand::Const(Const::Str(string)) => {
+                self.strings.push(string.clone());
+                let array = format!("[{} x i8]", string.len() + 1);
+                format!(
+                    "getelementptr inbounds ({}, {}* @.str.{}, i64 0, i64 0)",
+                    array,
+                    array,
+                    self.strings.len() - 1
+                )
+            }
+        })
+    }
+
+    /// Lowers `rvalue` and returns the LLVM value holding the result. The
+    /// value is empty for calls that return nothing.
+    fn rvalue(&mut self, rvalue: &Rvalue) -> Result<String, Error> {
+        let inst = match rvalue {
+            Rvalue::Use(operand) => return self.operand(operand),
+            Rvalue::Unary(op, operand) => {
+                let ty = self.func.operand_ty(operand);
+                let value = self.operand(operand)?;
+                match (op, ty) {
+                    (UnOp::Neg, Ty::Int) => format!("sub i32 0, {}", value),
+                    (UnOp::Neg, _) => format!("fneg double {}", value),
+                    (UnOp::Not, _) => format!("xor i1 {}, true", value),
+                }
+            }
+            Rvalue::Call { func, args } if func == "assert" || func == "assert_eq" => {
+                let callee = match self.func.operand_ty(&args[0]) {
+                    _ if func == "assert" => "fir_assert",
+                    Ty::Int => "fir_assert_eq_int",
+                    Ty::Float => "fir_assert_eq_float",
+                    Ty::Bool => "fir_assert_eq_bool",
+                    Ty::Char => "fir_assert_eq_char",
+                    Ty::Str => "fir_assert_eq_str",
+                    ty => return Err(self.unsupported(format!("Can not compare a `{}`", ty))),
+                };
+                let mut values = Vec::new();
+                for arg in args {
+                    let ty = llvm_type(&self.func.operand_ty(arg));
+                    values.push(format!("{} {}", ty, self.operand(arg)?));
+                }
+                self.inst(format!("call void @{}({})", callee, values.join(", ")));
+                return Ok(String::new());
+            }
+            Rvalue::Call { func, .. } if func == "hash" => {
+                return Err(self.unsupported("Can not hash a value".to_string()))
+            }
+            Rvalue::Call { func, args } if ownership::is_builtin(func) => {
+                for arg in args {
+                    let ty = self.func.operand_ty(arg);
+                    let value = self.operand(arg)?;
+                    let print = match ty {
+                        Ty::Int => "fir_print_int",
+                        Ty::Float => "fir_print_float",
+                        Ty::Bool => "fir_print_bool",
+                        Ty::Char => "fir_print_char",
+                        Ty::Str => "fir_print_str",
+                        ty => return Err(self.unsupported(format!("Can not print a `{}`", ty))),
+                    };
+                    self.inst(format!(
+                        "call void @{}({} {})",
+                        print,
+                        llvm_type(&ty),
+                        value
+                    ));
+                }
+                self.inst("call void @fir_print_newline()".to_string());
+                return Ok(String::new());
+            }
+            Rvalue::Call { func, args } => {
+                let ret = self.program.function(func).unwrap().ret.clone();
+                let mut values = Vec::new();
+                for arg in args {
+                    let ty = match arg {
+                        Operand::Ref(local) => {
+                            format!("{}*", llvm_type(&self.func.locals[*local].ty))
+                        }
+                        arg => llvm_type(&self.func.operand_ty(arg)),
+                    };
+                    values.push(format!("{} {}", ty, self.operand(arg)?));
+                }
+                let call = format!("call {} @{}({})", llvm_type(&ret), func, values.join(", "));
+                if ret == Ty::Unit {
+                    self.inst(call);
+                    return Ok(String::new());
+                }
+                call
+            }
+            Rvalue::Struct { name, members } => {
+                let mut value = "undef".to_string();
+                for (index, member) in members.iter().enumerate() {
+                    let ty = llvm_type(&self.func.operand_ty(member));
+                    let member = self.operand(member)?;
+                    let result = self.temp();
+                    self.inst(format!(
+                        "{} = insertvalue %{} {}, {} {}, {}",
+                        result, name, value, ty, member, index
+                    ));
+                    value = result;
+                }
+                return Ok(value);
+            }
+            Rvalue::Member(operand, index) => {
+                let ty = llvm_type(&self.func.operand_ty(operand));
+                let value = self.operand(operand)?;
+                format!("extractvalue {} {}, {}", ty, value, index)
+            }
+            Rvalue::Binary(op, lhs, rhs) => {
+                let ty = self.func.operand_ty(lhs);
+                if let Ty::Struct(_) = ty {
+                    return Err(self.unsupported(format!("Can not compare a `{}`", ty)));
+                }
+                let lhs = self.operand(lhs)?;
+                let rhs = self.operand(rhs)?;
+                if ty == Ty::Str && matches!(op, BinOp::Eq | BinOp::Ne) {
+                    let equal = self.temp();
+                    self.inst(format!(
+                        "{} = call i1 @fir_str_eq(i8* {}, i8* {})",
+                        equal, lhs, rhs
+                    ));
+                    if *op == BinOp::Eq {
+                        return Ok(equal);
+                    }
+                    format!("xor i1 {}, true", equal)
+                } else {
+                    binary(*op, &ty, &lhs, &rhs)
+                }
+            }
+        };
+        let result = self.temp();
+        self.inst(format!("{} = {}", result, inst));
+        Ok(result)
+    }
+}
+
+/// The instruction computing `lhs op rhs` for operands of type `ty`.
+fn binary(op: BinOp, ty: &Ty, lhs: &str, rhs: &str) -> String {
+    match (ty, op) {
+        (Ty::Str, _) => format!("call i8* @fir_str_concat(i8* {}, i8* {})", lhs, rhs),
+        (Ty::Int, BinOp::Pow) => format!("call i32 @fir_pow_int(i32 {}, i32 {})", lhs, rhs),
+        (Ty::Float, BinOp::Pow) => {
+            format!("call double @llvm.pow.f64(double {}, double {})", lhs, rhs)
+        }
+        (Ty::Float, op) => {
+            let inst = match op {
+                BinOp::Add => "fadd",
+                BinOp::Sub => "fsub",
+                BinOp::Mul => "fmul",
+                BinOp::Div => "fdiv",
+                BinOp::Eq => "fcmp oeq",
+                BinOp::Ne => "fcmp une",
+                BinOp::Lt => "fcmp olt",
+                BinOp::Gt => "fcmp ogt",
+                BinOp::Le => "fcmp ole",
+                _ => "fcmp oge",
+            };
+            format!("{} double {}, {}", inst, lhs, rhs)
+        }
+        (ty, op) => {
+            let inst = match op {
+                BinOp::Add => "add",
+                BinOp::Sub => "sub",
+                BinOp::Mul => "mul",
+                BinOp::Div => "sdiv",
+                BinOp::Eq => "icmp eq",
+                BinOp::Ne => "icmp ne",
+                BinOp::Lt => "icmp slt",
+                BinOp::Gt => "icmp sgt",
+                BinOp::Le => "icmp sle",
+                _ => "icmp sge",
+            };
+            format!("{} {} {}, {}", inst, llvm_type(ty), lhs, rhs)
+        }
+    }
+}
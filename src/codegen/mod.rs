@@ -0,0 +1,2 @@
+pub mod llvm;
+pub mod rust;
@@ -0,0 +1,696 @@
+//! Lowering of fir items to Rust source, using the decisions made by
+//! `ownership` for every binding and every use of a name. Statements that
+//! `parallel` found to be independent run on scoped threads. Attributes
+//! with a Rust counterpart are emitted as it, see `rust_attr`, and what a
+//! struct derives as `#[derive]`s and a `Display` for `Print`. A struct is
+//! only `Clone` if it derives it, so copying one that does not is an error.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::{
+    ast::{self, print},
+    attrs,
+    error::{codes, Error},
+    lexer::TokenKind,
+    ownership::{self, Binding, Event, FnOwnership, Mode, Ownership},
+    parallel::{self, FnPlan, Plan},
+    types::{ExprTypes, Ty},
+};
+
+/// The Rust source of `items`, which have to check: `exprs` are the types
+/// of the expressions of each function, by name, and `ownership` is what
+/// `ownership::analyze` made of them.
+pub fn emit(
+    items: &[Result<ast::Spanned<ast::Item>, Error>],
+    exprs: &HashMap<String, Rc<ExprTypes>>,
+    ownership: &Ownership,
+    parallel: bool,
+) -> Result<String, Error> {
+    let plan = parallel::analyze(items, ownership, parallel)?;
+    let structs = items
+        .iter()
+        .flatten()
+        .filter_map(|item| match &item.node {
+            ast::Item::Struct {
+                attrs,
+                name: Ok(name),
+                ..
+            } => Some((
+                name.name.to_string(),
+                derives(attrs).any(|derive| derive == "Clone"),
+            )),
+            _ => None,
+        })
+        .collect();
+    let mut backend = RustBackend {
+        ownership,
+        plan: &plan,
+        structs,
+        exprs,
+        func: None,
+        body: None,
+        types: None,
+        cursor: 0,
+        blocks: None,
+        block: 0,
+        indent: 0,
+        hash: false,
+        out: String::new(),
+    };
+    let shared = ownership.functions.iter().any(|func| {
+        func.bindings
+            .iter()
+            .any(|binding| binding.mode == Mode::Shared)
+    });
+    if shared {
+        backend.line("use std::cell::RefCell;");
+        backend.line("use std::rc::Rc;");
+        backend.line("");
+    }
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            backend.line("");
+        }
+        backend.item(item.as_ref().map_err(Clone::clone)?)?;
+    }
+    if backend.hash {
+        backend.line("");
+        backend.line("fn hash<T: std::hash::Hash>(value: &T) -> i32 {");
+        backend.line("    use std::hash::Hasher;");
+        backend.line("    let mut hasher = std::collections::hash_map::DefaultHasher::new();");
+        backend.line("    value.hash(&mut hasher);");
+        backend.line("    hasher.finish() as i32");
+        backend.line("}");
+    }
+    Ok(backend.out)
+}
+
+pub fn rust_type(ty: &ast::Type) -> Result<String, Error> {
+    let name = match ty.name.as_str() {
+        "int" => "i32",
+        "float" => "f64",
+        "bool" => "bool",
+        "char" => "char",
+        "string" => "String",
+        name => name,
+    };
+    if ty.generics.is_empty() {
+        return Ok(name.to_string());
+    }
+    let generics = ty
+        .generics
+        .iter()
+        .map(|generic| rust_type(generic.as_ref().map_err(Clone::clone)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(format!("{}<{}>", name, generics.join(", ")))
+}
+
+/// The Rust type of the fir type `ty`.
+fn rust_ty(ty: &Ty) -> String {
+    match ty {
+        Ty::Int => "i32".to_string(),
+        Ty::Float => "f64".to_string(),
+        Ty::Bool => "bool".to_string(),
+        Ty::Char => "char".to_string(),
+        Ty::Str => "String".to_string(),
+        Ty::Unit => "()".to_string(),
+        Ty::Struct(name) => name.clone(),
+    }
+}
+
+/// What a struct with `attrs` derives.
+fn derives(attrs: &[ast::Spanned<ast::Attr>]) -> impl Iterator<Item = &str> {
+    attrs::get(attrs, "derive")
+        .into_iter()
+        .flat_map(attrs::names)
+        .map(|(name, _)| name.as_str())
+}
+
+/// The Rust attribute `attr` is emitted as, if it has one. `#[allow]` keeps
+/// the lints rustc has a counterpart of.
+fn rust_attr(attr: &ast::Attr) -> Option<String> {
+    let args: Vec<String> = attr
+        .args
+        .iter()
+        .map(|arg| match &arg.node {
+            ast::AttrArg::Name(name) => name.to_string(),
+            ast::AttrArg::Str(string) => format!("\"{}\"", string),
+        })
+        .collect();
+    match attr.name.as_str() {
+        "test" => Some("#[test]".to_string()),
+        "inline" if args.is_empty() => Some("#[inline]".to_string()),
+        "inline" => Some(format!("#[inline({})]", args[0])),
+        "deprecated" if args.is_empty() => Some("#[deprecated]".to_string()),
+        "deprecated" => Some(format!("#[deprecated(note = {})]", args[0])),
+        "allow" => {
+            let mut lints = Vec::new();
+            for lint in &args {
+                let lint = match lint.as_str() {
+                    "unused_variables" | "unused_parameters" => "unused_variables",
+                    "unused_functions" => "dead_code",
+                    "non_camel_case_structs" => "non_camel_case_types",
+                    "non_snake_case" => "non_snake_case",
+                    "unreachable_code" => "unreachable_code",
+                    _ => continue,
+                };
+                if !lints.contains(&lint) {
+                    lints.push(lint);
+                }
+            }
+            (!lints.is_empty()).then(|| format!("#[allow({})]", lints.join(", ")))
+        }
+        _ => None,
+    }
+}
+
+struct RustBackend<'a> {
+    ownership: &'a Ownership,
+    plan: &'a Plan,
+    /// Every struct, and whether it derives `Clone`.
+    structs: HashMap<String, bool>,
+    exprs: &'a HashMap<String, Rc<ExprTypes>>,
+    func: Option<&'a FnOwnership>,
+    /// The body of `func`, which its statements and expressions are in.
+    body: Option<&'a ast::Body>,
+    /// The types of the expressions of `body`.
+    types: Option<&'a ExprTypes>,
+    /// Index of the next `ownership::Event` of `func`. Names are visited in
+    /// the same order `ownership` recorded them.
+    cursor: usize,
+    blocks: Option<&'a FnPlan>,
+    /// Index of the next block of `blocks`, visited in source order as well.
+    block: usize,
+    indent: usize,
+    /// Whether `hash` is called, which needs a helper after the items.
+    hash: bool,
+    out: String,
+}
+
+impl<'a> RustBackend<'a> {
+    fn line(&mut self, line: &str) {
+        if !line.is_empty() {
+            self.out.push_str(&"    ".repeat(self.indent));
+        }
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    fn attrs(&mut self, attrs: &[ast::Spanned<ast::Attr>]) {
+        for attr in attrs.iter().filter_map(|attr| rust_attr(attr)) {
+            self.line(&attr);
+        }
+    }
+
+    /// Writes a fir doc comment as a Rust one.
+    fn doc(&mut self, doc: &ast::Doc) {
+        for line in doc.iter().flat_map(|doc| doc.lines()) {
+            self.line(format!("/// {}", line).trim_end());
+        }
+    }
+
+    fn event(&mut self) -> (Event, Option<&'a Binding>) {
+        let func = self.func.expect("names are only emitted inside functions");
+        let event = func.events[self.cursor].clone();
+        self.cursor += 1;
+        let binding = event.binding.map(|binding| &func.bindings[binding]);
+        (event, binding)
+    }
+
+    /// The body of the function being emitted.
+    fn body(&self) -> &'a ast::Body {
+        self.body
+            .expect("statements are only emitted inside functions")
+    }
+
+    /// The type of the expression `id` of the body.
+    fn ty(&self, id: ast::ExprId) -> &'a Ty {
+        &self
+            .types
+            .expect("expressions are only emitted inside functions")[id]
+    }
+
+    fn item(&mut self, item: &'a ast::Item) -> Result<(), Error> {
+        match item {
+            ast::Item::Struct {
+                doc,
+                attrs,
+                name,
+                members,
+            } => {
+                self.doc(doc);
+                self.attrs(attrs);
+                let mut rust_derives = Vec::new();
+                for derive in derives(attrs) {
+                    match derive {
+                        // `assert_eq!` shows values that differ with `Debug`.
+                        "Eq" => rust_derives.extend(["Debug", "PartialEq"]),
+                        "Hash" | "Clone" => rust_derives.push(derive),
+                        _ => {}
+                    }
+                }
+                if !rust_derives.is_empty() {
+                    self.line(&format!("#[derive({})]", rust_derives.join(", ")));
+                }
+                let name = rust_type(name.as_ref().map_err(Clone::clone)?)?;
+                self.line(&format!("pub struct {} {{", name));
+                self.indent += 1;
+                for member in members {
+                    let ty = rust_type(member.ty.as_ref().map_err(Clone::clone)?)?;
+                    self.doc(&member.doc);
+                    self.attrs(&member.attrs);
+                    self.line(&format!("pub {}: {},", member.name, ty));
+                }
+                self.indent -= 1;
+                self.line("}");
+                if derives(attrs).any(|derive| derive == "Print") {
+                    self.display(&name, members);
+                }
+            }
+            ast::Item::Function {
+                doc,
+                attrs,
+                name,
+                parameters,
+                return_type,
+                body,
+            } => {
+                let func = self.ownership.function(name).unwrap();
+                let mut params = Vec::new();
+                for binding in &func.bindings[..parameters.len()] {
+                    let ty = rust_ty(&binding.ty);
+                    params.push(match binding.mode {
+                        Mode::Borrow => format!("{}: &{}", binding.name, ty),
+                        Mode::BorrowMut => format!("{}: &mut {}", binding.name, ty),
+                        _ if binding.mutable => format!("mut {}: {}", binding.name, ty),
+                        _ => format!("{}: {}", binding.name, ty),
+                    });
+                }
+                self.func = Some(func);
+                self.body = Some(body);
+                self.types = Some(&self.exprs[name.as_str()]);
+                self.cursor = 0;
+                self.blocks = self.plan.function(name);
+                self.block = 0;
+                let return_type = match return_type {
+                    Some(ty) => format!(" -> {}", rust_type(ty.as_ref().map_err(Clone::clone)?)?),
+                    None => String::new(),
+                };
+                self.doc(doc);
+                self.attrs(attrs);
+                self.line(&format!(
+                    "fn {}({}){} {{",
+                    name,
+                    params.join(", "),
+                    return_type
+                ));
+                self.stmts(&body.block)?;
+                self.line("}");
+                self.func = None;
+                self.body = None;
+                self.types = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// `Display` for a struct that derives `Print`, which `print` uses. It
+    /// writes `Point { x: 1, y: 2 }`, with each member as it prints.
+    fn display(&mut self, name: &str, members: &[ast::Member]) {
+        let format: Vec<String> = members
+            .iter()
+            .map(|member| format!("{}: {{}}", member.name))
+            .collect();
+        let format = if format.is_empty() {
+            format!("{} {{{{}}}}", name)
+        } else {
+            format!("{} {{{{ {} }}}}", name, format.join(", "))
+        };
+        let values: String = members
+            .iter()
+            .map(|member| format!(", self.{}", member.name))
+            .collect();
+        self.line("");
+        self.line(&format!("impl std::fmt::Display for {} {{", name));
+        self.indent += 1;
+        self.line("fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {");
+        self.indent += 1;
+        self.line(&format!("write!(f, \"{}\"{})", format, values));
+        self.indent -= 1;
+        self.line("}");
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn stmts(&mut self, stmts: &[ast::StmtId]) -> Result<(), Error> {
+        let groups = match self.blocks {
+            Some(blocks) => blocks.blocks[self.block].groups.clone(),
+            None => Vec::new(),
+        };
+        self.block += 1;
+        self.indent += 1;
+        let mut position = 0;
+        while position < stmts.len() {
+            match groups.iter().find(|group| group.start == position) {
+                Some(group) => {
+                    self.parallel(&stmts[group.clone()])?;
+                    position = group.end;
+                }
+                None => {
+                    self.attrs(self.body().attrs(stmts[position]));
+                    self.stmt(self.body().stmt(stmts[position])?)?;
+                    position += 1;
+                }
+            }
+        }
+        self.indent -= 1;
+        Ok(())
+    }
+
+    /// Runs the values of independent `let` statements on scoped threads.
+    /// fir names can not start with `_`, so the thread handles can't clash
+    /// with names used in the values.
+    fn parallel(&mut self, stmts: &[ast::StmtId]) -> Result<(), Error> {
+        let mut names = Vec::new();
+        let mut spawns = Vec::new();
+        for (index, stmt) in stmts.iter().enumerate() {
+            if let ast::Stmt::Let { var_name, value } = &self.body().stmt(*stmt)?.node {
+                let (value, _) = self.value(*value)?;
+                if self.event().1.unwrap().mutable {
+                    names.push(format!("mut {}", var_name));
+                } else {
+                    names.push(var_name.to_string());
+                }
+                spawns.push(format!("let _{} = _scope.spawn(|| {});", index, value));
+            }
+        }
+        self.line(&format!(
+            "let ({}) = std::thread::scope(|_scope| {{",
+            names.join(", ")
+        ));
+        self.indent += 1;
+        for spawn in &spawns {
+            self.line(spawn);
+        }
+        let joins: Vec<String> = (0..spawns.len())
+            .map(|index| format!("_{}.join().unwrap()", index))
+            .collect();
+        self.line(&format!("({})", joins.join(", ")));
+        self.indent -= 1;
+        self.line("});");
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &ast::Stmt) -> Result<(), Error> {
+        match stmt {
+            ast::Stmt::Let { var_name, value } => {
+                let (value, source) = self.value(*value)?;
+                let binding = self.event().1.unwrap();
+                let line = match binding.mode {
+                    Mode::Shared if source == Some(Mode::Shared) => {
+                        format!("let {} = {};", var_name, value)
+                    }
+                    Mode::Shared => format!("let {} = Rc::new(RefCell::new({}));", var_name, value),
+                    _ if binding.mutable => format!("let mut {} = {};", var_name, value),
+                    _ => format!("let {} = {};", var_name, value),
+                };
+                self.line(&line);
+            }
+            ast::Stmt::Assignment { var_name, value } => {
+                let (value, _) = self.value(*value)?;
+                let line = match self.event().1 {
+                    Some(binding) if binding.mode == Mode::Shared => {
+                        format!("*{}.borrow_mut() = {};", var_name, value)
+                    }
+                    Some(binding) if binding.mode == Mode::BorrowMut => {
+                        format!("*{} = {};", var_name, value)
+                    }
+                    _ => format!("{} = {};", var_name, value),
+                };
+                self.line(&line);
+            }
+            ast::Stmt::IfStmt {
+                condition,
+                body,
+                else_stmt,
+            } => self.if_stmt("", *condition, body, *else_stmt)?,
+            ast::Stmt::Block { stmts } => {
+                self.line("{");
+                self.stmts(stmts)?;
+                self.line("}");
+            }
+            ast::Stmt::Expr { expr } => {
+                let value = self.expr(*expr)?;
+                self.line(&format!("{};", value));
+            }
+            ast::Stmt::Return { value: None } => self.line("return;"),
+            ast::Stmt::Return { value: Some(value) } => {
+                let (value, _) = self.value(*value)?;
+                self.line(&format!("return {};", value));
+            }
+            ast::Stmt::Error(err) => return Err(err.clone()),
+        }
+        Ok(())
+    }
+
+    fn if_stmt(
+        &mut self,
+        prefix: &str,
+        condition: ast::ExprId,
+        body: &[ast::StmtId],
+        else_stmt: Option<ast::StmtId>,
+    ) -> Result<(), Error> {
+        let condition = self.expr(condition)?;
+        self.line(&format!("{}if {} {{", prefix, condition));
+        self.stmts(body)?;
+        match else_stmt {
+            None => self.line("}"),
+            Some(else_stmt) => match &self.body().stmt(else_stmt)?.node {
+                ast::Stmt::IfStmt {
+                    condition,
+                    body,
+                    else_stmt,
+                } => self.if_stmt("} else ", *condition, body, *else_stmt)?,
+                ast::Stmt::Block { stmts } => {
+                    self.line("} else {");
+                    self.stmts(stmts)?;
+                    self.line("}");
+                }
+                stmt => {
+                    self.line("} else {");
+                    self.indent += 1;
+                    self.stmt(stmt)?;
+                    self.indent -= 1;
+                    self.line("}");
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// The value of a `let` or an assignment. When the value is a plain name,
+    /// also returns how that name is passed on.
+    fn value(&mut self, id: ast::ExprId) -> Result<(String, Option<Mode>), Error> {
+        let expr = self.body().expr(id)?;
+        let name = match &expr.node {
+            ast::Expr::Ident(name) => name,
+            _ => return Ok((self.expr(id)?, None)),
+        };
+        let (event, binding) = self.event();
+        if let Ty::Struct(ty) = self.ty(id) {
+            if event.mode == Mode::Clone && self.structs.get(ty) == Some(&false) {
+                return Err(Error::new(
+                    format!(
+                        "`{}` is copied here, which needs `{}` to derive `Clone`",
+                        name, ty
+                    ),
+                    codes::E040,
+                    expr.span.clone(),
+                )
+                .with_note(
+                    format!("the Rust backend copies it, as {}", event.reason),
+                    None,
+                ));
+            }
+        }
+        let code = match (event.mode, binding) {
+            (Mode::Clone, Some(binding)) if binding.mode == Mode::Shared => {
+                format!("{}.borrow().clone()", name)
+            }
+            (Mode::Clone, _) => format!("{}.clone()", name),
+            (Mode::Shared, _) => format!("Rc::clone(&{})", name),
+            _ => name.to_string(),
+        };
+        Ok((code, Some(event.mode)))
+    }
+
+    fn arg(&mut self, name: &str) -> String {
+        let (event, binding) = self.event();
+        let binding = match binding {
+            Some(binding) => binding,
+            None => return format!("&{}", name),
+        };
+        let by_ref = binding.is_param && !ownership::is_copy(&binding.ty);
+        match event.mode {
+            Mode::Borrow if binding.mode == Mode::Shared => format!("&{}.borrow()", name),
+            Mode::Borrow if by_ref => format!("&*{}", name),
+            Mode::Borrow => format!("&{}", name),
+            Mode::BorrowMut if binding.mode == Mode::Shared => {
+                format!("&mut {}.borrow_mut()", name)
+            }
+            Mode::BorrowMut if by_ref => format!("&mut *{}", name),
+            Mode::BorrowMut => format!("&mut {}", name),
+            _ if binding.mode == Mode::Shared => format!("*{}.borrow()", name),
+            _ => name.to_string(),
+        }
+    }
+
+    /// Emits the expression `id` so that names are only looked at, never
+    /// moved.
+    fn expr(&mut self, id: ast::ExprId) -> Result<String, Error> {
+        let expr = self.body().expr(id)?;
+        Ok(match &expr.node {
+            ast::Expr::Literal(ast::Lit::Int(int)) => int.to_string(),
+            ast::Expr::Literal(ast::Lit::Float(float)) => format!("{:?}", float),
+            ast::Expr::Literal(ast::Lit::Str(string)) => format!("String::from(\"{}\")", string),
+            ast::Expr::Literal(ast::Lit::Bool(bool)) => bool.to_string(),
+            ast::Expr::Ident(name) => match self.event().1 {
+                Some(binding) if binding.mode == Mode::Shared => format!("*{}.borrow()", name),
+                Some(binding) if binding.is_param && !ownership::is_copy(&binding.ty) => {
+                    format!("*{}", name)
+                }
+                _ => name.to_string(),
+            },
+            ast::Expr::FnCall { fn_name, args } if ownership::is_builtin(fn_name) => {
+                let mut values = Vec::new();
+                for arg in args {
+                    values.push(self.expr(*arg)?);
+                }
+                // Failed assertions show the source of their operands.
+                let sources: Vec<_> = args
+                    .iter()
+                    .map(|arg| print::expr_source(self.body(), *arg))
+                    .collect();
+                match (fn_name.as_str(), &values[..]) {
+                    ("assert", [condition]) => format!(
+                        "assert!({}, \"Assertion failed: `{{}}`\", {:?})",
+                        condition, sources[0]
+                    ),
+                    ("assert_eq", [lhs, rhs]) => format!(
+                        "assert_eq!({}, {}, \"`{{}}` == `{{}}`\", {:?}, {:?})",
+                        lhs, rhs, sources[0], sources[1]
+                    ),
+                    ("hash", [value]) => {
+                        self.hash = true;
+                        format!("hash(&{})", value)
+                    }
+                    ("print", []) => "println!()".to_string(),
+                    ("print", values) => format!(
+                        "println!(\"{}\", {})",
+                        "{}".repeat(values.len()),
+                        values.join(", ")
+                    ),
+                    (name, values) => {
+                        return Err(Error::new(
+                            format!("`{}` can not be given {} arguments", name, values.len()),
+                            codes::E024,
+                            expr.span.clone(),
+                        ))
+                    }
+                }
+            }
+            ast::Expr::FnCall { fn_name, args } => {
+                let modes = self.ownership.param_modes(fn_name).unwrap_or_default();
+                let mut values = Vec::new();
+                for (index, arg) in args.iter().enumerate() {
+                    if let ast::Expr::Ident(name) = &self.body().expr(*arg)?.node {
+                        values.push(self.arg(name));
+                        continue;
+                    }
+                    values.push(match modes.get(index) {
+                        Some(Mode::Copy) => self.expr(*arg)?,
+                        Some(Mode::BorrowMut) => format!("&mut {}", self.operand(*arg)?),
+                        _ => format!("&{}", self.operand(*arg)?),
+                    });
+                }
+                format!("{}({})", fn_name, values.join(", "))
+            }
+            ast::Expr::PrefixOp { op, expr } => {
+                let value = self.operand(*expr)?;
+                match op {
+                    TokenKind::Minus | TokenKind::Bang => format!("{}{}", op, value),
+                    _ => value,
+                }
+            }
+            ast::Expr::PostfixOp { op, .. } => {
+                return Err(Error::new(
+                    format!("Postfix `{}` can not be emitted as Rust", op),
+                    codes::E003,
+                    expr.span.clone(),
+                ))
+            }
+            ast::Expr::Member {
+                expr: inner,
+                member,
+            } => {
+                let value = match self.expr(*inner)? {
+                    value if value.starts_with('*') => format!("({})", value),
+                    value => value,
+                };
+                let ty = self.ty(id);
+                match ty {
+                    Ty::Struct(name) if self.structs.get(name) == Some(&false) => {
+                        return Err(Error::new(
+                            format!(
+                                "`{}` is copied here, which needs `{}` to derive `Clone`",
+                                print::expr_source(self.body(), id),
+                                name
+                            ),
+                            codes::E040,
+                            expr.span.clone(),
+                        )
+                        .with_note("the Rust backend copies members it reads".to_string(), None))
+                    }
+                    ty if !ownership::is_copy(ty) => format!("{}.{}.clone()", value, member),
+                    _ => format!("{}.{}", value, member),
+                }
+            }
+            ast::Expr::StructLit {
+                struct_name,
+                fields,
+            } => {
+                let mut values = Vec::new();
+                for (member, value) in fields {
+                    values.push(format!("{}: {}", member, self.value(*value)?.0));
+                }
+                if values.is_empty() {
+                    format!("{} {{}}", struct_name)
+                } else {
+                    format!("{} {{ {} }}", struct_name, values.join(", "))
+                }
+            }
+            ast::Expr::InfixOp { op, lhs, rhs } => {
+                let ty = self.ty(*lhs);
+                let (lhs, rhs) = (self.operand(*lhs)?, self.operand(*rhs)?);
+                match op {
+                    TokenKind::Plus if *ty == Ty::Str => {
+                        format!("format!(\"{{}}{{}}\", {}, {})", lhs, rhs)
+                    }
+                    TokenKind::Caret if *ty == Ty::Float => format!("f64::powf({}, {})", lhs, rhs),
+                    TokenKind::Caret => format!("i32::pow({}, {} as u32)", lhs, rhs),
+                    op => format!("{} {} {}", lhs, op, rhs),
+                }
+            }
+        })
+    }
+
+    /// Like `expr`, with operators wrapped in parentheses.
+    fn operand(&mut self, id: ast::ExprId) -> Result<String, Error> {
+        let value = self.expr(id)?;
+        match self.body().expr(id)?.node {
+            ast::Expr::InfixOp { .. } => Ok(format!("({})", value)),
+            _ => Ok(value),
+        }
+    }
+}
@@ -0,0 +1,421 @@
+//! Builds the AST from a concrete syntax tree.
+//!
+//! The syntax tree is the only thing the AST comes from, so every command
+//! agrees on what is valid. The syntax errors are those of the parse; a node
+//! that lacks a part the AST needs, or that has tokens in it the parser
+//! skipped, becomes an `Err` with the first of them at or after its start.
+//! Where the AST can do without what is missing, like a `;` or a `)`, the
+//! node is built anyway, so what follows it can still be checked.
+
+use logos::Span;
+
+use crate::{
+    ast::{self, Spanned, Symbol},
+    db::Items,
+    error::{codes, Error},
+    lexer::TokenKind,
+};
+
+use super::{
+    parser::Parse,
+    view::{self, Item, Node},
+    NodeKind, SyntaxElement, SyntaxNode, SyntaxToken,
+};
+
+/// The items of the file `parse` is of.
+pub fn items(parse: &Parse) -> Items {
+    let mut lowering = Lowering {
+        errors: &parse.errors,
+        body: ast::Body::default(),
+    };
+    parse
+        .syntax()
+        .children()
+        .map(|node| match Item::cast(node.clone()) {
+            Some(Item::Fn(item)) => lowering.function(&item),
+            Some(Item::Struct(item)) => lowering.structure(&item),
+            None => Err(lowering.error(&node)),
+        })
+        .collect()
+}
+
+/// The span of `node` without the `///` comments it may start with.
+fn span(node: &SyntaxNode) -> Span {
+    let end = node.span().end;
+    let start = node
+        .children_with_tokens()
+        .into_iter()
+        .find(|child| !matches!(child, SyntaxElement::Token(token) if token.kind().is_trivia()))
+        .map_or(end, |child| child.span().start);
+    start..end
+}
+
+fn ident(token: &SyntaxToken) -> ast::Ident {
+    Spanned::new(Symbol::intern(token.text()), token.span())
+}
+
+/// The text of a string literal between its quotes.
+fn unquote(token: &SyntaxToken) -> String {
+    let text = token.text();
+    text[1..text.len() - 1].to_string()
+}
+
+/// Whether the parser skipped tokens in `node` itself, or in the list of
+/// elements `list` directly in it.
+fn skipped(node: &SyntaxNode, list: Option<NodeKind>) -> bool {
+    node.children().any(|child| {
+        child.kind() == NodeKind::Error
+            || Some(child.kind()) == list
+                && child
+                    .children()
+                    .any(|child| child.kind() == NodeKind::Error)
+    })
+}
+
+struct Lowering<'a> {
+    errors: &'a [Error],
+    /// The body of the function being lowered.
+    body: ast::Body,
+}
+
+impl<'a> Lowering<'a> {
+    /// The syntax error `node` failed with.
+    fn error(&self, node: &SyntaxNode) -> Error {
+        let span = span(node);
+        self.errors
+            .iter()
+            .find(|error| error.span().start >= span.start)
+            .cloned()
+            .unwrap_or_else(|| {
+                Error::new(
+                    "Expected the rest of this code".to_string(),
+                    codes::E001,
+                    span,
+                )
+            })
+    }
+
+    fn function(&mut self, item: &view::FnItem) -> Result<Spanned<ast::Item>, Error> {
+        let node = item.syntax();
+        let attrs = self.attrs(item.attrs())?;
+        let (name, body) = match (node.token(TokenKind::KwFn), item.name(), item.body()) {
+            (Some(_), Some(name), Some(body)) if !skipped(node, Some(NodeKind::ParamList)) => {
+                (name, body)
+            }
+            _ => return Err(self.error(node)),
+        };
+        let parameters = item
+            .params()
+            .iter()
+            .map(|param| match param.name() {
+                Some(name) => Ok((ident(&name), self.ty(param.ty(), param.syntax()))),
+                None => Err(self.error(param.syntax())),
+            })
+            .collect::<Result<_, _>>()?;
+        let return_type = node
+            .children()
+            .find(|child| child.kind() == NodeKind::RetType)
+            .map(|ret| self.ty(item.return_type(), &ret));
+
+        self.body = ast::Body::default();
+        let block = self.block(&body)?;
+        let body = ast::Body {
+            block,
+            ..std::mem::take(&mut self.body)
+        };
+        Ok(Spanned::new(
+            ast::Item::Function {
+                doc: item.doc(),
+                attrs,
+                name: ident(&name),
+                parameters,
+                return_type,
+                body,
+            },
+            span(node),
+        ))
+    }
+
+    fn structure(&mut self, item: &view::StructItem) -> Result<Spanned<ast::Item>, Error> {
+        let node = item.syntax();
+        if skipped(node, None) {
+            return Err(self.error(node));
+        }
+        let attrs = self.attrs(item.attrs())?;
+        let name = self.ty(item.name(), node);
+        let members = item
+            .members()
+            .iter()
+            .map(|member| {
+                Ok(ast::Member {
+                    doc: member.doc(),
+                    attrs: self.attrs(member.attrs())?,
+                    name: ident(&member.name().ok_or_else(|| self.error(member.syntax()))?),
+                    ty: self.ty(member.ty(), member.syntax()),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Spanned::new(
+            ast::Item::Struct {
+                doc: item.doc(),
+                attrs,
+                name,
+                members,
+            },
+            span(node),
+        ))
+    }
+
+    fn attrs(&self, attrs: Vec<view::Attr>) -> Result<ast::Attrs, Error> {
+        attrs
+            .iter()
+            .map(|attr| {
+                let node = attr.syntax();
+                let name = match attr.name() {
+                    Some(name) if !skipped(node, None) => name,
+                    _ => return Err(self.error(node)),
+                };
+                let args = attr
+                    .args()
+                    .iter()
+                    .map(|arg| {
+                        let node = match arg.kind() {
+                            TokenKind::String => ast::AttrArg::Str(unquote(arg)),
+                            _ => ast::AttrArg::Name(Symbol::intern(arg.text())),
+                        };
+                        Spanned::new(node, arg.span())
+                    })
+                    .collect();
+                Ok(Spanned::new(
+                    ast::Attr {
+                        name: ident(&name),
+                        args,
+                    },
+                    span(node),
+                ))
+            })
+            .collect()
+    }
+
+    /// The type `ty`, which is missing from `parent` if it is `None`.
+    fn ty(&self, ty: Option<view::Type>, parent: &SyntaxNode) -> Result<Spanned<ast::Type>, Error> {
+        let ty = ty.ok_or_else(|| self.error(parent))?;
+        let node = ty.syntax();
+        match ty.name() {
+            Some(name) if !skipped(node, Some(NodeKind::GenericArgs)) => Ok(Spanned::new(
+                ast::Type {
+                    name: ident(&name),
+                    generics: ty
+                        .generics()
+                        .iter()
+                        .map(|generic| self.ty(Some(generic.clone()), node))
+                        .collect(),
+                },
+                span(node),
+            )),
+            _ => Err(self.error(node)),
+        }
+    }
+
+    /// The statements of `block`, each with its attributes. The statements
+    /// the parser skipped are `Err`s.
+    fn block(&mut self, block: &view::Block) -> Result<Vec<ast::StmtId>, Error> {
+        let mut stmts = Vec::new();
+        for node in block.syntax().children() {
+            let stmt = match view::Stmt::cast(node.clone()) {
+                Some(stmt) => stmt,
+                None if node.kind() == NodeKind::Error => {
+                    stmts.push(self.body.stmts.alloc(Err(self.error(&node))));
+                    continue;
+                }
+                None => continue,
+            };
+            let attrs = self.attrs(stmt.attrs());
+            let id = match attrs {
+                Ok(attrs) => {
+                    let stmt = self.stmt(&stmt);
+                    let id = self.body.stmts.alloc(stmt);
+                    if !attrs.is_empty() {
+                        self.body.attrs.insert(id, attrs);
+                    }
+                    id
+                }
+                Err(err) => self.body.stmts.alloc(Err(err)),
+            };
+            stmts.push(id);
+        }
+        Ok(stmts)
+    }
+
+    fn stmt(&mut self, stmt: &view::Stmt) -> Result<Spanned<ast::Stmt>, Error> {
+        let node = stmt.syntax();
+        let stmt = match stmt {
+            view::Stmt::Let(stmt) => {
+                let name = stmt.name().ok_or_else(|| self.error(node))?;
+                ast::Stmt::Let {
+                    var_name: ident(&name),
+                    value: self.expr_in(stmt.value(), node),
+                }
+            }
+            view::Stmt::Assign(stmt) => {
+                let name = stmt.name().ok_or_else(|| self.error(node))?;
+                ast::Stmt::Assignment {
+                    var_name: ident(&name),
+                    value: self.expr_in(stmt.value(), node),
+                }
+            }
+            view::Stmt::Expr(stmt) => ast::Stmt::Expr {
+                expr: self.expr_in(stmt.expr(), node),
+            },
+            view::Stmt::If(stmt) => {
+                let condition = self.expr_in(stmt.condition(), node);
+                let body = stmt.body().ok_or_else(|| self.error(node))?;
+                let body = self.block(&body)?;
+                let else_stmt = match stmt.else_stmt() {
+                    Some(else_stmt) => {
+                        let else_stmt = self.stmt(&else_stmt);
+                        Some(self.body.stmts.alloc(else_stmt))
+                    }
+                    None => None,
+                };
+                ast::Stmt::IfStmt {
+                    condition,
+                    body,
+                    else_stmt,
+                }
+            }
+            view::Stmt::Return(stmt) => {
+                let value = match stmt.value() {
+                    Some(value) => Some(self.expr_in(Some(value), node)),
+                    // A value that failed to parse is not the same as none.
+                    None if node.token(TokenKind::SemiColon).is_none() => {
+                        return Err(self.error(node))
+                    }
+                    None => None,
+                };
+                ast::Stmt::Return { value }
+            }
+            view::Stmt::Block(block) => ast::Stmt::Block {
+                stmts: self.block(block)?,
+            },
+        };
+        Ok(Spanned::new(stmt, span(node)))
+    }
+
+    /// Adds the expression `expr` of `parent` to the body, or its error if
+    /// it is missing.
+    fn expr_in(&mut self, expr: Option<view::Expr>, parent: &SyntaxNode) -> ast::ExprId {
+        let expr = match expr {
+            Some(expr) => self.expr(&expr),
+            None => Err(self.error(parent)),
+        };
+        self.body.exprs.alloc(expr)
+    }
+
+    fn expr(&mut self, expr: &view::Expr) -> Result<Spanned<ast::Expr>, Error> {
+        let node = expr.syntax();
+        let expr = match expr {
+            view::Expr::Literal(literal) => {
+                let token = literal.token().ok_or_else(|| self.error(node))?;
+                let text = token.text();
+                ast::Expr::Literal(match token.kind() {
+                    TokenKind::Int => ast::Lit::Int(text.parse().map_err(|_| {
+                        Error::new(
+                            format!(
+                                "`{}` does not fit in an `int`, which is at most {}",
+                                text,
+                                i32::MAX
+                            ),
+                            codes::E034,
+                            token.span(),
+                        )
+                    })?),
+                    TokenKind::Float => {
+                        ast::Lit::Float(text.parse().map_err(|_| self.error(node))?)
+                    }
+                    TokenKind::String => ast::Lit::Str(unquote(&token)),
+                    kind => ast::Lit::Bool(kind == TokenKind::KwTrue),
+                })
+            }
+            view::Expr::NameRef(name) => {
+                ast::Expr::Ident(ident(&name.name().ok_or_else(|| self.error(node))?))
+            }
+            view::Expr::Call(call) => {
+                let name = call.name().ok_or_else(|| self.error(node))?;
+                if skipped(node, Some(NodeKind::ArgList)) {
+                    return Err(self.error(node));
+                }
+                let args = call
+                    .args()
+                    .iter()
+                    .map(|arg| {
+                        let arg = self.expr(arg);
+                        self.body.exprs.alloc(arg)
+                    })
+                    .collect();
+                ast::Expr::FnCall {
+                    fn_name: ident(&name),
+                    args,
+                }
+            }
+            view::Expr::Prefix(prefix) => {
+                let op = prefix.op().ok_or_else(|| self.error(node))?.kind();
+                ast::Expr::PrefixOp {
+                    op,
+                    expr: self.expr_in(prefix.expr(), node),
+                }
+            }
+            view::Expr::Infix(infix) => {
+                let op = match infix.op() {
+                    // `=` is reported as a comparison, see `parser::if_stmt`.
+                    Some(op) if op.kind() != TokenKind::Eq => op.kind(),
+                    _ => return Err(self.error(node)),
+                };
+                let lhs = self.expr_in(infix.lhs(), node);
+                let rhs = self.expr_in(infix.rhs(), node);
+                ast::Expr::InfixOp { op, lhs, rhs }
+            }
+            view::Expr::Postfix(postfix) => {
+                let op = postfix.op().ok_or_else(|| self.error(node))?.kind();
+                ast::Expr::PostfixOp {
+                    op,
+                    expr: self.expr_in(postfix.expr(), node),
+                }
+            }
+            view::Expr::Member(member) => {
+                let name = member.name().ok_or_else(|| self.error(node))?;
+                ast::Expr::Member {
+                    expr: self.expr_in(member.expr(), node),
+                    member: ident(&name),
+                }
+            }
+            view::Expr::StructLit(literal) => {
+                let name = literal.name().ok_or_else(|| self.error(node))?;
+                if skipped(node, None) {
+                    return Err(self.error(node));
+                }
+                let mut fields = Vec::new();
+                for field in literal.fields() {
+                    let field_node = field.syntax();
+                    let member = match field.name() {
+                        Some(member) if field_node.token(TokenKind::Colon).is_some() => member,
+                        _ => return Err(self.error(field_node)),
+                    };
+                    fields.push((ident(&member), self.expr_in(field.value(), field_node)));
+                }
+                ast::Expr::StructLit {
+                    struct_name: ident(&name),
+                    fields,
+                }
+            }
+            // There is no AST node for grouped expressions.
+            view::Expr::Paren(paren) => {
+                return match paren.expr() {
+                    Some(expr) => self.expr(&expr),
+                    None => Err(self.error(node)),
+                }
+            }
+        };
+        Ok(Spanned::new(expr, span(node)))
+    }
+}
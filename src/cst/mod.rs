@@ -0,0 +1,376 @@
+//! A lossless concrete syntax tree.
+//!
+//! Unlike `ast`, the tree keeps every token of the source, whitespace and
+//! comments included, so its text is the source byte for byte. It comes in
+//! two layers, like the trees of rust-analyzer:
+//!
+//! - Green nodes are immutable and only know their kind, their width and
+//!   their children. They are reference counted, so an edited tree shares
+//!   every untouched subtree with the original.
+//! - Red `SyntaxNode`s are created on demand while walking a green tree and
+//!   add what depends on the position: the parent and the absolute offset.
+//!
+//! `parser::parse` builds the tree, `Parse::reparse` updates it after an
+//! edit, `view` puts typed accessors on top of it and `lower` builds the
+//! AST from those.
+
+use std::fmt::{self, Write};
+use std::rc::Rc;
+
+use logos::Span;
+
+use crate::lexer::TokenKind;
+
+pub mod lower;
+pub mod parser;
+pub mod reparse;
+mod tokens;
+pub mod view;
+
+pub use parser::parse;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    File,
+    FnItem,
+    /// `#[name]` or `#[name(args)]`, at the start of an item, member or
+    /// statement.
+    Attr,
+    StructItem,
+    ParamList,
+    Param,
+    RetType,
+    Member,
+    Type,
+    GenericArgs,
+    Block,
+    LetStmt,
+    AssignStmt,
+    ExprStmt,
+    IfStmt,
+    ElseBranch,
+    ReturnStmt,
+    Literal,
+    NameRef,
+    CallExpr,
+    ArgList,
+    PrefixExpr,
+    InfixExpr,
+    PostfixExpr,
+    /// `expr.name`.
+    MemberExpr,
+    ParenExpr,
+    /// `Name { name: expr, ... }`.
+    StructLit,
+    /// A `name: expr` of a `StructLit`.
+    Field,
+    /// Tokens the parser could not make sense of.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenToken {
+    pub kind: TokenKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.len(),
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenNode {
+    kind: NodeKind,
+    /// Length of the node's text in bytes.
+    len: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: NodeKind, children: Vec<GreenElement>) -> Self {
+        let len = children.iter().map(GreenElement::len).sum();
+        Self {
+            kind,
+            len,
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+
+    fn write_text(&self, out: &mut String) {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => node.write_text(out),
+                GreenElement::Token(token) => out.push_str(&token.text),
+            }
+        }
+    }
+}
+
+/// Builds a green tree bottom up. Nodes are started before their children
+/// are added, or, when it is only clear afterwards that some children
+/// belong to a node, at a checkpoint taken before them.
+#[derive(Default)]
+pub struct Builder {
+    /// The kinds of the unfinished nodes, with the index in `children`
+    /// their first child is at.
+    parents: Vec<(NodeKind, usize)>,
+    children: Vec<GreenElement>,
+}
+
+impl Builder {
+    pub fn start_node(&mut self, kind: NodeKind) {
+        self.parents.push((kind, self.children.len()));
+    }
+
+    pub fn checkpoint(&self) -> usize {
+        self.children.len()
+    }
+
+    pub fn start_node_at(&mut self, checkpoint: usize, kind: NodeKind) {
+        assert!(checkpoint <= self.children.len(), "checkpoint is gone");
+        self.parents.push((kind, checkpoint));
+    }
+
+    /// How far the tree is built, to `rewind` to.
+    pub fn mark(&self) -> (usize, usize) {
+        (self.parents.len(), self.children.len())
+    }
+
+    /// Drops the nodes started and the children added since `mark`.
+    pub fn rewind(&mut self, (parents, children): (usize, usize)) {
+        self.parents.truncate(parents);
+        self.children.truncate(children);
+    }
+
+    pub fn token(&mut self, kind: TokenKind, text: &str) {
+        self.children.push(GreenElement::Token(Rc::new(GreenToken {
+            kind,
+            text: text.to_string(),
+        })));
+    }
+
+    pub fn finish_node(&mut self) {
+        let (kind, first) = self.parents.pop().expect("no node to finish");
+        let children = self.children.split_off(first);
+        self.children
+            .push(GreenElement::Node(Rc::new(GreenNode::new(kind, children))));
+    }
+
+    /// The root, once every node is finished.
+    pub fn finish(mut self) -> Rc<GreenNode> {
+        assert!(self.parents.is_empty(), "unfinished nodes");
+        match (self.children.pop(), self.children.is_empty()) {
+            (Some(GreenElement::Node(root)), true) => root,
+            _ => panic!("the tree must have exactly one root node"),
+        }
+    }
+}
+
+/// A node of a green tree at a position in it.
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    /// Index in the parent's children.
+    index: usize,
+    offset: usize,
+}
+
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    offset: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> Self {
+        Self(Rc::new(NodeData {
+            green,
+            parent: None,
+            index: 0,
+            offset: 0,
+        }))
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<&SyntaxNode> {
+        self.0.parent.as_ref()
+    }
+
+    pub fn span(&self) -> Span {
+        self.0.offset..self.0.offset + self.0.green.len()
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        self.0.green.write_text(&mut text);
+        text
+    }
+
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        let mut children = Vec::new();
+        for (index, child) in self.0.green.children.iter().enumerate() {
+            children.push(match child {
+                GreenElement::Node(green) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                    green: green.clone(),
+                    parent: Some(self.clone()),
+                    index,
+                    offset,
+                }))),
+                GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                    green: green.clone(),
+                    offset,
+                }),
+            });
+            offset += child.len();
+        }
+        children
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = SyntaxNode> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+    }
+
+    /// The tokens directly in this node, trivia included.
+    pub fn tokens(&self) -> impl Iterator<Item = SyntaxToken> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(_) => None,
+                SyntaxElement::Token(token) => Some(token),
+            })
+    }
+
+    /// The first token of the given kind directly in this node.
+    pub fn token(&self, kind: TokenKind) -> Option<SyntaxToken> {
+        self.tokens().find(|token| token.kind() == kind)
+    }
+
+    /// A copy of the whole tree with this node replaced by `green`. The
+    /// result is the new root; only the nodes on the path to it are new.
+    pub fn replace_with(&self, mut green: Rc<GreenNode>) -> SyntaxNode {
+        let mut node = self.clone();
+        while let Some(parent) = node.parent().cloned() {
+            let mut children = parent.0.green.children.clone();
+            children[node.0.index] = GreenElement::Node(green);
+            green = Rc::new(GreenNode::new(parent.kind(), children));
+            node = parent;
+        }
+        SyntaxNode::new_root(green)
+    }
+
+    /// The tree as an indented list of nodes and tokens with their spans.
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        self.dump_into(&mut out, 0);
+        out
+    }
+
+    fn dump_into(&self, out: &mut String, depth: usize) {
+        let indent = "  ".repeat(depth);
+        writeln!(out, "{}{:?}", indent, self).unwrap();
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node) => node.dump_into(out, depth + 1),
+                SyntaxElement::Token(token) => {
+                    writeln!(out, "{}  {:?}", indent, token).unwrap();
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.span();
+        write!(f, "{:?}@{}..{}", self.kind(), span.start, span.end)
+    }
+}
+
+/// The source text of the node.
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text())
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> TokenKind {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    pub fn span(&self) -> Span {
+        self.offset..self.offset + self.green.text.len()
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.span();
+        write!(
+            f,
+            "{:?}@{}..{} {:?}",
+            self.kind(),
+            span.start,
+            span.end,
+            self.text()
+        )
+    }
+}
+
+impl SyntaxElement {
+    pub fn span(&self) -> Span {
+        match self {
+            SyntaxElement::Node(node) => node.span(),
+            SyntaxElement::Token(token) => token.span(),
+        }
+    }
+}
@@ -0,0 +1,833 @@
+//! Parses source into a concrete syntax tree.
+//!
+//! This is the only parser of fir: the AST is built from its tree, see
+//! `lower`. It never gives up: whatever it can not make sense of ends up in
+//! `Error` nodes, so the tree always holds the whole source. Trivia is
+//! attached to the innermost node that is open when the token after it is
+//! consumed, which keeps it out of the nodes that end before it. Only `///`
+//! comments go the other way: they start the node of the item or member they
+//! document.
+
+use std::rc::Rc;
+
+use logos::{Logos, Span};
+
+use crate::{
+    error::{codes, Code, Error},
+    lexer::{self, TokenKind},
+};
+
+use super::{tokens::TokenStream, Builder, GreenElement, GreenNode, NodeKind, SyntaxNode};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parse {
+    pub green: Rc<GreenNode>,
+    pub errors: Vec<Error>,
+}
+
+impl Parse {
+    pub fn syntax(&self) -> SyntaxNode {
+        SyntaxNode::new_root(self.green.clone())
+    }
+}
+
+pub fn parse(input: &str) -> Parse {
+    let mut parser = Parser::new(input, 0);
+    parser.file();
+    Parse {
+        green: parser.builder.finish(),
+        errors: parser.errors,
+    }
+}
+
+/// Parses `input`, which starts at `offset` in the file, as a single block
+/// or item. Gives up unless that takes all of `input` and the node is
+/// closed by its `}`, as then the node is what parsing the whole file would
+/// give for it.
+pub(super) fn parse_node(
+    kind: NodeKind,
+    input: &str,
+    offset: usize,
+) -> Option<(Rc<GreenNode>, Vec<Error>)> {
+    let mut parser = Parser::new(input, offset);
+    let first = match kind {
+        NodeKind::Block => TokenKind::LBrace,
+        NodeKind::FnItem => TokenKind::KwFn,
+        NodeKind::StructItem => TokenKind::KwStruct,
+        _ => return None,
+    };
+    // A documented item starts with its `///` comments, and an item with
+    // attributes with the first of them.
+    let first_token = parser.tokens.rest().first().map(|(kind, _)| *kind);
+    let documented = kind != NodeKind::Block && first_token == Some(TokenKind::DocComment);
+    let first = match kind {
+        NodeKind::FnItem | NodeKind::StructItem if parser.at(TokenKind::Hash) => TokenKind::Hash,
+        _ => first,
+    };
+    if !documented && first_token != Some(first) || !parser.at(first) {
+        return None;
+    }
+    match kind {
+        NodeKind::Block => parser.block(),
+        NodeKind::FnItem => parser.fn_item(),
+        _ => parser.struct_item(),
+    }
+    if !parser.tokens.rest().is_empty() {
+        return None;
+    }
+    let green = parser.builder.finish();
+    closed(&green).then_some((green, parser.errors))
+}
+
+/// Whether the last token of a block or item is its closing brace.
+pub(super) fn closed(node: &GreenNode) -> bool {
+    match node.children().last() {
+        Some(GreenElement::Token(token)) => token.kind == TokenKind::RBrace,
+        Some(GreenElement::Node(body)) if node.kind() == NodeKind::FnItem => closed(body),
+        _ => false,
+    }
+}
+
+/// Every token of `input`, including trivia. The lexer covers the input
+/// without gaps, but any it leaves are filled with `Error` tokens so no text
+/// can get lost.
+fn lex(input: &str) -> Vec<(TokenKind, Span)> {
+    let mut tokens = Vec::new();
+    let mut end = 0;
+    for (kind, span) in TokenKind::lexer(input).spanned() {
+        if span.start > end {
+            tokens.push((TokenKind::Error, end..span.start));
+        }
+        end = span.end;
+        tokens.push((kind, span));
+    }
+    if end < input.len() {
+        tokens.push((TokenKind::Error, end..input.len()));
+    }
+    tokens
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    /// Where `input` starts in the file, for the spans of errors.
+    offset: usize,
+    tokens: TokenStream,
+    builder: Builder,
+    errors: Vec<Error>,
+}
+
+/// A position of the parser, see `Parser::snapshot`.
+#[derive(Debug, Clone, Copy)]
+struct Snapshot {
+    pos: usize,
+    errors: usize,
+    tree: (usize, usize),
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str, offset: usize) -> Self {
+        Self {
+            input,
+            offset,
+            tokens: TokenStream::new(lex(input), input.len()),
+            builder: Builder::default(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// The kind of the `n`th token ahead that is not trivia.
+    fn nth(&self, n: usize) -> TokenKind {
+        self.tokens.nth(n)
+    }
+
+    fn current(&self) -> TokenKind {
+        self.nth(0)
+    }
+
+    fn at(&self, kind: TokenKind) -> bool {
+        self.current() == kind
+    }
+
+    fn eat_trivia(&mut self) {
+        while self
+            .tokens
+            .rest()
+            .first()
+            .is_some_and(|(kind, _)| kind.is_trivia())
+        {
+            let (kind, span) = self.tokens.bump().unwrap();
+            self.builder.token(kind, &self.input[span]);
+        }
+    }
+
+    /// Adds the current token to the open node, if there is one left. An
+    /// `Error` token is reported here, with what the lexer found wrong.
+    fn bump(&mut self) {
+        self.eat_trivia();
+        if let Some((kind, span)) = self.tokens.bump() {
+            if kind == TokenKind::Error {
+                let error = lexer::error(self.input, span.clone());
+                self.errors.push(error.moved(self.offset as isize));
+            }
+            self.builder.token(kind, &self.input[span]);
+        }
+    }
+
+    fn start_node(&mut self, kind: NodeKind) {
+        self.eat_trivia();
+        self.builder.start_node(kind);
+    }
+
+    /// Starts a node that takes in the `///` comments right before it, and
+    /// whatever trivia is between them and its first token.
+    fn start_documented(&mut self, kind: NodeKind) {
+        let rest = self.tokens.rest();
+        let trivia = rest.iter().take_while(|(kind, _)| kind.is_trivia()).count();
+        let doc = rest[..trivia]
+            .iter()
+            .position(|(kind, _)| *kind == TokenKind::DocComment)
+            .unwrap_or(trivia);
+        for _ in 0..doc {
+            let (kind, span) = self.tokens.bump().unwrap();
+            self.builder.token(kind, &self.input[span]);
+        }
+        self.builder.start_node(kind);
+    }
+
+    fn checkpoint(&mut self) -> usize {
+        self.eat_trivia();
+        self.builder.checkpoint()
+    }
+
+    fn finish_node(&mut self) {
+        self.builder.finish_node();
+    }
+
+    /// Where parsing is now, to `rewind` to if what follows turns out not to
+    /// be what it was tried as.
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            pos: self.tokens.pos(),
+            errors: self.errors.len(),
+            tree: self.builder.mark(),
+        }
+    }
+
+    /// Goes back to `snapshot`, dropping the tokens, nodes and errors added
+    /// since.
+    fn rewind(&mut self, snapshot: Snapshot) {
+        self.tokens.rewind(snapshot.pos);
+        self.errors.truncate(snapshot.errors);
+        self.builder.rewind(snapshot.tree);
+    }
+
+    /// The span of the current token, or an empty one at the end of input.
+    fn span(&self) -> Span {
+        self.tokens.nth_span(0)
+    }
+
+    /// Reports an error at the current token, unless that is an `Error`
+    /// token, which `bump` reports instead.
+    fn error(&mut self, msg: String, code: &'static Code) {
+        if self.at(TokenKind::Error) {
+            return;
+        }
+        let span = self.span();
+        let span = self.offset + span.start..self.offset + span.end;
+        self.errors.push(Error::new(msg, code, span));
+    }
+
+    /// Adds a fix to the last error, replacing `span` with `text`.
+    fn fix(&mut self, msg: String, span: Span, text: &str) {
+        let span = self.offset + span.start..self.offset + span.end;
+        let error = self.errors.pop().expect("no error to fix");
+        self.errors
+            .push(error.with_fix(msg, span, text.to_string()));
+    }
+
+    /// Where the last token that is not trivia ends.
+    fn prev_end(&self) -> usize {
+        self.tokens.current_span().end
+    }
+
+    /// Consumes a token of the given kind, or reports that it is missing.
+    fn expect(&mut self, kind: TokenKind) -> bool {
+        if self.at(kind) {
+            self.bump();
+            true
+        } else {
+            let found = self.current();
+            self.error(
+                format!("Expected to consume {} but got {:#?}", kind, found),
+                codes::E001,
+            );
+            if closes(kind) && found != TokenKind::Error {
+                let end = self.prev_end();
+                self.fix(format!("Insert `{}`", kind), end..end, &kind.to_string());
+            }
+            false
+        }
+    }
+
+    /// Consumes the identifier naming a `what`, or reports that it is missing.
+    fn expect_name(&mut self, what: &str) -> bool {
+        if self.at(TokenKind::Ident) {
+            self.bump();
+            true
+        } else {
+            let found = self.current();
+            self.error(
+                format!("Expected identifier as {}, but found `{:#?}`", what, found),
+                codes::E008,
+            );
+            false
+        }
+    }
+
+    /// Moves the current token into an `Error` node.
+    fn bump_error(&mut self) {
+        self.start_node(NodeKind::Error);
+        self.bump();
+        self.finish_node();
+    }
+
+    /// Moves tokens into an `Error` node up to one `stop` accepts, at
+    /// least the current one.
+    fn bump_errors(&mut self, stop: fn(TokenKind) -> bool) {
+        self.start_node(NodeKind::Error);
+        self.bump();
+        while !self.at(TokenKind::EOF) && !stop(self.current()) {
+            self.bump();
+        }
+        self.finish_node();
+    }
+
+    /// Parses a list of elements up to `close`, separated by optional
+    /// commas. `element` returns false, without consuming anything, if the
+    /// current token can not start an element; that token is then skipped.
+    /// A token that only appears after the list, like the `{` of a body,
+    /// ends it early.
+    fn list(&mut self, close: TokenKind, element: fn(&mut Self) -> bool) {
+        while !self.at(close) && !self.at(TokenKind::EOF) && !ends_list(self.current()) {
+            if !element(self) {
+                self.bump_error();
+                continue;
+            }
+            if self.at(TokenKind::Comma) {
+                self.bump();
+            }
+        }
+        self.expect(close);
+    }
+
+    fn file(&mut self) {
+        self.builder.start_node(NodeKind::File);
+        while !self.at(TokenKind::EOF) {
+            match self.current() {
+                TokenKind::KwFn => self.fn_item(),
+                TokenKind::KwStruct => self.struct_item(),
+                TokenKind::Hash if self.after_attrs() == TokenKind::KwStruct => self.struct_item(),
+                TokenKind::Hash => self.fn_item(),
+                kind => {
+                    self.error(format!("Unknown start of item: `{}`", kind), codes::E010);
+                    self.bump_errors(|kind| starts_item(kind) || kind == TokenKind::Hash);
+                }
+            }
+        }
+        self.eat_trivia();
+        self.finish_node();
+    }
+
+    fn fn_item(&mut self) {
+        self.start_documented(NodeKind::FnItem);
+        self.attrs();
+        if !self.at(TokenKind::KwFn) {
+            let found = self.current();
+            self.error(
+                format!("Expected an item after attributes, but found `{}`", found),
+                codes::E010,
+            );
+            self.finish_node();
+            return;
+        }
+        self.bump();
+        self.expect_name("function name");
+
+        self.start_node(NodeKind::ParamList);
+        if self.expect(TokenKind::LParen) {
+            self.list(TokenKind::RParen, Self::param);
+        }
+        self.finish_node();
+
+        if self.at(TokenKind::Arrow) {
+            self.start_node(NodeKind::RetType);
+            self.bump();
+            self.type_();
+            self.finish_node();
+        }
+        self.block();
+        self.finish_node();
+    }
+
+    /// The attributes before an item, member or statement, in the node of
+    /// what they are on. Which are known is checked on the AST, see
+    /// `crate::attrs`.
+    fn attrs(&mut self) {
+        while self.at(TokenKind::Hash) {
+            self.start_node(NodeKind::Attr);
+            self.bump();
+            if self.expect(TokenKind::LSquare) && self.expect_name("attribute") {
+                if self.at(TokenKind::LParen) {
+                    self.bump();
+                    self.list(TokenKind::RParen, |parser| {
+                        let is_arg =
+                            matches!(parser.current(), TokenKind::Ident | TokenKind::String);
+                        if is_arg {
+                            parser.bump();
+                        } else {
+                            let found = parser.current();
+                            parser.error(
+                                format!(
+                                    "Expected a name or a string as argument of an attribute, but found {}",
+                                    found
+                                ),
+                                codes::E020,
+                            );
+                        }
+                        is_arg
+                    });
+                }
+                self.expect(TokenKind::RSquare);
+            }
+            self.finish_node();
+        }
+    }
+
+    /// The kind of the first token after the attributes at the current one.
+    fn after_attrs(&self) -> TokenKind {
+        let mut tokens = self
+            .tokens
+            .rest()
+            .iter()
+            .map(|(kind, _)| *kind)
+            .filter(|kind| !kind.is_trivia());
+        loop {
+            match tokens.next() {
+                Some(TokenKind::Hash) => {
+                    if !tokens.any(|kind| kind == TokenKind::RSquare) {
+                        return TokenKind::EOF;
+                    }
+                }
+                Some(kind) => return kind,
+                None => return TokenKind::EOF,
+            }
+        }
+    }
+
+    fn param(&mut self) -> bool {
+        self.named_type(NodeKind::Param, "function parameter")
+    }
+
+    fn struct_item(&mut self) {
+        self.start_documented(NodeKind::StructItem);
+        self.attrs();
+        self.bump();
+        self.type_();
+        if self.expect(TokenKind::LBrace) {
+            self.list(TokenKind::RBrace, Self::member);
+        }
+        self.finish_node();
+    }
+
+    fn member(&mut self) -> bool {
+        self.named_type(NodeKind::Member, "struct member")
+    }
+
+    /// A `name: Type` pair, for parameters and members.
+    /// Members can have attributes.
+    fn named_type(&mut self, kind: NodeKind, what: &str) -> bool {
+        let attributed = kind == NodeKind::Member && self.at(TokenKind::Hash);
+        if !self.at(TokenKind::Ident) && !attributed {
+            // Reports the missing name.
+            return self.expect_name(what);
+        }
+        if kind == NodeKind::Member {
+            self.start_documented(kind);
+            self.attrs();
+        } else {
+            self.start_node(kind);
+        }
+        self.expect_name(what);
+        self.expect(TokenKind::Colon);
+        self.type_();
+        self.finish_node();
+        true
+    }
+
+    fn type_(&mut self) {
+        if !self.at(TokenKind::Ident) {
+            let found = self.current();
+            self.error(
+                format!(
+                    "Expected identifier as start of type, but found `{:#?}`",
+                    found
+                ),
+                codes::E009,
+            );
+            return;
+        }
+        self.start_node(NodeKind::Type);
+        self.bump();
+        if self.at(TokenKind::LAngle) {
+            self.start_node(NodeKind::GenericArgs);
+            self.bump();
+            self.list(TokenKind::RAngle, |parser| {
+                let is_type = parser.at(TokenKind::Ident);
+                parser.type_();
+                is_type
+            });
+            self.finish_node();
+        }
+        self.finish_node();
+    }
+
+    fn block(&mut self) {
+        let checkpoint = self.checkpoint();
+        self.block_at(checkpoint);
+    }
+
+    /// A block whose node starts at `checkpoint`, before its attributes.
+    fn block_at(&mut self, checkpoint: usize) {
+        self.builder.start_node_at(checkpoint, NodeKind::Block);
+        if self.expect(TokenKind::LBrace) {
+            // A keyword starting an item means the closing brace is missing.
+            while !self.at(TokenKind::RBrace)
+                && !self.at(TokenKind::EOF)
+                && !starts_item(self.current())
+            {
+                self.statement();
+            }
+            self.expect(TokenKind::RBrace);
+        }
+        self.finish_node();
+    }
+
+    /// A statement, whose node starts at its attributes.
+    fn statement(&mut self) {
+        let checkpoint = self.checkpoint();
+        let attributed = self.at(TokenKind::Hash);
+        self.attrs();
+        match self.current() {
+            TokenKind::KwLet => {
+                self.builder.start_node_at(checkpoint, NodeKind::LetStmt);
+                self.bump();
+                self.expect_name("`let` binding");
+                self.expect(TokenKind::Eq);
+                self.expression(0);
+                self.expect(TokenKind::SemiColon);
+                self.finish_node();
+            }
+            TokenKind::Ident if self.nth(1) == TokenKind::LParen => {
+                self.builder.start_node_at(checkpoint, NodeKind::ExprStmt);
+                self.expression(0);
+                self.expect(TokenKind::SemiColon);
+                self.finish_node();
+            }
+            TokenKind::Ident => {
+                self.builder.start_node_at(checkpoint, NodeKind::AssignStmt);
+                self.bump();
+                self.expect(TokenKind::Eq);
+                self.expression(0);
+                self.expect(TokenKind::SemiColon);
+                self.finish_node();
+            }
+            TokenKind::KwIf => self.if_stmt(checkpoint),
+            TokenKind::KwReturn => {
+                self.builder.start_node_at(checkpoint, NodeKind::ReturnStmt);
+                self.bump();
+                if !self.at(TokenKind::SemiColon) {
+                    self.expression(0);
+                }
+                self.expect(TokenKind::SemiColon);
+                self.finish_node();
+            }
+            TokenKind::LBrace => self.block_at(checkpoint),
+            // Left for the block to close.
+            TokenKind::RBrace if attributed => {
+                self.error(
+                    "Expected a statement after attributes, but found `}`".to_string(),
+                    codes::E002,
+                );
+                self.builder.start_node_at(checkpoint, NodeKind::Error);
+                self.finish_node();
+            }
+            kind => {
+                self.error(format!("Unknown start of statement {}", kind), codes::E002);
+                // Skips to the end of the statement, or up to the start of
+                // the next one if there is no semicolon.
+                self.builder.start_node_at(checkpoint, NodeKind::Error);
+                while !self.at(TokenKind::EOF)
+                    && !self.at(TokenKind::RBrace)
+                    && !starts_stmt(self.current())
+                    && !starts_item(self.current())
+                {
+                    let end = self.at(TokenKind::SemiColon);
+                    self.bump();
+                    if end {
+                        break;
+                    }
+                }
+                self.finish_node();
+            }
+        }
+    }
+
+    fn if_stmt(&mut self, checkpoint: usize) {
+        self.builder.start_node_at(checkpoint, NodeKind::IfStmt);
+        self.bump();
+        self.expect(TokenKind::LParen);
+        let checkpoint = self.checkpoint();
+        self.expression(0);
+        if self.at(TokenKind::Eq) {
+            let span = self.span();
+            self.error(
+                "Expected `==` to compare, but found `=`".to_string(),
+                codes::E013,
+            );
+            self.fix("Compare with `==`".to_string(), span, "==");
+            self.builder.start_node_at(checkpoint, NodeKind::InfixExpr);
+            self.bump();
+            self.expression(0);
+            self.finish_node();
+        }
+        self.expect(TokenKind::RParen);
+        self.block();
+        if self.at(TokenKind::KwElse) {
+            self.start_node(NodeKind::ElseBranch);
+            self.bump();
+            if self.at(TokenKind::KwIf) {
+                let checkpoint = self.checkpoint();
+                self.if_stmt(checkpoint);
+            } else {
+                self.block();
+            }
+            self.finish_node();
+        }
+        self.finish_node();
+    }
+
+    fn expression(&mut self, binding_power: u8) {
+        if !self.at_expression() {
+            // Left for the enclosing production to skip, so it can
+            // resynchronise on its terminator.
+            let found = self.current();
+            self.error(
+                format!("Unknown start of expression: `{}`", found),
+                codes::E011,
+            );
+            return;
+        }
+        let checkpoint = self.checkpoint();
+        match self.current() {
+            TokenKind::Int
+            | TokenKind::Float
+            | TokenKind::String
+            | TokenKind::KwTrue
+            | TokenKind::KwFalse => {
+                self.start_node(NodeKind::Literal);
+                self.bump();
+                self.finish_node();
+            }
+            TokenKind::Ident if self.nth(1) == TokenKind::LParen => {
+                self.start_node(NodeKind::CallExpr);
+                self.bump();
+                self.start_node(NodeKind::ArgList);
+                self.bump();
+                self.list(TokenKind::RParen, |parser| {
+                    let is_expression = parser.at_expression();
+                    parser.expression(0);
+                    is_expression
+                });
+                self.finish_node();
+                self.finish_node();
+            }
+            TokenKind::Ident if self.nth(1) == TokenKind::LBrace => self.struct_lit(),
+            TokenKind::Ident => self.name_ref(),
+            TokenKind::LParen => {
+                self.start_node(NodeKind::ParenExpr);
+                self.bump();
+                self.expression(0);
+                self.expect(TokenKind::RParen);
+                self.finish_node();
+            }
+            op @ (TokenKind::Plus | TokenKind::Minus | TokenKind::Bang) => {
+                self.start_node(NodeKind::PrefixExpr);
+                self.bump();
+                let ((), right_binding_power) = op.prefix_binding_power();
+                self.expression(right_binding_power);
+                self.finish_node();
+            }
+            kind => unreachable!("Unknown start of expression: `{}`", kind),
+        }
+
+        loop {
+            let op = self.current();
+            if let Some((left_binding_power, ())) = op.postfix_binding_power() {
+                if left_binding_power < binding_power {
+                    break;
+                }
+                if op == TokenKind::Dot {
+                    self.builder.start_node_at(checkpoint, NodeKind::MemberExpr);
+                    self.bump();
+                    self.expect_name("member");
+                } else {
+                    self.builder
+                        .start_node_at(checkpoint, NodeKind::PostfixExpr);
+                    self.bump();
+                }
+                self.finish_node();
+                continue;
+            }
+            if let Some((left_binding_power, right_binding_power)) = op.infix_binding_power() {
+                if left_binding_power < binding_power {
+                    break;
+                }
+                self.builder.start_node_at(checkpoint, NodeKind::InfixExpr);
+                self.bump();
+                self.expression(right_binding_power);
+                self.finish_node();
+                continue;
+            }
+            break;
+        }
+    }
+
+    fn name_ref(&mut self) {
+        self.start_node(NodeKind::NameRef);
+        self.bump();
+        self.finish_node();
+    }
+
+    /// A struct literal, `Point { x: 1, y: 2 }`. A name followed by a
+    /// block, as in `let a = b` missing its `;` before one, is tried as a
+    /// literal as well; unless the braces are empty or start with a
+    /// `name:`, that is taken back and the name is left on its own.
+    fn struct_lit(&mut self) {
+        let snapshot = self.snapshot();
+        self.start_node(NodeKind::StructLit);
+        self.bump();
+        self.bump();
+        let field = self.at(TokenKind::Ident) && self.nth(1) == TokenKind::Colon;
+        if !field && !self.at(TokenKind::RBrace) {
+            self.rewind(snapshot);
+            self.name_ref();
+            return;
+        }
+        self.list(TokenKind::RBrace, Self::field);
+        self.finish_node();
+    }
+
+    /// A `name: value` of a struct literal.
+    fn field(&mut self) -> bool {
+        if !self.at(TokenKind::Ident) {
+            return false;
+        }
+        self.start_node(NodeKind::Field);
+        self.bump();
+        self.expect(TokenKind::Colon);
+        self.expression(0);
+        self.finish_node();
+        true
+    }
+
+    fn at_expression(&self) -> bool {
+        matches!(
+            self.current(),
+            TokenKind::Int
+                | TokenKind::Float
+                | TokenKind::String
+                | TokenKind::KwTrue
+                | TokenKind::KwFalse
+                | TokenKind::Ident
+                | TokenKind::LParen
+                | TokenKind::Plus
+                | TokenKind::Minus
+                | TokenKind::Bang
+        )
+    }
+}
+
+fn starts_item(kind: TokenKind) -> bool {
+    matches!(kind, TokenKind::KwFn | TokenKind::KwStruct)
+}
+
+fn starts_stmt(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::KwLet | TokenKind::KwIf | TokenKind::KwReturn
+    )
+}
+
+/// Whether a missing `kind` can be inserted after the last token, as it
+/// ends what comes before it.
+fn closes(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::SemiColon | TokenKind::RParen | TokenKind::RBrace
+    )
+}
+
+fn ends_list(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::LBrace | TokenKind::RBrace | TokenKind::SemiColon
+    ) || starts_item(kind)
+        || starts_stmt(kind)
+}
+
+pub trait Operator {
+    /// Prefix operators bind their operand to the right.
+    fn prefix_binding_power(&self) -> ((), u8);
+
+    /// Infix operators bind two operands, lhs and rhs.
+    fn infix_binding_power(&self) -> Option<(u8, u8)>;
+
+    /// Postfix operators bind their operand to the left.
+    fn postfix_binding_power(&self) -> Option<(u8, ())>;
+}
+
+impl Operator for TokenKind {
+    fn prefix_binding_power(&self) -> ((), u8) {
+        match self {
+            TokenKind::Plus | TokenKind::Minus | TokenKind::Bang => ((), 51),
+            // Prefixes are the only operators we have already seen
+            // when we call this, so we know the token must be
+            // one of the above
+            _ => unreachable!("Not a prefix operator: {:?}", self),
+        }
+    }
+
+    fn infix_binding_power(&self) -> Option<(u8, u8)> {
+        let result = match self {
+            TokenKind::Or => (1, 2),
+            TokenKind::And => (3, 4),
+            TokenKind::Eqq | TokenKind::Neq => (5, 6),
+            TokenKind::LAngle | TokenKind::RAngle | TokenKind::Leq | TokenKind::Geq => (7, 8),
+            TokenKind::Plus | TokenKind::Minus => (9, 10),
+            TokenKind::Times | TokenKind::Slash => (11, 12),
+            TokenKind::Caret => (22, 21), // <- This binds stronger to the left!
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    fn postfix_binding_power(&self) -> Option<(u8, ())> {
+        let result = match self {
+            TokenKind::Bang => (101, ()),
+            // Member access, `p.x`.
+            TokenKind::Dot => (103, ()),
+            _ => return None,
+        };
+        Some(result)
+    }
+}
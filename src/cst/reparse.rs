@@ -0,0 +1,287 @@
+//! Incremental reparsing after an edit.
+//!
+//! Only the innermost block or item around the edit is parsed again and the
+//! new subtree replaces the old one, so every other green node is shared
+//! with the tree before the edit. An edit that reaches the first or last
+//! character of a block or item, or that changes where it ends, may change
+//! how the text around it parses, so such an edit is tried on the next block
+//! or item further out, and if there is none the whole file is parsed again.
+
+use logos::Span;
+
+use super::parser::{closed, parse, parse_node, Parse};
+use super::{NodeKind, SyntaxNode};
+
+/// Replaces the text in `span` with `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub span: Span,
+    pub text: String,
+}
+
+impl Edit {
+    /// The smallest edit that turns `old` into `new`, which replaces what
+    /// lies between the start and the end they have in common.
+    pub fn between(old: &str, new: &str) -> Edit {
+        let mut start = old
+            .bytes()
+            .zip(new.bytes())
+            .take_while(|(old, new)| old == new)
+            .count();
+        while !old.is_char_boundary(start) || !new.is_char_boundary(start) {
+            start -= 1;
+        }
+        let mut end = old
+            .bytes()
+            .rev()
+            .zip(new.bytes().rev())
+            .take(old.len().min(new.len()) - start)
+            .take_while(|(old, new)| old == new)
+            .count();
+        while !old.is_char_boundary(old.len() - end) || !new.is_char_boundary(new.len() - end) {
+            end -= 1;
+        }
+        Edit {
+            span: start..old.len() - end,
+            text: new[start..new.len() - end].to_string(),
+        }
+    }
+
+    pub fn apply(&self, text: &mut String) {
+        text.replace_range(self.span.clone(), &self.text);
+    }
+}
+
+impl Parse {
+    /// The parse of the text after `edit`, the same as parsing all of it
+    /// again would give.
+    pub fn reparse(&self, edit: &Edit) -> Parse {
+        let root = self.syntax();
+        for node in enclosing(&root, &edit.span).into_iter().rev() {
+            if let Some(parse) = self.reparse_node(&node, edit) {
+                return parse;
+            }
+        }
+        let mut text = root.text();
+        edit.apply(&mut text);
+        parse(&text)
+    }
+
+    fn reparse_node(&self, node: &SyntaxNode, edit: &Edit) -> Option<Parse> {
+        // Only a closed node has all of its errors inside it.
+        if !closed(node.green()) {
+            return None;
+        }
+        let span = node.span();
+        let mut text = node.text();
+        Edit {
+            span: edit.span.start - span.start..edit.span.end - span.start,
+            text: edit.text.clone(),
+        }
+        .apply(&mut text);
+        let (green, errors) = parse_node(node.kind(), &text, span.start)?;
+
+        // The node's errors are replaced and the ones after it move along.
+        // An error at its first token belongs to what comes before, as the
+        // node starts with a token it can not be missing.
+        let delta = edit.text.len() as isize - edit.span.len() as isize;
+        let before = self
+            .errors
+            .iter()
+            .filter(|error| error.span().start <= span.start);
+        let after = self
+            .errors
+            .iter()
+            .filter(|error| error.span().start >= span.end);
+        let errors = before
+            .cloned()
+            .chain(errors)
+            .chain(after.map(|error| error.clone().moved(delta)))
+            .collect();
+
+        Some(Parse {
+            green: node.replace_with(green).green().clone(),
+            errors,
+        })
+    }
+}
+
+/// The blocks and items that contain `span` without touching their first or
+/// last character, from the outermost in.
+fn enclosing(root: &SyntaxNode, span: &Span) -> Vec<SyntaxNode> {
+    let mut nodes = Vec::new();
+    let mut node = root.clone();
+    while let Some(child) = node.children().find(|child| {
+        let range = child.span();
+        range.start < span.start && span.end < range.end
+    }) {
+        if matches!(
+            child.kind(),
+            NodeKind::Block | NodeKind::FnItem | NodeKind::StructItem
+        ) {
+            nodes.push(child.clone());
+        }
+        node = child;
+    }
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    const TEXT: &str = "/// Adds.
+fn add(a: int, b: int) -> int {
+    let c = a + b;
+    if (c > 0) {
+        return c;
+    }
+    return 0;
+}
+
+struct Point {
+    x: int,
+    y: int,
+}
+
+fn main() {
+    print(add(1, 2));
+}
+";
+
+    /// Reparsing after `edit` gives the tree and the errors of parsing the
+    /// edited text.
+    fn check(text: &str, edit: Edit) {
+        let mut edited = text.to_string();
+        edit.apply(&mut edited);
+        assert_eq!(
+            parse(text).reparse(&edit),
+            parse(&edited),
+            "{:?} on {:?}",
+            edit,
+            text
+        );
+    }
+
+    fn replace(text: &str, old: &str, new: &str) -> Edit {
+        let start = text.find(old).unwrap();
+        Edit {
+            span: start..start + old.len(),
+            text: new.to_string(),
+        }
+    }
+
+    #[test]
+    fn inside_a_block() {
+        check(TEXT, replace(TEXT, "a + b", "a * b"));
+        check(TEXT, replace(TEXT, "return c;", "return c"));
+        check(TEXT, replace(TEXT, "let c", "let 5"));
+        check(
+            TEXT,
+            replace(TEXT, "print(add(1, 2));", "print(\"unterminated);"),
+        );
+    }
+
+    #[test]
+    fn at_the_edges_of_a_block() {
+        check(TEXT, replace(TEXT, "{\n        return c;\n    }", ""));
+        check(TEXT, replace(TEXT, "(c > 0) {", "(c > 0)"));
+        check(TEXT, replace(TEXT, "return c;\n    }", "return c;\n"));
+        check(TEXT, replace(TEXT, "return 0;\n}", "return 0;\n}}"));
+        check(TEXT, replace(TEXT, "    return 0;", "    /* return 0;"));
+    }
+
+    #[test]
+    fn across_items() {
+        check(TEXT, replace(TEXT, "}\n\nstruct", "\nstruct"));
+        check(
+            TEXT,
+            replace(TEXT, "y: int,\n}\n\nfn main", "y: int,\n\nfn main"),
+        );
+        check(TEXT, replace(TEXT, "/// Adds.\n", ""));
+        check(
+            TEXT,
+            replace(TEXT, "\nstruct Point", "\n/// A point.\nstruct Point"),
+        );
+        check(
+            TEXT,
+            replace(TEXT, "return 0;\n}\n\nstruct Point {", "return 1;"),
+        );
+    }
+
+    #[test]
+    fn errors_after_the_edit_move() {
+        let text = "fn a() {\n    let x = 1;\n}\n\nfn b() {\n    let 5 = 3;\n}\n";
+        check(text, replace(text, "let x = 1;", "let xyz = 123;"));
+        check(text, replace(text, "let x = 1;", ""));
+        check(text, replace(text, "let 5 = 3;", "let y = 3;"));
+    }
+
+    #[test]
+    fn keeps_the_other_items() {
+        let before = parse(TEXT);
+        let after = before.reparse(&replace(TEXT, "print(add(1, 2));", "print(3);"));
+        let items = |parse: &Parse| -> Vec<_> {
+            parse
+                .syntax()
+                .children()
+                .map(|node| node.green().clone())
+                .collect()
+        };
+        let (before, after) = (items(&before), items(&after));
+        assert!(Rc::ptr_eq(&before[0], &after[0]));
+        assert!(Rc::ptr_eq(&before[1], &after[1]));
+        assert!(!Rc::ptr_eq(&before[2], &after[2]));
+    }
+
+    #[test]
+    fn between_texts() {
+        for (old, new, edit) in [
+            ("let x = 1;", "let x = 12;", (9..9, "2")),
+            ("let x = 1;", "let x = 1;", (10..10, "")),
+            ("aaa", "aa", (2..3, "")),
+            ("ab", "aXb", (1..1, "X")),
+            ("\"é\"", "\"è\"", (1..3, "è")),
+            ("", "fn", (0..0, "fn")),
+        ] {
+            let between = Edit::between(old, new);
+            assert_eq!(
+                (between.span.clone(), between.text.as_str()),
+                edit,
+                "{:?} to {:?}",
+                old,
+                new
+            );
+            let mut text = old.to_string();
+            between.apply(&mut text);
+            assert_eq!(text, new);
+        }
+    }
+
+    /// Every single-token insertion and every one-character deletion.
+    #[test]
+    fn every_small_edit() {
+        for start in 0..=TEXT.len() {
+            for insert in ["{", "}", "(", ";", "x", "\"", "/*", "*/", "///", " "] {
+                check(
+                    TEXT,
+                    Edit {
+                        span: start..start,
+                        text: insert.to_string(),
+                    },
+                );
+            }
+            if start < TEXT.len() {
+                check(
+                    TEXT,
+                    Edit {
+                        span: start..start + 1,
+                        text: String::new(),
+                    },
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! The tokens the parser reads, lexed up front.
+//!
+//! `TokenStream` holds every token of the input, trivia included, and moves
+//! a cursor over them. Lookahead skips trivia. The stream keeps where the
+//! next and the last consumed token that are not trivia are, so the next
+//! token and the span of the last one are found without a scan, and only
+//! looking further ahead or going back to an earlier position scans. Past
+//! the last token it sees `EOF`, with an empty span at the end of the input.
+
+use logos::Span;
+
+use crate::lexer::TokenKind;
+
+pub struct TokenStream {
+    tokens: Vec<(TokenKind, Span)>,
+    /// Index of the next token, which may be trivia.
+    pos: usize,
+    /// Index of the first token from `pos` on that is not trivia, or the
+    /// number of tokens if there is none.
+    next: usize,
+    /// Index of the last token before `pos` that is not trivia.
+    last: Option<usize>,
+    /// Length of the input, where `EOF` is.
+    end: usize,
+}
+
+impl TokenStream {
+    pub fn new(tokens: Vec<(TokenKind, Span)>, end: usize) -> Self {
+        let mut stream = Self {
+            tokens,
+            pos: 0,
+            next: 0,
+            last: None,
+            end,
+        };
+        stream.next = stream.skip_trivia(0);
+        stream
+    }
+
+    /// Index of the first token from `pos` on that is not trivia.
+    fn skip_trivia(&self, pos: usize) -> usize {
+        self.tokens[pos..]
+            .iter()
+            .position(|(kind, _)| !kind.is_trivia())
+            .map_or(self.tokens.len(), |offset| pos + offset)
+    }
+
+    /// The `k`th token after the cursor that is not trivia, so `nth(0)` is
+    /// the next one.
+    fn nth_token(&self, k: usize) -> Option<&(TokenKind, Span)> {
+        match k {
+            0 => self.tokens.get(self.next),
+            k => self.tokens[self.next..]
+                .iter()
+                .filter(|(kind, _)| !kind.is_trivia())
+                .nth(k),
+        }
+    }
+
+    /// The kind of the `k`th token ahead that is not trivia.
+    pub fn nth(&self, k: usize) -> TokenKind {
+        self.nth_token(k).map_or(TokenKind::EOF, |(kind, _)| *kind)
+    }
+
+    /// The span of the token `nth(k)` returns.
+    pub fn nth_span(&self, k: usize) -> Span {
+        self.nth_token(k)
+            .map_or(self.end..self.end, |(_, span)| span.clone())
+    }
+
+    /// The tokens from the cursor on, trivia included.
+    pub fn rest(&self) -> &[(TokenKind, Span)] {
+        &self.tokens[self.pos..]
+    }
+
+    /// Consumes the next token, trivia or not, or returns `None` at the end
+    /// of the input.
+    pub fn bump(&mut self) -> Option<(TokenKind, Span)> {
+        let token = self.tokens.get(self.pos)?.clone();
+        if self.pos == self.next {
+            self.last = Some(self.pos);
+            self.next = self.skip_trivia(self.pos + 1);
+        }
+        self.pos += 1;
+        Some(token)
+    }
+
+    /// The span of the last token consumed that is not trivia, which is
+    /// empty at the start.
+    pub fn current_span(&self) -> Span {
+        self.last.map_or(0..0, |last| self.tokens[last].1.clone())
+    }
+
+    /// Index of the next token, to `rewind` to.
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Goes back to `pos`, which `pos` returned before.
+    pub fn rewind(&mut self, pos: usize) {
+        self.pos = pos;
+        self.next = self.skip_trivia(pos);
+        self.last = self.tokens[..pos]
+            .iter()
+            .rposition(|(kind, _)| !kind.is_trivia());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use logos::Logos;
+
+    use super::*;
+
+    fn stream(input: &str) -> TokenStream {
+        TokenStream::new(TokenKind::lexer(input).spanned().collect(), input.len())
+    }
+
+    #[test]
+    fn lookahead_skips_trivia() {
+        let mut tokens = stream("let /* a */ x\n = 1;");
+        tokens.bump();
+        assert_eq!(tokens.current_span(), 0..3);
+        assert_eq!(
+            [tokens.nth(0), tokens.nth(1), tokens.nth(2), tokens.nth(3)],
+            [
+                TokenKind::Ident,
+                TokenKind::Eq,
+                TokenKind::Int,
+                TokenKind::SemiColon
+            ]
+        );
+        assert_eq!(tokens.nth_span(1), 15..16);
+        assert_eq!(tokens.nth(4), TokenKind::EOF);
+        assert_eq!(tokens.nth_span(4), 19..19);
+        // Looking ahead consumes nothing.
+        assert_eq!(tokens.current_span(), 0..3);
+        assert_eq!(tokens.rest()[0].0, TokenKind::WhiteSpace);
+    }
+
+    #[test]
+    fn rewind_restores_the_position() {
+        let mut tokens = stream("a b c");
+        assert_eq!(tokens.current_span(), 0..0);
+        let start = tokens.pos();
+        tokens.bump();
+        let after_a = tokens.pos();
+        tokens.bump();
+        tokens.bump();
+        assert_eq!((tokens.current_span(), tokens.nth_span(0)), (2..3, 4..5));
+
+        tokens.rewind(after_a);
+        assert_eq!(tokens.pos(), after_a);
+        assert_eq!((tokens.current_span(), tokens.nth_span(0)), (0..1, 2..3));
+        tokens.rewind(start);
+        assert_eq!((tokens.current_span(), tokens.nth_span(0)), (0..0, 0..1));
+        assert_eq!(tokens.rest().len(), 5);
+    }
+}
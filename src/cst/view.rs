@@ -0,0 +1,439 @@
+//! Typed access to a concrete syntax tree.
+//!
+//! Every wrapper holds a `SyntaxNode` of one kind and has accessors for the
+//! parts of it, which are `None` or left out where the source is incomplete.
+//! The trivia stays reachable through `Node::syntax`.
+
+use crate::lexer::{self, TokenKind};
+
+use super::{NodeKind, SyntaxNode, SyntaxToken};
+
+/// A typed view of a syntax node.
+pub trait Node: Sized {
+    /// The view of `node`, if it is a node of this kind.
+    fn cast(node: SyntaxNode) -> Option<Self>;
+
+    fn syntax(&self) -> &SyntaxNode;
+}
+
+macro_rules! node {
+    ($name:ident) => {
+        #[derive(Debug, Clone)]
+        pub struct $name(SyntaxNode);
+
+        impl Node for $name {
+            fn cast(node: SyntaxNode) -> Option<Self> {
+                (node.kind() == NodeKind::$name).then(|| Self(node))
+            }
+
+            fn syntax(&self) -> &SyntaxNode {
+                &self.0
+            }
+        }
+    };
+}
+
+node!(FnItem);
+node!(Attr);
+node!(StructItem);
+node!(Param);
+node!(RetType);
+node!(Member);
+node!(Type);
+node!(Block);
+node!(LetStmt);
+node!(AssignStmt);
+node!(ExprStmt);
+node!(IfStmt);
+node!(ReturnStmt);
+node!(Literal);
+node!(NameRef);
+node!(CallExpr);
+node!(PrefixExpr);
+node!(InfixExpr);
+node!(PostfixExpr);
+node!(MemberExpr);
+node!(ParenExpr);
+node!(StructLit);
+node!(Field);
+
+fn child<T: Node>(node: &SyntaxNode) -> Option<T> {
+    node.children().find_map(T::cast)
+}
+
+fn children<T: Node>(node: &SyntaxNode) -> Vec<T> {
+    node.children().filter_map(T::cast).collect()
+}
+
+/// The text of the `///` comments a documented node starts with, as in
+/// `ast::Doc`.
+fn doc(node: &SyntaxNode) -> Option<String> {
+    let lines: Vec<_> = node
+        .tokens()
+        .take_while(|token| token.kind().is_trivia())
+        .filter(|token| token.kind() == TokenKind::DocComment)
+        .map(|token| lexer::doc_text(token.text()).to_string())
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// The first token that is not trivia, for the operators.
+fn first_token(node: &SyntaxNode) -> Option<SyntaxToken> {
+    node.tokens().find(|token| !token.kind().is_trivia())
+}
+
+#[derive(Debug, Clone)]
+pub enum Item {
+    Fn(FnItem),
+    Struct(StructItem),
+}
+
+impl Node for Item {
+    fn cast(node: SyntaxNode) -> Option<Self> {
+        match node.kind() {
+            NodeKind::FnItem => Some(Item::Fn(FnItem(node))),
+            NodeKind::StructItem => Some(Item::Struct(StructItem(node))),
+            _ => None,
+        }
+    }
+
+    fn syntax(&self) -> &SyntaxNode {
+        match self {
+            Item::Fn(item) => item.syntax(),
+            Item::Struct(item) => item.syntax(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Stmt {
+    Let(LetStmt),
+    Assign(AssignStmt),
+    Expr(ExprStmt),
+    If(IfStmt),
+    Return(ReturnStmt),
+    Block(Block),
+}
+
+impl Node for Stmt {
+    fn cast(node: SyntaxNode) -> Option<Self> {
+        Some(match node.kind() {
+            NodeKind::LetStmt => Stmt::Let(LetStmt(node)),
+            NodeKind::AssignStmt => Stmt::Assign(AssignStmt(node)),
+            NodeKind::ExprStmt => Stmt::Expr(ExprStmt(node)),
+            NodeKind::IfStmt => Stmt::If(IfStmt(node)),
+            NodeKind::ReturnStmt => Stmt::Return(ReturnStmt(node)),
+            NodeKind::Block => Stmt::Block(Block(node)),
+            _ => return None,
+        })
+    }
+
+    fn syntax(&self) -> &SyntaxNode {
+        match self {
+            Stmt::Let(stmt) => stmt.syntax(),
+            Stmt::Assign(stmt) => stmt.syntax(),
+            Stmt::Expr(stmt) => stmt.syntax(),
+            Stmt::If(stmt) => stmt.syntax(),
+            Stmt::Return(stmt) => stmt.syntax(),
+            Stmt::Block(stmt) => stmt.syntax(),
+        }
+    }
+}
+
+impl Stmt {
+    pub fn attrs(&self) -> Vec<Attr> {
+        children::<Attr>(self.syntax())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Literal(Literal),
+    NameRef(NameRef),
+    Call(CallExpr),
+    Prefix(PrefixExpr),
+    Infix(InfixExpr),
+    Postfix(PostfixExpr),
+    Member(MemberExpr),
+    Paren(ParenExpr),
+    StructLit(StructLit),
+}
+
+impl Node for Expr {
+    fn cast(node: SyntaxNode) -> Option<Self> {
+        Some(match node.kind() {
+            NodeKind::Literal => Expr::Literal(Literal(node)),
+            NodeKind::NameRef => Expr::NameRef(NameRef(node)),
+            NodeKind::CallExpr => Expr::Call(CallExpr(node)),
+            NodeKind::PrefixExpr => Expr::Prefix(PrefixExpr(node)),
+            NodeKind::InfixExpr => Expr::Infix(InfixExpr(node)),
+            NodeKind::PostfixExpr => Expr::Postfix(PostfixExpr(node)),
+            NodeKind::MemberExpr => Expr::Member(MemberExpr(node)),
+            NodeKind::ParenExpr => Expr::Paren(ParenExpr(node)),
+            NodeKind::StructLit => Expr::StructLit(StructLit(node)),
+            _ => return None,
+        })
+    }
+
+    fn syntax(&self) -> &SyntaxNode {
+        match self {
+            Expr::Literal(expr) => expr.syntax(),
+            Expr::NameRef(expr) => expr.syntax(),
+            Expr::Call(expr) => expr.syntax(),
+            Expr::Prefix(expr) => expr.syntax(),
+            Expr::Infix(expr) => expr.syntax(),
+            Expr::Postfix(expr) => expr.syntax(),
+            Expr::Member(expr) => expr.syntax(),
+            Expr::Paren(expr) => expr.syntax(),
+            Expr::StructLit(expr) => expr.syntax(),
+        }
+    }
+}
+
+impl FnItem {
+    pub fn doc(&self) -> Option<String> {
+        doc(&self.0)
+    }
+
+    pub fn attrs(&self) -> Vec<Attr> {
+        children::<Attr>(&self.0)
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0.token(TokenKind::Ident)
+    }
+
+    pub fn params(&self) -> Vec<Param> {
+        self.0
+            .children()
+            .find(|node| node.kind() == NodeKind::ParamList)
+            .map_or_else(Vec::new, |list| children::<Param>(&list))
+    }
+
+    pub fn return_type(&self) -> Option<Type> {
+        child::<RetType>(&self.0).and_then(|ret| child::<Type>(&ret.0))
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        child::<Block>(&self.0)
+    }
+}
+
+impl Attr {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0.token(TokenKind::Ident)
+    }
+
+    /// The names and strings between the parentheses.
+    pub fn args(&self) -> Vec<SyntaxToken> {
+        self.0
+            .tokens()
+            .skip_while(|token| token.kind() != TokenKind::LParen)
+            .filter(|token| matches!(token.kind(), TokenKind::Ident | TokenKind::String))
+            .collect()
+    }
+}
+
+impl StructItem {
+    pub fn doc(&self) -> Option<String> {
+        doc(&self.0)
+    }
+
+    pub fn attrs(&self) -> Vec<Attr> {
+        children::<Attr>(&self.0)
+    }
+
+    pub fn name(&self) -> Option<Type> {
+        child::<Type>(&self.0)
+    }
+
+    pub fn members(&self) -> Vec<Member> {
+        children::<Member>(&self.0)
+    }
+}
+
+impl Param {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0.token(TokenKind::Ident)
+    }
+
+    pub fn ty(&self) -> Option<Type> {
+        child::<Type>(&self.0)
+    }
+}
+
+impl Member {
+    pub fn doc(&self) -> Option<String> {
+        doc(&self.0)
+    }
+
+    pub fn attrs(&self) -> Vec<Attr> {
+        children::<Attr>(&self.0)
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0.token(TokenKind::Ident)
+    }
+
+    pub fn ty(&self) -> Option<Type> {
+        child::<Type>(&self.0)
+    }
+}
+
+impl Type {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0.token(TokenKind::Ident)
+    }
+
+    pub fn generics(&self) -> Vec<Type> {
+        self.0
+            .children()
+            .find(|node| node.kind() == NodeKind::GenericArgs)
+            .map_or_else(Vec::new, |args| children::<Type>(&args))
+    }
+}
+
+impl LetStmt {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0.token(TokenKind::Ident)
+    }
+
+    pub fn value(&self) -> Option<Expr> {
+        child::<Expr>(&self.0)
+    }
+}
+
+impl AssignStmt {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0.token(TokenKind::Ident)
+    }
+
+    pub fn value(&self) -> Option<Expr> {
+        child::<Expr>(&self.0)
+    }
+}
+
+impl ExprStmt {
+    pub fn expr(&self) -> Option<Expr> {
+        child::<Expr>(&self.0)
+    }
+}
+
+impl IfStmt {
+    pub fn condition(&self) -> Option<Expr> {
+        child::<Expr>(&self.0)
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        child::<Block>(&self.0)
+    }
+
+    /// The block or `if` statement after `else`.
+    pub fn else_stmt(&self) -> Option<Stmt> {
+        self.0
+            .children()
+            .find(|node| node.kind() == NodeKind::ElseBranch)
+            .and_then(|branch| child::<Stmt>(&branch))
+    }
+}
+
+impl ReturnStmt {
+    pub fn value(&self) -> Option<Expr> {
+        child::<Expr>(&self.0)
+    }
+}
+
+impl Literal {
+    pub fn token(&self) -> Option<SyntaxToken> {
+        first_token(&self.0)
+    }
+}
+
+impl NameRef {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0.token(TokenKind::Ident)
+    }
+}
+
+impl CallExpr {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0.token(TokenKind::Ident)
+    }
+
+    pub fn args(&self) -> Vec<Expr> {
+        self.0
+            .children()
+            .find(|node| node.kind() == NodeKind::ArgList)
+            .map_or_else(Vec::new, |args| children::<Expr>(&args))
+    }
+}
+
+impl PrefixExpr {
+    pub fn op(&self) -> Option<SyntaxToken> {
+        first_token(&self.0)
+    }
+
+    pub fn expr(&self) -> Option<Expr> {
+        child::<Expr>(&self.0)
+    }
+}
+
+impl InfixExpr {
+    pub fn lhs(&self) -> Option<Expr> {
+        child::<Expr>(&self.0)
+    }
+
+    pub fn op(&self) -> Option<SyntaxToken> {
+        first_token(&self.0)
+    }
+
+    pub fn rhs(&self) -> Option<Expr> {
+        self.0.children().filter_map(Expr::cast).nth(1)
+    }
+}
+
+impl PostfixExpr {
+    pub fn expr(&self) -> Option<Expr> {
+        child::<Expr>(&self.0)
+    }
+
+    pub fn op(&self) -> Option<SyntaxToken> {
+        first_token(&self.0)
+    }
+}
+
+impl ParenExpr {
+    pub fn expr(&self) -> Option<Expr> {
+        child::<Expr>(&self.0)
+    }
+}
+
+impl MemberExpr {
+    pub fn expr(&self) -> Option<Expr> {
+        child::<Expr>(&self.0)
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0.token(TokenKind::Ident)
+    }
+}
+
+impl StructLit {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0.token(TokenKind::Ident)
+    }
+
+    pub fn fields(&self) -> Vec<Field> {
+        children::<Field>(&self.0)
+    }
+}
+
+impl Field {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0.token(TokenKind::Ident)
+    }
+
+    pub fn value(&self) -> Option<Expr> {
+        child::<Expr>(&self.0)
+    }
+}
@@ -0,0 +1,659 @@
+//! A demand-driven query database for incremental compilation.
+//!
+//! Everything the compiler works out about a file is a query: the file's
+//! text is an input, and the syntax tree, items, signatures, type checks,
+//! ownership and MIR are computed from it and from each other when first
+//! asked for.
+//! Each result is memoized together with the queries it read. Setting a new
+//! text starts a new revision; a memo from an earlier one is reused if none
+//! of the queries it read has changed since, which is checked the same way
+//! for them first. A query that has to run again but gives a value equal to
+//! the old one counts as unchanged, so the queries reading it can be reused.
+//! The syntax tree is not even built again from scratch: only the text
+//! around what changed is parsed again, see `cst::reparse`.
+//!
+//! Signatures and parameter modes only change with the headers of functions
+//! and structs, so after an edit inside one function body only that function
+//! is checked and lowered again, besides the parse, the ownership analysis,
+//! which infers modes across functions, and the optimization of the program.
+//! The spans in a function's item and in the errors found checking and
+//! lowering it are kept relative to the start of the function, so the
+//! functions an edit only moves are not redone either, and the item leaves
+//! out its doc comment, so editing that redoes nothing.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::{
+    ast::{self, visit_mut::VisitorMut},
+    cst::{self, parser::Parse, reparse::Edit},
+    error::Error,
+    mir,
+    ownership::{self, FnOwnership, Mode, Ownership},
+    types::{self, ExprTypes, Types},
+};
+
+pub type Items = Vec<Result<ast::Spanned<ast::Item>, Error>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId(usize);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Query {
+    Text(FileId),
+    Syntax(FileId),
+    Items(FileId),
+    Signatures(FileId),
+    Functions(FileId),
+    Function(FileId, String),
+    Check(FileId, String),
+    Ownership(FileId),
+    FnOwnership(FileId, String),
+    ParamModes(FileId),
+    Lower(FileId, String),
+    Mir(FileId, u8),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Text(Rc<str>),
+    Syntax(Rc<Parse>),
+    /// The items and the syntax errors of a file.
+    Items(Rc<Items>, Rc<[Error]>),
+    Signatures(Rc<Result<Types, Error>>),
+    Functions(Rc<[String]>),
+    Function(Option<Rc<ast::Spanned<ast::Item>>>),
+    Check(Result<Rc<ExprTypes>, Error>),
+    Ownership(Rc<Result<Ownership, Error>>),
+    FnOwnership(Rc<Result<FnOwnership, Error>>),
+    ParamModes(Rc<Result<HashMap<String, Vec<Mode>>, Error>>),
+    Lower(Rc<Result<mir::Function, Error>>),
+    Mir(Rc<Result<mir::Program, Error>>),
+}
+
+struct Memo {
+    value: Value,
+    /// The queries read while computing `value`, in the order they were read.
+    deps: Vec<Query>,
+    /// The last revision `value` was known to be up to date in.
+    verified_at: u64,
+    /// The revision `value` last changed in.
+    changed_at: u64,
+}
+
+#[derive(Default)]
+pub struct Database {
+    revision: u64,
+    files: usize,
+    memos: HashMap<Query, Memo>,
+    /// The queries read by each query being computed, innermost last.
+    active: Vec<Vec<Query>>,
+    /// Every query run, for the tests to see what was reused.
+    #[cfg(test)]
+    executed: Vec<Query>,
+}
+
+impl Database {
+    pub fn add_file(&mut self, text: String) -> FileId {
+        let file = FileId(self.files);
+        self.files += 1;
+        self.set_text(file, text);
+        file
+    }
+
+    /// Adds a file that only has items, such as one read from AST JSON, so
+    /// there is no text or syntax tree to ask for, nor syntax errors.
+    pub fn add_items(&mut self, items: Items) -> FileId {
+        let file = FileId(self.files);
+        self.files += 1;
+        self.revision += 1;
+        self.memos.insert(
+            Query::Items(file),
+            Memo {
+                value: Value::Items(Rc::new(items), Rc::new([])),
+                deps: Vec::new(),
+                verified_at: self.revision,
+                changed_at: self.revision,
+            },
+        );
+        file
+    }
+
+    pub fn set_text(&mut self, file: FileId, text: String) {
+        self.revision += 1;
+        let query = Query::Text(file);
+        let value = Value::Text(text.into());
+        let changed_at = match self.memos.get(&query) {
+            Some(memo) if memo.value == value => memo.changed_at,
+            _ => self.revision,
+        };
+        self.memos.insert(
+            query,
+            Memo {
+                value,
+                deps: Vec::new(),
+                verified_at: self.revision,
+                changed_at,
+            },
+        );
+    }
+
+    fn get(&mut self, query: Query) -> Value {
+        if let Some(deps) = self.active.last_mut() {
+            deps.push(query.clone());
+        }
+        self.update(&query);
+        self.memos[&query].value.clone()
+    }
+
+    /// Brings the memo of `query` up to date, and returns the revision its
+    /// value last changed in.
+    fn update(&mut self, query: &Query) -> u64 {
+        if let Some(memo) = self.memos.get(query) {
+            if memo.verified_at == self.revision {
+                return memo.changed_at;
+            }
+            let (deps, verified_at) = (memo.deps.clone(), memo.verified_at);
+            // In order and stopping at the first change, as the later ones
+            // may not even make sense anymore: they were read because of
+            // the values of the earlier ones.
+            if deps.iter().all(|dep| self.update(dep) <= verified_at) {
+                let memo = self.memos.get_mut(query).unwrap();
+                memo.verified_at = self.revision;
+                return memo.changed_at;
+            }
+        }
+
+        #[cfg(test)]
+        self.executed.push(query.clone());
+        self.active.push(Vec::new());
+        let value = self.execute(query);
+        let deps = self.active.pop().unwrap();
+        let changed_at = match self.memos.get(query) {
+            Some(memo) if memo.value == value => memo.changed_at,
+            _ => self.revision,
+        };
+        self.memos.insert(
+            query.clone(),
+            Memo {
+                value,
+                deps,
+                verified_at: self.revision,
+                changed_at,
+            },
+        );
+        changed_at
+    }
+
+    fn execute(&mut self, query: &Query) -> Value {
+        match query {
+            Query::Text(file) => panic!("no text set for file {:?}", file),
+            Query::Syntax(file) => {
+                let text = self.text(*file);
+                // The tree knows the text it was parsed from, as it is
+                // lossless.
+                let parse = match self.memos.get(query) {
+                    Some(Memo {
+                        value: Value::Syntax(old),
+                        ..
+                    }) => old.reparse(&Edit::between(&old.syntax().text(), &text)),
+                    _ => cst::parse(&text),
+                };
+                Value::Syntax(Rc::new(parse))
+            }
+            Query::Items(file) => {
+                let parse = self.syntax(*file);
+                Value::Items(
+                    Rc::new(cst::lower::items(&parse)),
+                    parse.errors.as_slice().into(),
+                )
+            }
+            Query::Signatures(file) => {
+                Value::Signatures(Rc::new(types::declare(&self.items(*file))))
+            }
+            Query::Functions(file) => Value::Functions(
+                self.items(*file)
+                    .iter()
+                    .filter_map(|item| match item {
+                        Ok(ast::Spanned {
+                            node: ast::Item::Function { name, .. },
+                            ..
+                        }) => Some(name.to_string()),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            Query::Function(file, name) => {
+                Value::Function(function(&self.items(*file), name).map(|item| {
+                    let mut item = item.clone().moved(-(item.span.start as isize));
+                    Undocumented.visit_item(&mut item);
+                    Rc::new(item)
+                }))
+            }
+            Query::Check(file, name) => Value::Check(self.execute_check(*file, name)),
+            Query::Ownership(file) => Value::Ownership(Rc::new(self.execute_ownership(*file))),
+            Query::FnOwnership(file, name) => Value::FnOwnership(Rc::new(
+                self.ownership(*file)
+                    .as_ref()
+                    .as_ref()
+                    .map_err(Clone::clone)
+                    .map(|ownership| ownership.function(name).unwrap().clone()),
+            )),
+            Query::ParamModes(file) => Value::ParamModes(Rc::new(
+                self.ownership(*file)
+                    .as_ref()
+                    .as_ref()
+                    .map_err(Clone::clone)
+                    .map(Ownership::signatures),
+            )),
+            Query::Lower(file, name) => Value::Lower(Rc::new(self.execute_lower(*file, name))),
+            Query::Mir(file, level) => Value::Mir(Rc::new(self.execute_mir(*file, *level))),
+        }
+    }
+
+    fn execute_check(&mut self, file: FileId, name: &str) -> Result<Rc<ExprTypes>, Error> {
+        let types = self.signatures(file);
+        let types = types.as_ref().as_ref().map_err(Clone::clone)?;
+        let item = self
+            .function(file, name)
+            .unwrap_or_else(|| panic!("no function `{}`", name));
+        types::check_function(types, &item).map(Rc::new)
+    }
+
+    /// Like `execute_mir`, stops at the first syntax error, as the items are
+    /// built despite them.
+    fn execute_ownership(&mut self, file: FileId) -> Result<Ownership, Error> {
+        if let Some(err) = self.syntax_errors(file).first() {
+            return Err(err.clone());
+        }
+        let types = self.signatures(file);
+        let types = types.as_ref().as_ref().map_err(Clone::clone)?;
+        let exprs = self.expr_types(file)?;
+        ownership::analyze(&self.items(file), types, &exprs)
+    }
+
+    fn execute_lower(&mut self, file: FileId, name: &str) -> Result<mir::Function, Error> {
+        self.check(file, name)?;
+        let types = self.signatures(file);
+        let types = types.as_ref().as_ref().map_err(Clone::clone)?;
+        let func = self.fn_ownership(file, name);
+        let func = func.as_ref().as_ref().map_err(Clone::clone)?;
+        let signatures = self.param_modes(file);
+        let signatures = signatures.as_ref().as_ref().map_err(Clone::clone)?;
+        let item = self.function(file, name).unwrap();
+        Ok(mir::lower_function(types, func, signatures, &item)?.unwrap())
+    }
+
+    /// Reports errors in the same order as checking the whole file at once:
+    /// syntax, declarations, then function bodies, then ownership.
+    fn execute_mir(&mut self, file: FileId, level: u8) -> Result<mir::Program, Error> {
+        if let Some(err) = self.syntax_errors(file).first() {
+            return Err(err.clone());
+        }
+        let types = self.signatures(file);
+        let types = types.as_ref().as_ref().map_err(Clone::clone)?;
+        let names = self.functions(file);
+        for name in names.iter() {
+            self.check_function(file, name)?;
+        }
+        self.ownership(file)
+            .as_ref()
+            .as_ref()
+            .map_err(Clone::clone)?;
+
+        let mut program = mir::Program {
+            structs: types.structs.clone(),
+            derives: types.derives.clone(),
+            functions: Vec::new(),
+        };
+        // Functions are lowered with spans relative to their start, so that
+        // they are only lowered again when their own text changes.
+        for name in names.iter() {
+            let func = self.lower_function(file, name);
+            let start = self.start(file, name);
+            let func = func
+                .as_ref()
+                .as_ref()
+                .map_err(|err| err.clone().moved(start))?;
+            program.functions.push(func.clone().moved(start));
+        }
+        mir::optimize(&mut program, level);
+        mir::verify(&program)?;
+        Ok(program)
+    }
+
+    pub fn text(&mut self, file: FileId) -> Rc<str> {
+        match self.get(Query::Text(file)) {
+            Value::Text(text) => text,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The concrete syntax tree of the file.
+    pub fn syntax(&mut self, file: FileId) -> Rc<Parse> {
+        match self.get(Query::Syntax(file)) {
+            Value::Syntax(parse) => parse,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn items(&mut self, file: FileId) -> Rc<Items> {
+        match self.get(Query::Items(file)) {
+            Value::Items(items, _) => items,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The syntax errors of the file, which the items are built despite.
+    pub fn syntax_errors(&mut self, file: FileId) -> Rc<[Error]> {
+        match self.get(Query::Items(file)) {
+            Value::Items(_, errors) => errors,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The structs and function signatures of the file.
+    pub fn signatures(&mut self, file: FileId) -> Rc<Result<Types, Error>> {
+        match self.get(Query::Signatures(file)) {
+            Value::Signatures(types) => types,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The names of the functions in the file, in order.
+    pub fn functions(&mut self, file: FileId) -> Rc<[String]> {
+        match self.get(Query::Functions(file)) {
+            Value::Functions(names) => names,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The item of a function of the file, moved to start at 0.
+    pub fn function(&mut self, file: FileId, name: &str) -> Option<Rc<ast::Spanned<ast::Item>>> {
+        match self.get(Query::Function(file, name.to_string())) {
+            Value::Function(item) => item,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Where a function of the file starts, which the spans in `function`
+    /// and the errors of `check` and `lower_function` are relative to.
+    fn start(&mut self, file: FileId, name: &str) -> isize {
+        let items = self.items(file);
+        let item = function(&items, name).unwrap_or_else(|| panic!("no function `{}`", name));
+        item.span.start as isize
+    }
+
+    /// Type checks the body of a function of the file, giving the types of
+    /// its expressions.
+    pub fn check_function(&mut self, file: FileId, name: &str) -> Result<Rc<ExprTypes>, Error> {
+        self.check(file, name)
+            .map_err(|err| err.moved(self.start(file, name)))
+    }
+
+    /// `check_function`, with the error relative to the function.
+    fn check(&mut self, file: FileId, name: &str) -> Result<Rc<ExprTypes>, Error> {
+        match self.get(Query::Check(file, name.to_string())) {
+            Value::Check(result) => result,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The types of the expressions of every function of the file, by name,
+    /// or the first error in checking them.
+    pub fn expr_types(&mut self, file: FileId) -> Result<HashMap<String, Rc<ExprTypes>>, Error> {
+        let names = self.functions(file);
+        names
+            .iter()
+            .map(|name| Ok((name.clone(), self.check_function(file, name)?)))
+            .collect()
+    }
+
+    pub fn ownership(&mut self, file: FileId) -> Rc<Result<Ownership, Error>> {
+        match self.get(Query::Ownership(file)) {
+            Value::Ownership(ownership) => ownership,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The ownership of a function of the file.
+    pub fn fn_ownership(&mut self, file: FileId, name: &str) -> Rc<Result<FnOwnership, Error>> {
+        match self.get(Query::FnOwnership(file, name.to_string())) {
+            Value::FnOwnership(func) => func,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The parameter modes of every function of the file.
+    pub fn param_modes(&mut self, file: FileId) -> Rc<Result<HashMap<String, Vec<Mode>>, Error>> {
+        match self.get(Query::ParamModes(file)) {
+            Value::ParamModes(modes) => modes,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The unoptimized MIR of a function of the file, which has to check,
+    /// or the error lowering it, relative to the function.
+    fn lower_function(&mut self, file: FileId, name: &str) -> Rc<Result<mir::Function, Error>> {
+        match self.get(Query::Lower(file, name.to_string())) {
+            Value::Lower(func) => func,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The verified MIR of the file, optimized at `level`.
+    pub fn mir(&mut self, file: FileId, level: u8) -> Rc<Result<mir::Program, Error>> {
+        match self.get(Query::Mir(file, level)) {
+            Value::Mir(program) => program,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Every error `mir` could stop at, for reporting them all at once: the
+    /// syntax errors, then the first error in the declarations or else the
+    /// first in each function body, then the first after those. An error
+    /// that several queries run into, like one in a statement that did not
+    /// parse, is reported once.
+    pub fn errors(&mut self, file: FileId) -> Vec<Error> {
+        let mut errors = self.syntax_errors(file).to_vec();
+        let push = |errors: &mut Vec<Error>, err: &Error| {
+            if !errors.contains(err) {
+                errors.push(err.clone());
+            }
+        };
+        if let Err(err) = &*self.signatures(file) {
+            push(&mut errors, err);
+            return errors;
+        }
+        let mut checked = true;
+        for name in self.functions(file).iter() {
+            if let Err(err) = self.check_function(file, name) {
+                push(&mut errors, &err);
+                checked = false;
+            }
+        }
+        if checked {
+            if let Err(err) = &*self.mir(file, 0) {
+                push(&mut errors, err);
+            }
+        }
+        errors
+    }
+}
+
+/// The first function named `name` in `items`.
+/// Leaves out the doc comment of a function, which checking and lowering
+/// it never read, so that editing it does not redo either.
+struct Undocumented;
+
+impl VisitorMut for Undocumented {
+    fn visit_item(&mut self, item: &mut ast::Spanned<ast::Item>) {
+        if let ast::Item::Function { doc, .. } = &mut item.node {
+            *doc = None;
+        }
+    }
+}
+
+fn function<'a>(items: &'a Items, name: &str) -> Option<&'a ast::Spanned<ast::Item>> {
+    items.iter().flatten().find(|item| match &item.node {
+        ast::Item::Function { name: other, .. } => other.as_str() == name,
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "fn a(x: int) -> int {
+    return x + 1;
+}
+
+fn b(x: int) -> int {
+    return a(x) * 2;
+}
+
+fn main() {
+    print(b(1));
+}
+";
+
+    /// The functions whose `Check` and `Lower` queries ran again after
+    /// changing `old` to `new` in `TEXT`, to build it and to check all of
+    /// it.
+    fn rerun(old: &str, new: &str) -> Vec<String> {
+        let mut db = Database::default();
+        let file = db.add_file(TEXT.to_string());
+        assert!(db.mir(file, 2).is_ok());
+        db.executed.clear();
+        db.set_text(file, TEXT.replacen(old, new, 1));
+        let _ = db.mir(file, 2);
+        let _ = db.errors(file);
+        let mut names: Vec<_> = db
+            .executed
+            .iter()
+            .filter_map(|query| match query {
+                Query::Check(_, name) => Some(format!("check {}", name)),
+                Query::Lower(_, name) => Some(format!("lower {}", name)),
+                _ => None,
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn editing_a_body_redoes_only_that_function() {
+        assert_eq!(
+            rerun("return x + 1;", "return x + 2;"),
+            ["check a", "lower a"]
+        );
+        assert_eq!(
+            rerun("return a(x) * 2;", "return a(x) * 3;"),
+            ["check b", "lower b"]
+        );
+        assert_eq!(
+            rerun("print(b(1));", "print(b(2));"),
+            ["check main", "lower main"]
+        );
+    }
+
+    #[test]
+    fn growing_the_last_body_keeps_the_others() {
+        assert_eq!(
+            rerun("print(b(1));", "print(b(1));\n    print(b(2));"),
+            ["check main", "lower main"]
+        );
+    }
+
+    #[test]
+    fn editing_a_doc_comment_redoes_nothing() {
+        assert_eq!(
+            rerun("fn b(", "/// Doubles `a`.\nfn b("),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn growing_the_first_body_keeps_the_others() {
+        assert_eq!(
+            rerun("return x + 1;", "let y = x + 1;\n    return y;"),
+            ["check a", "lower a"]
+        );
+    }
+
+    #[test]
+    fn errors_in_a_moved_function_move_with_it() {
+        let mut db = Database::default();
+        let text = TEXT.replacen("print(b(1));", "print(b(true));", 1);
+        let file = db.add_file(text.clone());
+        let errors = db.errors(file);
+        let start = text.find("b(true)").unwrap();
+        assert_eq!(errors[0].span(), start..start + 7);
+
+        let text = text.replacen("return x + 1;", "let y = x + 1;\n    return y;", 1);
+        db.executed.clear();
+        db.set_text(file, text.clone());
+        let errors = db.errors(file);
+        let start = text.find("b(true)").unwrap();
+        assert_eq!(errors[0].span(), start..start + 7);
+        assert!(!db
+            .executed
+            .contains(&Query::Check(file, "main".to_string())));
+    }
+
+    #[test]
+    fn renaming_a_parameter_keeps_the_callers() {
+        assert_eq!(
+            rerun(
+                "fn a(x: int) -> int {\n    return x",
+                "fn a(y: int) -> int {\n    return y"
+            ),
+            ["check a", "lower a"]
+        );
+    }
+
+    #[test]
+    fn changing_a_signature_redoes_the_callers() {
+        assert_eq!(
+            rerun(
+                "fn a(x: int) -> int {\n    return x + 1;",
+                "fn a(x: int) -> bool {\n    return x > 1;"
+            ),
+            ["check a", "check b", "check main"]
+        );
+    }
+
+    #[test]
+    fn setting_a_text_reparses_only_what_changed() {
+        let mut db = Database::default();
+        let file = db.add_file(TEXT.to_string());
+        let before = db.syntax(file);
+        let text = TEXT.replacen("return x + 1;", "return x + 10;", 1);
+        db.set_text(file, text.clone());
+        let after = db.syntax(file);
+        assert_eq!(*after, cst::parse(&text));
+        let items = |parse: &Parse| -> Vec<_> {
+            parse
+                .syntax()
+                .children()
+                .map(|node| node.green().clone())
+                .collect()
+        };
+        let (before, after) = (items(&before), items(&after));
+        assert!(!Rc::ptr_eq(&before[0], &after[0]));
+        assert!(Rc::ptr_eq(&before[1], &after[1]));
+        assert!(Rc::ptr_eq(&before[2], &after[2]));
+    }
+
+    #[test]
+    fn same_text_runs_nothing() {
+        let mut db = Database::default();
+        let file = db.add_file(TEXT.to_string());
+        assert!(db.mir(file, 1).is_ok());
+        db.executed.clear();
+        db.set_text(file, TEXT.to_string());
+        assert!(db.mir(file, 1).is_ok());
+        assert_eq!(db.executed, []);
+    }
+}
@@ -0,0 +1,481 @@
+//! The documentation of a project as a static HTML site, for `fir doc`.
+//!
+//! Every source file of the project is a module and gets a page, with the
+//! signatures of its functions and a table of the members of each struct,
+//! together with their doc comments. A type in a signature or member links
+//! to the struct of that name, in the module itself if it has one and
+//! otherwise in the first module that does. `index.html` lists the modules,
+//! and a search box on every page looks names up in `search-index.js`.
+//! Tests are left out.
+//! Nothing is loaded from outside the site, so it works offline.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::{
+    ast::{self, Spanned},
+    db::Items,
+    error::Error,
+};
+
+const STYLE: &str = include_str!("doc/style.css");
+const SEARCH: &str = include_str!("doc/search.js");
+
+/// A source file, named by its path in the project without the `.fir` and
+/// with `::` between directories, e.g. `geometry::point`.
+pub struct Module<'a> {
+    pub name: String,
+    /// The directories and the file of the path, which name its page, as
+    /// `name` is ambiguous when a directory or file has `::` in it.
+    pub path: Vec<String>,
+    pub items: &'a Items,
+}
+
+/// A name the search box finds.
+#[derive(Serialize)]
+struct Entry<'a> {
+    name: &'a str,
+    kind: &'static str,
+    module: &'a str,
+    href: String,
+    summary: String,
+}
+
+/// The files of the site, by their path in it, and their contents.
+pub fn site(modules: &[Module]) -> Vec<(String, String)> {
+    let structs = structs(modules);
+    let mut files = Vec::new();
+    let mut entries = Vec::new();
+    for module in modules {
+        entries.push(Entry {
+            name: &module.name,
+            kind: "module",
+            module: &module.name,
+            href: url(&page(module)),
+            summary: String::new(),
+        });
+        for item in module.items.iter().flatten() {
+            let (kind, name, doc) = match &item.node {
+                ast::Item::Struct {
+                    doc, name: Ok(ty), ..
+                } => ("struct", ty.name.as_str(), doc),
+                ast::Item::Function { doc, name, .. } if !item.is_test() => {
+                    ("fn", name.as_str(), doc)
+                }
+                _ => continue,
+            };
+            entries.push(Entry {
+                name,
+                kind,
+                module: &module.name,
+                href: format!("{}#{}.{}", url(&page(module)), kind, name),
+                summary: summary(doc),
+            });
+        }
+        files.push((page(module), module_page(module, &structs)));
+    }
+    files.push(("index.html".to_string(), index_page(modules)));
+    files.push((
+        "search-index.js".to_string(),
+        format!(
+            "window.SEARCH_INDEX = {};\n",
+            serde_json::to_string(&entries).unwrap()
+        ),
+    ));
+    files.push(("style.css".to_string(), STYLE.to_string()));
+    files.push(("search.js".to_string(), SEARCH.to_string()));
+    files
+}
+
+/// The page of `module`, e.g. `mod.geometry.point.html`, with `.` between
+/// the parts of its path. Within a part `-` is written `--` and `.` is
+/// written `-.`, so `geometry.point.fir` gets `mod.geometry-.point.html`,
+/// and the `mod.` keeps a module named `index` from overwriting `index.html`.
+fn page(module: &Module) -> String {
+    let mut page = "mod".to_string();
+    for part in &module.path {
+        page.push('.');
+        page.push_str(&part.replace('-', "--").replace('.', "-."));
+    }
+    page.push_str(".html");
+    page
+}
+
+/// `page` as a link, with anything but letters, digits and `-._~` escaped
+/// so that `#`, `?` and `%` in a file name are not taken for parts of it.
+fn url(page: &str) -> String {
+    let mut url = String::new();
+    for byte in page.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                url.push(byte as char)
+            }
+            _ => write!(url, "%{:02X}", byte).unwrap(),
+        }
+    }
+    url
+}
+
+/// The modules that have a struct of each name, in order, for linking
+/// types.
+fn structs<'a>(modules: &'a [Module<'a>]) -> HashMap<&'a str, Vec<&'a Module<'a>>> {
+    let mut structs: HashMap<&str, Vec<&Module>> = HashMap::new();
+    for module in modules {
+        for item in module.items.iter().flatten() {
+            if let ast::Item::Struct { name: Ok(ty), .. } = &item.node {
+                structs.entry(ty.name.as_str()).or_default().push(module);
+            }
+        }
+    }
+    structs
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The first paragraph of `doc`, as plain text.
+fn summary(doc: &ast::Doc) -> String {
+    doc.as_deref()
+        .unwrap_or("")
+        .split("\n\n")
+        .next()
+        .unwrap_or("")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `doc` as paragraphs, split at empty lines, with `code` in backticks.
+fn doc_html(doc: &ast::Doc) -> String {
+    let mut html = String::new();
+    for paragraph in doc.as_deref().unwrap_or("").split("\n\n") {
+        let paragraph = paragraph.trim();
+        if paragraph.is_empty() {
+            continue;
+        }
+        html.push_str("<p>");
+        for (index, part) in paragraph.split('`').enumerate() {
+            // Every other part is between backticks.
+            if index % 2 == 1 {
+                write!(html, "<code>{}</code>", escape(part)).unwrap();
+            } else {
+                html.push_str(&escape(part));
+            }
+        }
+        html.push_str("</p>\n");
+    }
+    html
+}
+
+/// Writes pages, with the head and the search box of every page.
+struct Page<'a> {
+    html: String,
+    /// The module the page is of, if it is of one.
+    module: Option<&'a Module<'a>>,
+    structs: &'a HashMap<&'a str, Vec<&'a Module<'a>>>,
+}
+
+impl<'a> Page<'a> {
+    fn new(
+        title: &str,
+        module: Option<&'a Module<'a>>,
+        structs: &'a HashMap<&'a str, Vec<&'a Module<'a>>>,
+    ) -> Self {
+        let mut html = String::new();
+        write!(
+            html,
+            concat!(
+                "<!DOCTYPE html>\n",
+                "<html lang=\"en\">\n",
+                "<head>\n",
+                "<meta charset=\"utf-8\">\n",
+                "<title>{}</title>\n",
+                "<link rel=\"stylesheet\" href=\"style.css\">\n",
+                "<script src=\"search-index.js\" defer></script>\n",
+                "<script src=\"search.js\" defer></script>\n",
+                "</head>\n",
+                "<body>\n",
+                "<nav><a href=\"index.html\">Modules</a> ",
+                "<input id=\"search\" type=\"search\" placeholder=\"Search\" autocomplete=\"off\">",
+                "<ul id=\"results\"></ul></nav>\n",
+                "<main>\n",
+            ),
+            escape(title)
+        )
+        .unwrap();
+        Self {
+            html,
+            module,
+            structs,
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.html.push_str("</main>\n</body>\n</html>\n");
+        self.html
+    }
+
+    /// Writes `ty`, with every struct in it linked.
+    fn ty(&mut self, ty: &Result<Spanned<ast::Type>, Error>) {
+        let ty = match ty {
+            Ok(ty) => ty,
+            Err(_) => return self.html.push_str("<span class=\"error\">?</span>"),
+        };
+        let name = ty.name.as_str();
+        let module = self.structs.get(name).map(|modules| {
+            match self
+                .module
+                .filter(|module| modules.iter().any(|other| std::ptr::eq(*other, *module)))
+            {
+                Some(module) => module,
+                None => modules[0],
+            }
+        });
+        match module {
+            Some(module) => write!(
+                self.html,
+                "<a class=\"type\" href=\"{}#struct.{}\">{}</a>",
+                url(&page(module)),
+                name,
+                name
+            ),
+            None => write!(self.html, "<span class=\"type\">{}</span>", name),
+        }
+        .unwrap();
+        if !ty.generics.is_empty() {
+            self.html.push_str("&lt;");
+            for (index, generic) in ty.generics.iter().enumerate() {
+                if index > 0 {
+                    self.html.push_str(", ");
+                }
+                self.ty(generic);
+            }
+            self.html.push_str("&gt;");
+        }
+    }
+
+    fn function(
+        &mut self,
+        doc: &ast::Doc,
+        name: &ast::Ident,
+        parameters: &[(ast::Ident, Result<Spanned<ast::Type>, Error>)],
+        return_type: &Option<Result<Spanned<ast::Type>, Error>>,
+    ) {
+        write!(
+            self.html,
+            "<section id=\"fn.{}\">\n<h3><code>fn <span class=\"fn\">{}</span>(",
+            name, name
+        )
+        .unwrap();
+        for (index, (parameter, ty)) in parameters.iter().enumerate() {
+            if index > 0 {
+                self.html.push_str(", ");
+            }
+            write!(self.html, "{}: ", parameter).unwrap();
+            self.ty(ty);
+        }
+        self.html.push(')');
+        if let Some(return_type) = return_type {
+            self.html.push_str(" -&gt; ");
+            self.ty(return_type);
+        }
+        self.html.push_str("</code></h3>\n");
+        self.html.push_str(&doc_html(doc));
+        self.html.push_str("</section>\n");
+    }
+
+    fn struct_(&mut self, doc: &ast::Doc, ty: &Spanned<ast::Type>, members: &[ast::Member]) {
+        write!(
+            self.html,
+            "<section id=\"struct.{}\">\n<h3><code>struct {}</code></h3>\n",
+            ty.name, ty.name
+        )
+        .unwrap();
+        self.html.push_str(&doc_html(doc));
+        if !members.is_empty() {
+            self.html
+                .push_str("<table>\n<tr><th>Member</th><th>Type</th><th>Description</th></tr>\n");
+            for member in members {
+                write!(
+                    self.html,
+                    "<tr><td><code>{}</code></td><td><code>",
+                    member.name
+                )
+                .unwrap();
+                self.ty(&member.ty);
+                writeln!(
+                    self.html,
+                    "</code></td><td>{}</td></tr>",
+                    doc_html(&member.doc)
+                )
+                .unwrap();
+            }
+            self.html.push_str("</table>\n");
+        }
+        self.html.push_str("</section>\n");
+    }
+}
+
+fn module_page<'a>(
+    module: &'a Module<'a>,
+    structs: &'a HashMap<&'a str, Vec<&'a Module<'a>>>,
+) -> String {
+    let mut page = Page::new(&module.name, Some(module), structs);
+    writeln!(
+        page.html,
+        "<h1>Module <code>{}</code></h1>",
+        escape(&module.name)
+    )
+    .unwrap();
+
+    let items: Vec<_> = module.items.iter().flatten().collect();
+    let struct_items: Vec<_> = items
+        .iter()
+        .filter_map(|item| match &item.node {
+            ast::Item::Struct {
+                doc,
+                name: Ok(ty),
+                members,
+                ..
+            } => Some((doc, ty, members)),
+            _ => None,
+        })
+        .collect();
+    if !struct_items.is_empty() {
+        page.html.push_str("<h2>Structs</h2>\n");
+        for (doc, ty, members) in struct_items {
+            page.struct_(doc, ty, members);
+        }
+    }
+
+    let functions: Vec<_> = items
+        .iter()
+        .filter_map(|item| match &item.node {
+            ast::Item::Function {
+                doc,
+                name,
+                parameters,
+                return_type,
+                ..
+            } if !item.is_test() => Some((doc, name, parameters, return_type)),
+            _ => None,
+        })
+        .collect();
+    if !functions.is_empty() {
+        page.html.push_str("<h2>Functions</h2>\n");
+        for (doc, name, parameters, return_type) in functions {
+            page.function(doc, name, parameters, return_type);
+        }
+    }
+
+    let errors = module.items.iter().filter(|item| item.is_err()).count();
+    if errors > 0 {
+        writeln!(
+            page.html,
+            "<p class=\"error\">{} items could not be parsed and are left out.</p>",
+            errors
+        )
+        .unwrap();
+    }
+    page.finish()
+}
+
+fn index_page(modules: &[Module]) -> String {
+    let structs = HashMap::new();
+    let mut page = Page::new("Modules", None, &structs);
+    page.html.push_str("<h1>Modules</h1>\n<ul>\n");
+    for module in modules {
+        writeln!(
+            page.html,
+            "<li><a href=\"{}\">{}</a></li>",
+            url(&self::page(module)),
+            escape(&module.name)
+        )
+        .unwrap();
+    }
+    page.html.push_str("</ul>\n");
+    page.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    /// The site of a module with `text` at each of `paths`.
+    fn site_of(text: &str, paths: &[&[&str]]) -> Vec<(String, String)> {
+        let mut db = Database::default();
+        let file = db.add_file(text.to_string());
+        let items = db.items(file);
+        let modules: Vec<_> = paths
+            .iter()
+            .map(|path| Module {
+                name: path.join("::"),
+                path: path.iter().map(|part| part.to_string()).collect(),
+                items: &items,
+            })
+            .collect();
+        site(&modules)
+    }
+
+    #[test]
+    fn every_path_gets_its_own_page() {
+        let files = site_of(
+            "/// A point.\nstruct Point {\n    x: int,\n}\n",
+            &[
+                &["a", "b"],
+                &["a.b"],
+                &["a-", "b"],
+                &["a-.b"],
+                &["index"],
+                &["search-index"],
+                &["x#y"],
+            ],
+        );
+        let names: Vec<_> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "mod.a.b.html",
+                "mod.a-.b.html",
+                "mod.a--.b.html",
+                "mod.a---.b.html",
+                "mod.index.html",
+                "mod.search--index.html",
+                "mod.x#y.html",
+                "index.html",
+                "search-index.js",
+                "style.css",
+                "search.js",
+            ]
+        );
+        let index = &files[7].1;
+        assert!(index.contains("<a href=\"mod.a-.b.html\">a.b</a>"));
+        assert!(index.contains("<a href=\"mod.x%23y.html\">x#y</a>"));
+    }
+
+    #[test]
+    fn types_link_to_their_own_module_first() {
+        let files = site_of(
+            "struct Point {\n    x: int,\n}\n\nfn origin() -> Point {\n    return Point { x: 0 };\n}\n",
+            &[&["a", "b"], &["a.b"]],
+        );
+        for (page, html) in &files[..2] {
+            let link = format!("<a class=\"type\" href=\"{}#struct.Point\">", page);
+            assert!(html.contains(&link), "{}", html);
+        }
+    }
+}
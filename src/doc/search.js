@@ -0,0 +1,52 @@
+// Looks names up in `window.SEARCH_INDEX`, which `search-index.js` sets.
+(function () {
+    var input = document.getElementById("search");
+    var results = document.getElementById("results");
+
+    function show(query) {
+        results.innerHTML = "";
+        query = query.trim().toLowerCase();
+        if (!query) {
+            return;
+        }
+        var found = window.SEARCH_INDEX.filter(function (entry) {
+            return entry.name.toLowerCase().indexOf(query) !== -1;
+        });
+        // Exact matches first, then names that start with the query.
+        found.sort(function (a, b) {
+            return rank(a.name.toLowerCase(), query) - rank(b.name.toLowerCase(), query);
+        });
+        found.slice(0, 20).forEach(function (entry) {
+            var item = document.createElement("li");
+            var link = document.createElement("a");
+            link.href = entry.href;
+            link.textContent = entry.name;
+            var kind = document.createElement("span");
+            kind.className = "kind";
+            kind.textContent = " " + entry.kind + (entry.kind === "module" ? "" : " in " + entry.module);
+            item.appendChild(link);
+            item.appendChild(kind);
+            if (entry.summary) {
+                item.appendChild(document.createTextNode(" — " + entry.summary));
+            }
+            results.appendChild(item);
+        });
+    }
+
+    function rank(name, query) {
+        if (name === query) {
+            return 0;
+        }
+        return name.indexOf(query) === 0 ? 1 : 2;
+    }
+
+    input.addEventListener("input", function () {
+        show(input.value);
+    });
+    input.addEventListener("keydown", function (event) {
+        var first = results.querySelector("a");
+        if (event.key === "Enter" && first) {
+            window.location.href = first.href;
+        }
+    });
+})();
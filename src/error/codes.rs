@@ -0,0 +1,143 @@
+//! The registry of error codes.
+//!
+//! Every error the compiler reports carries one of the codes below, which
+//! `fir explain` prints the long explanation of. As `Error::new` only takes
+//! a `&Code`, a code can not be emitted without being registered here.
+//! Codes starting with `W` belong to the lints in `lint`.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Code {
+    pub code: &'static str,
+    pub severity: Severity,
+    pub title: &'static str,
+    /// Markdown, with an erroneous example and how to fix it where there
+    /// is one.
+    pub explanation: &'static str,
+}
+
+macro_rules! codes {
+    ($($code:ident: $severity:ident, $title:literal;)*) => {
+        $(
+            pub const $code: &Code = &Code {
+                code: stringify!($code),
+                severity: Severity::$severity,
+                title: $title,
+                explanation: include_str!(concat!("explanations/", stringify!($code), ".md")),
+            };
+        )*
+
+        pub const CODES: &[&Code] = &[$($code),*];
+    };
+}
+
+codes! {
+    E001: Error, "expected a different token";
+    E002: Error, "unknown start of statement";
+    E003: Error, "postfix operator";
+    E004: Error, "call with the wrong arguments";
+    E005: Error, "invalid MIR";
+    E006: Error, "stack overflow";
+    E007: Error, "invalid `.firc` file";
+    E008: Error, "expected a name";
+    E009: Error, "expected a type";
+    E010: Error, "unknown start of item";
+    E011: Error, "unknown start of expression";
+    E012: Error, "invalid manifest";
+    E013: Error, "`=` used as a comparison";
+    E014: Error, "invalid AST JSON";
+    E015: Error, "unexpected character";
+    E016: Error, "unterminated string literal";
+    E017: Error, "invalid escape in a string literal";
+    E018: Error, "exponent without digits";
+    E019: Error, "unterminated block comment";
+    E020: Error, "invalid attribute";
+    E021: Error, "derive on a struct whose member can not do it";
+    E022: Error, "unknown type";
+    E023: Error, "print of a value that can not be printed";
+    E024: Error, "wrong arguments to `assert` or `assert_eq`";
+    E025: Error, "struct defined twice";
+    E026: Error, "function defined twice";
+    E027: Error, "test with parameters or a return type";
+    E028: Error, "function that does not return on every path";
+    E029: Error, "`()` bound to a name";
+    E030: Error, "assignment of a value of another type";
+    E031: Error, "condition that is not a `bool`";
+    E032: Error, "return of a value of the wrong type";
+    E033: Error, "unknown name";
+    E034: Error, "integer literal too large for an `int`";
+    E035: Error, "`()` passed to a function";
+    E036: Error, "unknown function";
+    E037: Error, "prefix operator applied to the wrong type";
+    E038: Error, "operator applied to the wrong types";
+    E039: Error, "generic type";
+    E040: Error, "copy of a struct that does not derive `Clone`";
+    E041: Error, "not supported by the LLVM backend";
+    E042: Error, "no `main` function";
+    E043: Error, "`main` with parameters";
+    E044: Error, "division by zero";
+    E045: Error, "failed assertion";
+    E046: Error, "output that could not be written";
+    E047: Error, "unknown member";
+    E048: Error, "struct literal with missing or repeated members";
+    E049: Error, "member of the wrong type in a struct literal";
+    E050: Error, "hash of a value that can not be hashed";
+    W001: Warning, "unused variable";
+    W002: Warning, "unused parameter";
+    W003: Warning, "unused function";
+    W004: Warning, "shadowed binding";
+    W005: Warning, "unreachable code";
+    W006: Warning, "constant condition";
+    W007: Warning, "struct name not in UpperCamelCase";
+    W008: Warning, "name not in snake_case";
+    W009: Warning, "unknown lint";
+}
+
+/// Looks up a code, ignoring case.
+pub fn find(code: &str) -> Option<&'static Code> {
+    CODES
+        .iter()
+        .copied()
+        .find(|other| other.code.eq_ignore_ascii_case(code))
+}
+
+/// Serializes a code as its name, e.g. `"E001"`, for `#[serde(with)]`.
+pub mod by_name {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use super::Code;
+
+    pub fn serialize<S: Serializer>(
+        code: &&'static Code,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(code.code)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<&'static Code, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        super::find(&code)
+            .ok_or_else(|| de::Error::custom(format!("`{}` is not an error code", code)))
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt;
+
+use logos::Span;
+use serde::{Deserialize, Serialize};
+
+pub mod codes;
+pub mod report;
+
+pub use codes::{Code, Severity};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Error {
+    msg: String,
+    #[serde(with = "codes::by_name")]
+    code: &'static Code,
+    /// The severity of the code, unless a lint level changed it.
+    severity: Severity,
+    span: Span,
+    notes: Vec<Note>,
+    fixes: Vec<Fix>,
+}
+
+/// More about an error, possibly at another place in the source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Note {
+    pub msg: String,
+    pub span: Option<Span>,
+}
+
+/// A change to the source that fixes an error, safe to make without asking:
+/// `span` is replaced with `text`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fix {
+    pub msg: String,
+    pub span: Span,
+    pub text: String,
+}
+
+impl Error {
+    pub fn new(msg: String, code: &'static Code, span: Span) -> Self {
+        Self {
+            msg,
+            code,
+            severity: code.severity,
+            span,
+            notes: Vec::new(),
+            fixes: Vec::new(),
+        }
+    }
+
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn code(&self) -> &'static Code {
+        self.code
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn with_severity(self, severity: Severity) -> Self {
+        Self { severity, ..self }
+    }
+
+    pub fn span(&self) -> Span {
+        self.span.clone()
+    }
+
+    /// The error with every span in it moved by `delta` bytes.
+    pub fn moved(mut self, delta: isize) -> Self {
+        let moved = |span: &mut Span| {
+            *span = (span.start as isize + delta) as usize..(span.end as isize + delta) as usize;
+        };
+        moved(&mut self.span);
+        self.notes
+            .iter_mut()
+            .filter_map(|note| note.span.as_mut())
+            .for_each(moved);
+        self.fixes.iter_mut().for_each(|fix| moved(&mut fix.span));
+        self
+    }
+
+    pub fn notes(&self) -> &[Note] {
+        &self.notes
+    }
+
+    pub fn with_note(mut self, msg: String, span: Option<Span>) -> Self {
+        self.notes.push(Note { msg, span });
+        self
+    }
+
+    pub fn fixes(&self) -> &[Fix] {
+        &self.fixes
+    }
+
+    pub fn with_fix(mut self, msg: String, span: Span, text: String) -> Self {
+        self.fixes.push(Fix { msg, span, text });
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}]: {} ({}..{})",
+            self.severity, self.code.code, self.msg, self.span.start, self.span.end
+        )?;
+        for note in &self.notes {
+            write!(f, "\n    note: {}", note.msg)?;
+            if let Some(span) = &note.span {
+                write!(f, " ({}..{})", span.start, span.end)?;
+            }
+        }
+        for fix in &self.fixes {
+            write!(
+                f,
+                "\n    help: {} ({}..{})",
+                fix.msg, fix.span.start, fix.span.end
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+//! Errors as JSON, for tools.
+//!
+//! `json` gives one object per error, for a JSON lines stream, and `sarif`
+//! a whole SARIF 2.1.0 log, which code review tools can show inline. Lines
+//! and columns count from 1; columns count characters, not bytes.
+
+use logos::Span;
+use serde_json::{json, Value};
+
+use super::{codes, Error, Severity};
+
+/// An error in a file.
+pub struct Diagnostic<'a> {
+    pub path: &'a str,
+    pub text: &'a str,
+    pub error: &'a Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The line and column of a byte offset in `text`.
+pub fn position(text: &str, offset: usize) -> Position {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    Position {
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    }
+}
+
+fn severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    }
+}
+
+fn span(text: &str, span: &Span) -> Value {
+    let (start, end) = (position(text, span.start), position(text, span.end));
+    json!({
+        "start": span.start,
+        "end": span.end,
+        "line": start.line,
+        "column": start.column,
+        "end_line": end.line,
+        "end_column": end.column,
+    })
+}
+
+/// The error as a single line of JSON.
+pub fn json(diagnostic: &Diagnostic) -> String {
+    let Diagnostic { path, text, error } = diagnostic;
+    let notes: Vec<_> = error
+        .notes()
+        .iter()
+        .map(|note| {
+            json!({
+                "message": note.msg,
+                "span": note.span.as_ref().map(|note| span(text, note)),
+            })
+        })
+        .collect();
+    let fixes: Vec<_> = error
+        .fixes()
+        .iter()
+        .map(|fix| {
+            json!({
+                "message": fix.msg,
+                "span": span(text, &fix.span),
+                "replacement": fix.text,
+            })
+        })
+        .collect();
+    json!({
+        "code": error.code().code,
+        "severity": severity(error.severity()),
+        "message": error.msg(),
+        "file": path,
+        "span": span(text, &error.span()),
+        "notes": notes,
+        "fixes": fixes,
+    })
+    .to_string()
+}
+
+fn region(text: &str, span: &Span) -> Value {
+    let (start, end) = (position(text, span.start), position(text, span.end));
+    json!({
+        "startLine": start.line,
+        "startColumn": start.column,
+        "endLine": end.line,
+        "endColumn": end.column,
+        "charOffset": text[..span.start.min(text.len())].chars().count(),
+        "charLength": text.get(span.clone()).map_or(0, |text| text.chars().count()),
+    })
+}
+
+fn location(path: &str, text: &str, span: &Span) -> Value {
+    json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": path },
+            "region": region(text, span),
+        }
+    })
+}
+
+/// The errors as a SARIF log with a single run, with every code as a rule.
+pub fn sarif(diagnostics: &[Diagnostic]) -> String {
+    let rules: Vec<_> = codes::CODES
+        .iter()
+        .map(|code| {
+            json!({
+                "id": code.code,
+                "shortDescription": { "text": code.title },
+                "help": { "text": code.explanation, "markdown": code.explanation },
+                "defaultConfiguration": { "level": severity(code.severity) },
+            })
+        })
+        .collect();
+
+    let results: Vec<_> = diagnostics
+        .iter()
+        .map(|Diagnostic { path, text, error }| {
+            let related: Vec<_> = error
+                .notes()
+                .iter()
+                .enumerate()
+                .map(|(id, note)| {
+                    let mut related = match &note.span {
+                        Some(span) => location(path, text, span),
+                        None => json!({}),
+                    };
+                    related["id"] = json!(id);
+                    related["message"] = json!({ "text": note.msg });
+                    related
+                })
+                .collect();
+            let fixes: Vec<_> = error
+                .fixes()
+                .iter()
+                .map(|fix| {
+                    json!({
+                        "description": { "text": fix.msg },
+                        "artifactChanges": [{
+                            "artifactLocation": { "uri": path },
+                            "replacements": [{
+                                "deletedRegion": region(text, &fix.span),
+                                "insertedContent": { "text": fix.text },
+                            }],
+                        }],
+                    })
+                })
+                .collect();
+            json!({
+                "ruleId": error.code().code,
+                "ruleIndex": codes::CODES.iter().position(|code| *code == error.code()),
+                "level": severity(error.severity()),
+                "message": { "text": error.msg() },
+                "locations": [location(path, text, &error.span())],
+                "relatedLocations": related,
+                "fixes": fixes,
+            })
+        })
+        .collect();
+
+    let log = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "fir",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                }
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }],
+    });
+    serde_json::to_string_pretty(&log).unwrap()
+}
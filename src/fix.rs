@@ -0,0 +1,127 @@
+//! Applying the fixes that come with syntax errors.
+//!
+//! The fixes of one parse are applied together, leaving out any that
+//! overlap an earlier one, and the text is parsed again for the fixes of
+//! the errors that are left. A round is only kept if it leaves fewer errors
+//! than it started with; otherwise the fixes are tried one at a time, and
+//! fixing stops once none of them helps.
+
+use crate::{cst, error::Fix};
+
+/// Fixing stops after this many rounds, even if there are fixes left.
+const MAX_ROUNDS: usize = 32;
+
+/// The text with the fixes applied, and how many were.
+pub fn fix(text: &str) -> (String, usize) {
+    let mut text = text.to_string();
+    let mut applied = 0;
+    for _ in 0..MAX_ROUNDS {
+        let errors = cst::parse(&text).errors;
+        let mut fixes: Vec<&Fix> = errors.iter().flat_map(|err| err.fixes()).collect();
+        fixes.sort_by_key(|fix| (fix.span.start, fix.span.end));
+        let mut end = 0;
+        fixes.retain(|fix| {
+            let keep = fix.span.start >= end;
+            if keep {
+                end = fix.span.end.max(fix.span.start + 1);
+            }
+            keep
+        });
+
+        let better = |fixes: &[&Fix]| {
+            let fixed = apply(&text, fixes);
+            (cst::parse(&fixed).errors.len() < errors.len()).then_some(fixed)
+        };
+        let round = better(&fixes)
+            .map(|fixed| (fixed, fixes.len()))
+            .or_else(|| {
+                fixes
+                    .iter()
+                    .find_map(|fix| better(std::slice::from_ref(fix)).map(|fixed| (fixed, 1)))
+            });
+        match round {
+            Some((fixed, count)) => {
+                text = fixed;
+                applied += count;
+            }
+            None => break,
+        }
+    }
+    (text, applied)
+}
+
+/// `text` with `fixes`, which are sorted and do not overlap, applied.
+fn apply(text: &str, fixes: &[&Fix]) -> String {
+    let mut fixed = text.to_string();
+    for fix in fixes.iter().rev() {
+        fixed.replace_range(fix.span.clone(), &fix.text);
+    }
+    fixed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fixes `text`, checks that the result parses without errors and that
+    /// fixing it again changes nothing, and returns it.
+    fn fixed(text: &str, count: usize) -> String {
+        let (fixed, applied) = fix(text);
+        assert_eq!(applied, count, "{}", fixed);
+        assert!(cst::parse(&fixed).errors.is_empty(), "{}", fixed);
+        assert_eq!(fix(&fixed), (fixed.clone(), 0));
+        fixed
+    }
+
+    #[test]
+    fn missing_semicolons_are_inserted() {
+        assert_eq!(
+            fixed("fn main() {\n    let x = 1\n    print(x);\n}\n", 1),
+            "fn main() {\n    let x = 1;\n    print(x);\n}\n"
+        );
+    }
+
+    #[test]
+    fn missing_parens_are_inserted() {
+        assert_eq!(
+            fixed("fn main() {\n    print(1 + (2 * 3);\n}\n", 1),
+            "fn main() {\n    print(1 + (2 * 3));\n}\n"
+        );
+    }
+
+    #[test]
+    fn missing_braces_are_inserted() {
+        assert_eq!(
+            fixed("fn main() {\n    if (true) {\n        print(1);\n}\n", 1),
+            "fn main() {\n    if (true) {\n        print(1);\n}}\n"
+        );
+    }
+
+    #[test]
+    fn assignments_in_conditions_become_comparisons() {
+        assert_eq!(
+            fixed(
+                "fn main() {\n    if (1 = 1) {\n        print(1);\n    }\n}\n",
+                1
+            ),
+            "fn main() {\n    if (1 == 1) {\n        print(1);\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn several_mistakes_are_fixed_at_once() {
+        assert_eq!(
+            fixed(
+                "fn main() {\n    let x = 1\n    if (x = 1) {\n        print(x;\n    }\n}\n",
+                3
+            ),
+            "fn main() {\n    let x = 1;\n    if (x == 1) {\n        print(x);\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn text_without_errors_is_left_alone() {
+        let text = "fn main() {\n    print(1);\n}\n";
+        assert_eq!(fix(text), (text.to_string(), 0));
+    }
+}
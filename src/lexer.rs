@@ -1,7 +1,9 @@
 use core::fmt;
-use logos::Logos;
+use logos::{Lexer, Logos, Span};
 
-#[derive(Logos, Debug, Clone, PartialEq, Copy)]
+use crate::error::{codes, Error};
+
+#[derive(Logos, Debug, Clone, PartialEq, Eq, Hash, Copy)]
 pub enum TokenKind {
     // Single character operators
     #[token(".")]
@@ -18,6 +20,10 @@ pub enum TokenKind {
     Eq,
     #[token("_")]
     Under,
+    #[token("->")]
+    Arrow,
+    #[token("#")]
+    Hash,
     // Binary Operators
     #[token("+")]
     Plus,
@@ -66,23 +72,34 @@ pub enum TokenKind {
     RBrace,
 
     // Literals
-    #[regex(r#""((\\"|\\\\)|[^\\"])*""#)]
+    #[token("\"", string)]
     String,
 
-    #[regex(r#"//[^\n]*\n"#, logos::skip)]
+    #[regex(r#"//[^\n]*"#)]
     LineComment,
 
+    /// A `///` comment, documenting the item or member after it. Like in
+    /// Rust, `////` starts an ordinary comment.
+    #[regex(r#"///([^/\n][^\n]*)?"#, priority = 10)]
+    DocComment,
+
+    /// A `/* */` comment, which may contain others.
+    #[token("/*", block_comment)]
+    BlockComment,
+
     #[regex(r#"\d+"#, priority = 2)]
     Int,
 
     #[regex(r#"((\d+(\.\d+)?)|(\.\d+))([Ee](\+|-)?\d+)?"#)]
+    // An exponent without digits, like `1e`, makes the float an `Error`.
+    #[regex(r#"((\d+(\.\d+)?)|(\.\d+))[Ee](\+|-)?"#, |_| false)]
     Float,
 
     // Misc
     #[regex(r#"[A-Za-z]([A-Za-z]|_|\d)*"#)]
     Ident,
 
-    #[regex(r"[ \t\n\f]+", logos::skip)]
+    #[regex(r"[ \t\n\f]+")]
     WhiteSpace,
 
     // Keywords
@@ -101,12 +118,159 @@ pub enum TokenKind {
     #[token("struct")]
     KwStruct,
 
+    #[token("return")]
+    KwReturn,
+
+    #[token("true")]
+    KwTrue,
+
+    #[token("false")]
+    KwFalse,
+
     #[error]
     Error,
 
+    #[allow(clippy::upper_case_acronyms)]
     EOF,
 }
 
+impl TokenKind {
+    /// Whitespace and comments, which the parser skips but the concrete
+    /// syntax tree keeps.
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::WhiteSpace
+                | TokenKind::LineComment
+                | TokenKind::DocComment
+                | TokenKind::BlockComment
+        )
+    }
+}
+
+/// The escapes a string literal may contain.
+const ESCAPES: &[char] = &['"', '\\'];
+
+/// Lexes the rest of a string literal after its opening `"`. A string with
+/// an escape not in `ESCAPES` is an `Error`, and so is one without its
+/// closing `"`, which then runs to the end of its line.
+fn string(lex: &mut Lexer<TokenKind>) -> bool {
+    let rest = lex.remainder();
+    let mut chars = rest.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                lex.bump(i + 1);
+                return invalid_escape(lex.slice()).is_none();
+            }
+            '\\' => {
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+    lex.bump(rest.find('\n').unwrap_or(rest.len()));
+    false
+}
+
+/// Lexes the rest of a block comment after its opening `/*`, up to the `*/`
+/// closing it and any comments in it. A comment that is not closed runs to
+/// the end of the input, as an `Error`.
+fn block_comment(lex: &mut Lexer<TokenKind>) -> bool {
+    let rest = lex.remainder();
+    let mut depth = 1;
+    let mut i = 0;
+    while i < rest.len() {
+        if rest[i..].starts_with("/*") {
+            depth += 1;
+            i += 2;
+        } else if rest[i..].starts_with("*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                lex.bump(i);
+                return true;
+            }
+        } else {
+            i += rest[i..].chars().next().unwrap().len_utf8();
+        }
+    }
+    lex.bump(rest.len());
+    false
+}
+
+/// The text of a `///` comment, without the `///` and the space after it.
+pub fn doc_text(comment: &str) -> &str {
+    let text = comment.trim_start_matches('/');
+    text.strip_prefix(' ').unwrap_or(text)
+}
+
+/// The offset of the first escape in `string` not in `ESCAPES`.
+fn invalid_escape(string: &str) -> Option<usize> {
+    let mut chars = string.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' && !matches!(chars.next(), Some((_, c)) if ESCAPES.contains(&c)) {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Explains why the text at `span` of `input` lexed as an `Error` token.
+pub fn error(input: &str, span: Span) -> Error {
+    let text = &input[span.clone()];
+    let start = span.start;
+    if text.starts_with('"') {
+        if let Some(i) = invalid_escape(text) {
+            let len = text[i + 1..].chars().next().map_or(0, char::len_utf8);
+            return Error::new(
+                format!(
+                    "Invalid escape `{}` in string literal",
+                    &text[i..i + 1 + len]
+                ),
+                codes::E017,
+                start + i..start + i + 1 + len,
+            )
+            .with_note(
+                format!(
+                    "only {} can be escaped",
+                    ESCAPES
+                        .iter()
+                        .map(|c| format!("`\\{}`", c))
+                        .collect::<Vec<_>>()
+                        .join(" and ")
+                ),
+                None,
+            );
+        }
+        return Error::new("Unterminated string literal".to_string(), codes::E016, span)
+            .with_note("the string starts here".to_string(), Some(start..start + 1));
+    }
+    if text.starts_with("/*") {
+        return Error::new("Unterminated block comment".to_string(), codes::E019, span).with_note(
+            "the comment starts here".to_string(),
+            Some(start..start + 2),
+        );
+    }
+    if text.len() > 1 && text.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return Error::new(
+            format!("Expected digits after the exponent of `{}`", text),
+            codes::E018,
+            span,
+        );
+    }
+    let c = text.chars().next().unwrap();
+    Error::new(
+        format!(
+            "Unexpected character `{}` (U+{:04X})",
+            c.escape_debug(),
+            c as u32
+        ),
+        codes::E015,
+        span,
+    )
+}
+
 // TODO: Change the symbols to words for the error messages in the future:
 // Example: change TokenKind::Dot => "." to TokenKind::Dot => "dot"
 
@@ -124,6 +288,8 @@ impl fmt::Display for TokenKind {
                 TokenKind::Caret => "^",
                 TokenKind::Eq => "=",
                 TokenKind::Under => "_",
+                TokenKind::Arrow => "->",
+                TokenKind::Hash => "#",
 
                 // Binary ops
                 TokenKind::Plus => "+",
@@ -155,6 +321,8 @@ impl fmt::Display for TokenKind {
                 // Literals
                 TokenKind::String => "String",
                 TokenKind::LineComment => "comment",
+                TokenKind::DocComment => "doc comment",
+                TokenKind::BlockComment => "block comment",
                 TokenKind::Int => "an integer",
                 TokenKind::Float => "a floatinf point literal",
 
@@ -168,6 +336,9 @@ impl fmt::Display for TokenKind {
                 TokenKind::KwIf => "if",
                 TokenKind::KwElse => "else",
                 TokenKind::KwFn => "fn",
+                TokenKind::KwReturn => "return",
+                TokenKind::KwTrue => "true",
+                TokenKind::KwFalse => "false",
 
                 TokenKind::Error => "ERROR",
                 TokenKind::EOF => "<EOF>",
@@ -175,3 +346,95 @@ impl fmt::Display for TokenKind {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast, db::Database};
+
+    fn tokens(input: &str) -> Vec<(TokenKind, &str)> {
+        TokenKind::lexer(input)
+            .spanned()
+            .filter(|(kind, _)| *kind != TokenKind::WhiteSpace)
+            .map(|(kind, span)| (kind, &input[span]))
+            .collect()
+    }
+
+    #[test]
+    fn block_comments_nest() {
+        assert_eq!(
+            tokens("a /* x /* y */ z */ b /**/ c /* é */"),
+            [
+                (TokenKind::Ident, "a"),
+                (TokenKind::BlockComment, "/* x /* y */ z */"),
+                (TokenKind::Ident, "b"),
+                (TokenKind::BlockComment, "/**/"),
+                (TokenKind::Ident, "c"),
+                (TokenKind::BlockComment, "/* é */"),
+            ]
+        );
+    }
+
+    #[test]
+    fn doc_comments_are_three_slashes_only() {
+        assert_eq!(
+            tokens("/// doc\n// line\n//// line\n///\n"),
+            [
+                (TokenKind::DocComment, "/// doc"),
+                (TokenKind::LineComment, "// line"),
+                (TokenKind::LineComment, "//// line"),
+                (TokenKind::DocComment, "///"),
+            ]
+        );
+        assert_eq!(doc_text("/// A point."), "A point.");
+        assert_eq!(doc_text("///  Indented."), " Indented.");
+        assert_eq!(doc_text("///"), "");
+    }
+
+    #[test]
+    fn doc_comments_attach_to_the_next_item_or_member() {
+        let mut db = Database::default();
+        let file = db.add_file(
+            "/// A point.\n///\n/// In the plane.\nstruct Point {\n    /// Across.\n    x: int,\n    // Not a doc comment.\n    y: int,\n}\n\n/* Not one either. */\nfn origin() -> Point {\n    return Point { x: 0, y: 0 };\n}\n\n/// The answer.\n#[test]\nfn answer() {\n}\n"
+                .to_string(),
+        );
+        let items = db.items(file);
+        let docs: Vec<_> = items
+            .iter()
+            .map(|item| match &item.as_ref().unwrap().node {
+                ast::Item::Struct { doc, members, .. } => {
+                    let members: Vec<_> = members.iter().map(|member| &member.doc).collect();
+                    assert_eq!(members, [&Some("Across.".to_string()), &None]);
+                    doc.clone()
+                }
+                ast::Item::Function { doc, .. } => doc.clone(),
+            })
+            .collect();
+        assert_eq!(
+            docs,
+            [
+                Some("A point.\n\nIn the plane.".to_string()),
+                None,
+                Some("The answer.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_block_comments_are_errors() {
+        let input = "fn main() {\n}\n/* a /* b */\nfn f() {\n}\n";
+        let (kind, span) = TokenKind::lexer(input).spanned().last().unwrap();
+        assert_eq!(kind, TokenKind::Error);
+        assert_eq!(span, 14..input.len());
+        let err = error(input, span);
+        assert_eq!(err.code().code, "E019");
+        assert_eq!(err.span(), 14..input.len());
+        assert_eq!(err.notes()[0].span, Some(14..16));
+
+        let mut db = Database::default();
+        let file = db.add_file(input.to_string());
+        let items = db.items(file);
+        let err = items.iter().find_map(|item| item.as_ref().err()).unwrap();
+        assert_eq!(err.code().code, "E019");
+    }
+}
@@ -0,0 +1,651 @@
+//! Lints: warnings about code that compiles, but is likely a mistake.
+//!
+//! Every lint has a name, a code in the registry and a default level. The
+//! `[lints]` section of `fir.toml` changes the level of a lint for the whole
+//! project, and a `// fir:allow(name, ...)` comment turns lints off for a
+//! part of a file. On a line of its own, the comment covers the item or
+//! statement that starts after it; after code, it covers its own line. An
+//! `#[allow(name, ...)]` attribute covers the item, member or statement it
+//! is on.
+//!
+//! The lints only look at the parts of the file that parsed.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use logos::{Logos, Span};
+
+use crate::{
+    ast::{
+        self,
+        visit::{self, Visitor},
+    },
+    attrs,
+    error::{
+        codes::{self, Code},
+        Error, Severity,
+    },
+    lexer::TokenKind,
+    types,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Level {
+    pub fn parse(level: &str) -> Option<Level> {
+        match level {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Allow => write!(f, "allow"),
+            Level::Warn => write!(f, "warn"),
+            Level::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Lint {
+    pub name: &'static str,
+    pub code: &'static Code,
+    pub default: Level,
+}
+
+macro_rules! lints {
+    ($($lint:ident: $name:literal, $code:ident, $level:ident;)*) => {
+        $(
+            pub const $lint: &Lint = &Lint {
+                name: $name,
+                code: codes::$code,
+                default: Level::$level,
+            };
+        )*
+
+        pub const LINTS: &[&Lint] = &[$($lint),*];
+    };
+}
+
+lints! {
+    UNUSED_VARIABLES: "unused_variables", W001, Warn;
+    UNUSED_PARAMETERS: "unused_parameters", W002, Warn;
+    UNUSED_FUNCTIONS: "unused_functions", W003, Warn;
+    SHADOWING: "shadowing", W004, Allow;
+    UNREACHABLE_CODE: "unreachable_code", W005, Warn;
+    CONSTANT_CONDITION: "constant_condition", W006, Warn;
+    NON_CAMEL_CASE_STRUCTS: "non_camel_case_structs", W007, Warn;
+    NON_SNAKE_CASE: "non_snake_case", W008, Warn;
+    UNKNOWN_LINTS: "unknown_lints", W009, Warn;
+}
+
+pub fn find(name: &str) -> Option<&'static Lint> {
+    LINTS.iter().copied().find(|lint| lint.name == name)
+}
+
+/// The lints that fire in `items`, parsed from `text`, in source order.
+/// `levels` overrides the default levels; allowed lints are left out and
+/// denied ones are errors.
+pub fn check(
+    text: &str,
+    items: &[Result<ast::Spanned<ast::Item>, Error>],
+    levels: &HashMap<&'static str, Level>,
+) -> Vec<Error> {
+    let mut linter = Linter::default();
+    linter.items(items);
+    let allows = allows(text, items, &mut linter.findings);
+
+    let mut findings: Vec<_> = linter
+        .findings
+        .into_iter()
+        .filter(|(lint, err)| {
+            !allows
+                .iter()
+                .any(|(names, span)| names.contains(&lint.name) && span.contains(&err.span().start))
+        })
+        .filter_map(|(lint, err)| {
+            let (level, note) = match levels.get(lint.name) {
+                Some(level) => (
+                    *level,
+                    format!("`{}` is set to {} in the manifest", lint.name, level),
+                ),
+                None => (
+                    lint.default,
+                    format!("`{}` is set to {} by default", lint.name, lint.default),
+                ),
+            };
+            let err = err.with_note(note, None);
+            match level {
+                Level::Allow => None,
+                Level::Warn => Some(err.with_severity(Severity::Warning)),
+                Level::Deny => Some(err.with_severity(Severity::Error)),
+            }
+        })
+        .collect();
+    findings.sort_by_key(|err| err.span().start);
+    findings
+}
+
+/// The `fir:allow` comments in `text` and the `#[allow]` attributes in
+/// `items`, as the lints they name and the range they cover. Names that are
+/// not lints are reported as such.
+fn allows(
+    text: &str,
+    items: &[Result<ast::Spanned<ast::Item>, Error>],
+    findings: &mut Vec<(&'static Lint, Error)>,
+) -> Vec<(Vec<&'static str>, Span)> {
+    let tokens: Vec<_> = TokenKind::lexer(text).spanned().collect();
+    let mut starts = Starts::default();
+    for item in items.iter().flatten() {
+        starts.visit_item(item);
+    }
+    let Starts(starts, attrs) = starts;
+
+    let mut allows = Vec::new();
+    for (names, covered) in attrs {
+        let lints = names
+            .into_iter()
+            .filter_map(|(name, span)| lint(findings, name.as_str(), span))
+            .collect();
+        allows.push((lints, covered));
+    }
+    for (i, (kind, span)) in tokens.iter().enumerate() {
+        if *kind != TokenKind::LineComment {
+            continue;
+        }
+        let comment = text[span.clone()].trim_start_matches('/').trim();
+        let names = match comment
+            .strip_prefix("fir:allow(")
+            .and_then(|names| names.strip_suffix(')'))
+        {
+            Some(names) => names,
+            None => continue,
+        };
+
+        let lints = names
+            .split(',')
+            .filter_map(|name| lint(findings, name.trim(), span.clone()))
+            .collect();
+
+        let start = line(text, span.start).start;
+        let covered = if !text[start..span.start].trim().is_empty() {
+            start..span.end
+        } else {
+            match tokens[i..].iter().find(|(kind, _)| !kind.is_trivia()) {
+                Some((_, next)) => starts
+                    .iter()
+                    .find(|span| span.start == next.start)
+                    .cloned()
+                    .unwrap_or_else(|| line(text, next.start)),
+                None => continue,
+            }
+        };
+        allows.push((lints, covered));
+    }
+    allows
+}
+
+/// The name of the lint `name`, which is reported at `span` if there is no
+/// such lint.
+fn lint(
+    findings: &mut Vec<(&'static Lint, Error)>,
+    name: &str,
+    span: Span,
+) -> Option<&'static str> {
+    match find(name) {
+        Some(lint) => Some(lint.name),
+        None => {
+            findings.push((
+                UNKNOWN_LINTS,
+                Error::new(
+                    format!("There is no lint named `{}`", name),
+                    UNKNOWN_LINTS.code,
+                    span,
+                ),
+            ));
+            None
+        }
+    }
+}
+
+/// The line `offset` is on, without the newline.
+fn line(text: &str, offset: usize) -> Span {
+    let start = text[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+    let end = text[offset..]
+        .find('\n')
+        .map_or(text.len(), |newline| offset + newline);
+    start..end
+}
+
+/// The spans of items and statements, outermost first, and the lints the
+/// `#[allow]` attributes of items, members and statements name, with the
+/// span of what they are on.
+#[derive(Default)]
+struct Starts(Vec<Span>, Vec<(Vec<(ast::Symbol, Span)>, Span)>);
+
+impl Starts {
+    fn allow(&mut self, attrs: &[ast::Spanned<ast::Attr>], span: Span) {
+        if let Some(allow) = attrs::get(attrs, "allow") {
+            let names = attrs::names(allow)
+                .map(|(name, span)| (*name, span.clone()))
+                .collect();
+            self.1.push((names, span));
+        }
+    }
+}
+
+impl Visitor for Starts {
+    fn visit_item(&mut self, item: &ast::Spanned<ast::Item>) {
+        self.0.push(item.span.clone());
+        self.allow(item.attrs(), item.span.clone());
+        if let ast::Item::Struct { members, .. } = &item.node {
+            for member in members {
+                let start = member
+                    .attrs
+                    .first()
+                    .map_or(&member.name.span, |attr| &attr.span);
+                let end = match &member.ty {
+                    Ok(ty) => ty.span.end,
+                    Err(_) => member.name.span.end,
+                };
+                self.allow(&member.attrs, start.start..end);
+            }
+        }
+        visit::walk_item(self, item);
+    }
+
+    fn visit_stmt(&mut self, body: &ast::Body, id: ast::StmtId, stmt: &ast::Spanned<ast::Stmt>) {
+        self.0.push(stmt.span.clone());
+        self.allow(body.attrs(id), stmt.span.clone());
+        visit::walk_stmt(self, body, stmt);
+    }
+}
+
+struct Binding {
+    name: ast::Ident,
+    is_param: bool,
+    used: bool,
+}
+
+#[derive(Default)]
+struct Linter {
+    findings: Vec<(&'static Lint, Error)>,
+    scopes: Vec<Vec<Binding>>,
+    /// The function being linted.
+    function: String,
+    /// The functions called by some other function.
+    called: HashSet<String>,
+}
+
+impl Linter {
+    fn report(&mut self, lint: &'static Lint, msg: String, span: Span) {
+        self.findings.push((lint, Error::new(msg, lint.code, span)));
+    }
+
+    fn items(&mut self, items: &[Result<ast::Spanned<ast::Item>, Error>]) {
+        for item in items.iter().flatten() {
+            self.visit_item(item);
+        }
+
+        for item in items.iter().flatten() {
+            if let ast::Item::Function { name, .. } = &item.node {
+                // `fir test` calls tests.
+                if !item.is_test()
+                    && name.as_str() != "main"
+                    && !self.called.contains(name.as_str())
+                {
+                    self.report(
+                        UNUSED_FUNCTIONS,
+                        format!("Function `{}` is never called", name),
+                        name.span.clone(),
+                    );
+                }
+            }
+        }
+    }
+
+    fn snake_case(&mut self, what: &str, name: &ast::Ident) {
+        if name.chars().any(|c| c.is_ascii_uppercase()) {
+            self.report(
+                NON_SNAKE_CASE,
+                format!("{} `{}` should have a snake_case name", what, name),
+                name.span.clone(),
+            );
+        }
+    }
+
+    fn pop_scope(&mut self) {
+        for binding in self.scopes.pop().unwrap() {
+            if binding.used {
+                continue;
+            }
+            let (lint, what) = if binding.is_param {
+                (UNUSED_PARAMETERS, "Parameter")
+            } else {
+                (UNUSED_VARIABLES, "Variable")
+            };
+            self.report(
+                lint,
+                format!("{} `{}` is never used", what, binding.name),
+                binding.name.span.clone(),
+            );
+        }
+    }
+
+    fn use_binding(&mut self, name: &ast::Ident) {
+        let binding = self
+            .scopes
+            .iter_mut()
+            .flatten()
+            .rev()
+            .find(|binding| *binding.name == **name);
+        if let Some(binding) = binding {
+            binding.used = true;
+        }
+    }
+}
+
+impl Visitor for Linter {
+    fn visit_item(&mut self, item: &ast::Spanned<ast::Item>) {
+        match &item.node {
+            ast::Item::Struct { name: Ok(ty), .. } => {
+                let name = &ty.name;
+                if !is_camel_case(name) {
+                    self.report(
+                        NON_CAMEL_CASE_STRUCTS,
+                        format!("Struct `{}` should have an UpperCamelCase name", name),
+                        name.span.clone(),
+                    );
+                }
+                visit::walk_item(self, item);
+            }
+            ast::Item::Struct { .. } => visit::walk_item(self, item),
+            ast::Item::Function {
+                name, parameters, ..
+            } => {
+                self.snake_case("Function", name);
+                self.function = name.to_string();
+                let mut params = Vec::new();
+                for (param, _) in parameters {
+                    self.snake_case("Parameter", param);
+                    params.push(Binding {
+                        name: param.clone(),
+                        is_param: true,
+                        used: false,
+                    });
+                }
+                self.scopes.push(params);
+                visit::walk_item(self, item);
+                self.pop_scope();
+            }
+        }
+    }
+
+    fn visit_block(&mut self, body: &ast::Body, stmts: &[ast::StmtId]) {
+        self.scopes.push(Vec::new());
+        let mut returned = false;
+        for (i, id) in stmts.iter().enumerate() {
+            let stmt = match &body[*id] {
+                Ok(stmt) => stmt,
+                Err(_) => continue,
+            };
+            self.visit_stmt(body, *id, stmt);
+            // Reported once, for everything after the statement.
+            if !returned && types::returns(body, std::slice::from_ref(id)) == Ok(true) {
+                returned = true;
+                let rest: Vec<_> = stmts[i + 1..]
+                    .iter()
+                    .filter_map(|stmt| body[*stmt].as_ref().ok())
+                    .collect();
+                if let (Some(first), Some(last)) = (rest.first(), rest.last()) {
+                    self.report(
+                        UNREACHABLE_CODE,
+                        "Unreachable code, every path before it returns".to_string(),
+                        first.span.start..last.span.end,
+                    );
+                }
+            }
+        }
+        self.pop_scope();
+    }
+
+    fn visit_stmt(&mut self, body: &ast::Body, _id: ast::StmtId, stmt: &ast::Spanned<ast::Stmt>) {
+        // The value of a `let` is seen before its name.
+        visit::walk_stmt(self, body, stmt);
+        match &stmt.node {
+            ast::Stmt::Let { var_name, .. } => {
+                self.snake_case("Variable", var_name);
+                let shadowed = self
+                    .scopes
+                    .iter()
+                    .flatten()
+                    .any(|binding| *binding.name == **var_name);
+                if shadowed {
+                    self.report(
+                        SHADOWING,
+                        format!("`{}` shadows an earlier binding", var_name),
+                        var_name.span.clone(),
+                    );
+                }
+                self.scopes.last_mut().unwrap().push(Binding {
+                    name: var_name.clone(),
+                    is_param: false,
+                    used: false,
+                });
+            }
+            // An object may have other names the assignment is seen by.
+            ast::Stmt::Assignment { var_name, .. } => self.use_binding(var_name),
+            ast::Stmt::IfStmt { condition, .. } => {
+                if let Ok(condition) = &body[*condition] {
+                    if is_constant(body, condition) {
+                        self.report(
+                            CONSTANT_CONDITION,
+                            "The condition only depends on literals".to_string(),
+                            condition.span.clone(),
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn visit_expr(&mut self, body: &ast::Body, _id: ast::ExprId, expr: &ast::Spanned<ast::Expr>) {
+        match &expr.node {
+            ast::Expr::Ident(name) => self.use_binding(name),
+            ast::Expr::FnCall { fn_name, .. } if fn_name.as_str() != self.function => {
+                self.called.insert(fn_name.to_string());
+            }
+            _ => {}
+        }
+        visit::walk_expr(self, body, expr);
+    }
+}
+
+/// Whether `expr` is made of literals and operators only.
+fn is_constant(body: &ast::Body, expr: &ast::Spanned<ast::Expr>) -> bool {
+    let constant = |expr: &ast::ExprId| match &body[*expr] {
+        Ok(expr) => is_constant(body, expr),
+        Err(_) => false,
+    };
+    match &expr.node {
+        ast::Expr::Literal(_) => true,
+        ast::Expr::Ident(_) | ast::Expr::FnCall { .. } => false,
+        ast::Expr::PrefixOp { expr, .. }
+        | ast::Expr::PostfixOp { expr, .. }
+        | ast::Expr::Member { expr, .. } => constant(expr),
+        ast::Expr::InfixOp { lhs, rhs, .. } => constant(lhs) && constant(rhs),
+        ast::Expr::StructLit { fields, .. } => fields.iter().all(|(_, value)| constant(value)),
+    }
+}
+
+fn is_camel_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase()) && !name.contains('_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cst;
+
+    /// The code, severity and source of each finding in `text`.
+    fn lints(text: &str, levels: &[(&'static str, Level)]) -> Vec<(String, Severity, String)> {
+        let items = cst::lower::items(&cst::parse(text));
+        check(text, &items, &levels.iter().copied().collect())
+            .iter()
+            .map(|err| {
+                (
+                    err.code().code.to_string(),
+                    err.severity(),
+                    text[err.span()].to_string(),
+                )
+            })
+            .collect()
+    }
+
+    fn warnings(findings: &[(&str, &str)]) -> Vec<(String, Severity, String)> {
+        findings
+            .iter()
+            .map(|(code, source)| (code.to_string(), Severity::Warning, source.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn every_lint_fires() {
+        let cases: &[(&str, &[(&str, &str)])] = &[
+            (
+                "fn main() {\n    let x = 1;\n    print(2);\n}\n",
+                &[("W001", "x")],
+            ),
+            (
+                "fn greet(name: string, times: int) {\n    print(name);\n}\n\n\
+                 fn main() {\n    greet(\"fir\", 2);\n}\n",
+                &[("W002", "times")],
+            ),
+            (
+                "fn helper() -> int {\n    return 1;\n}\n\nfn main() {\n}\n",
+                &[("W003", "helper")],
+            ),
+            (
+                "fn sign(x: int) -> int {\n    return 1;\n    print(x);\n}\n\n\
+                 fn main() {\n    print(sign(2));\n}\n",
+                &[("W005", "print(x);")],
+            ),
+            (
+                "fn main() {\n    if (1 < 2) {\n        print(1);\n    }\n}\n",
+                &[("W006", "1 < 2")],
+            ),
+            (
+                "struct point_2d {\n    x: int,\n}\n\nfn main() {\n}\n",
+                &[("W007", "point_2d")],
+            ),
+            (
+                "fn addTwo(firstValue: int) -> int {\n    return firstValue + 2;\n}\n\n\
+                 fn main() {\n    print(addTwo(1));\n}\n",
+                &[("W008", "addTwo"), ("W008", "firstValue")],
+            ),
+            (
+                "#[allow(unused_variable)]\nfn main() {\n    let x = 1;\n}\n",
+                &[("W009", "unused_variable"), ("W001", "x")],
+            ),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(lints(text, &[]), warnings(expected), "{}", text);
+        }
+    }
+
+    #[test]
+    fn shadowing_is_allowed_by_default() {
+        let text = "fn main() {\n    let x = 1;\n    let x = x + 1;\n    print(x);\n}\n";
+        assert_eq!(lints(text, &[]), []);
+        assert_eq!(
+            lints(text, &[("shadowing", Level::Warn)]),
+            warnings(&[("W004", "x")])
+        );
+    }
+
+    #[test]
+    fn comments_allow_lints() {
+        // On a line of its own, the comment covers the statement after it.
+        let text = "fn main() {
+    // fir:allow(unused_variables)
+    let x = 1;
+    let y = 2;
+}
+";
+        assert_eq!(lints(text, &[]), warnings(&[("W001", "y")]));
+
+        // After code, it covers its own line.
+        let text = "fn main() {
+    let x = 1; // fir:allow(unused_variables)
+    let y = 2;
+}
+";
+        assert_eq!(lints(text, &[]), warnings(&[("W001", "y")]));
+
+        // Before an item, it covers all of it, and it can name several lints.
+        let text = "// fir:allow(unused_functions, unused_variables)
+fn helper() {
+    let x = 1;
+}
+
+fn main() {
+    let y = 2;
+}
+";
+        assert_eq!(lints(text, &[]), warnings(&[("W001", "y")]));
+
+        let text = "fn main() {\n    // fir:allow(unused_variable)\n    let x = 1;\n}\n";
+        assert_eq!(
+            lints(text, &[]),
+            warnings(&[("W009", "// fir:allow(unused_variable)"), ("W001", "x")])
+        );
+    }
+
+    #[test]
+    fn attributes_allow_lints() {
+        let text = "#[allow(unused_variables)]
+fn main() {
+    let x = 1;
+    #[allow(constant_condition)]
+    if (true) {
+        print(1);
+    }
+    if (false) {
+        print(2);
+    }
+}
+";
+        assert_eq!(lints(text, &[]), warnings(&[("W006", "false")]));
+    }
+
+    #[test]
+    fn levels_from_the_manifest_apply() {
+        let text = "fn main() {\n    let x = 1;\n    if (true) {\n        print(1);\n    }\n}\n";
+        let levels = [
+            ("unused_variables", Level::Deny),
+            ("constant_condition", Level::Allow),
+        ];
+        assert_eq!(
+            lints(text, &levels),
+            [("W001".to_string(), Severity::Error, "x".to_string())]
+        );
+        let items = cst::lower::items(&cst::parse(text));
+        let errors = check(text, &items, &levels.iter().copied().collect());
+        assert_eq!(
+            errors[0].notes()[0].msg,
+            "`unused_variables` is set to deny in the manifest"
+        );
+    }
+}
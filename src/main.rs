@@ -1,23 +1,488 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
+
 mod ast;
+mod attrs;
+mod bytecode;
+mod codegen;
+mod cst;
+mod db;
+mod doc;
 mod error;
+mod fix;
 mod lexer;
-mod parser;
+mod lint;
+mod manifest;
+mod mir;
+mod ownership;
+mod parallel;
+mod testing;
+mod types;
+
+const USAGE: &str = "usage: fir build [--emit=rust|llvm|mir|firc|ownership|parallel] [-O0|-O1|-O2] [--no-parallel] <file>
+       fir run [-O0|-O1|-O2] <file.fir|file.firc|file.json>
+       fir disasm [-O0|-O1|-O2] <file.fir|file.firc|file.json>
+       fir check [--message-format=human|json|sarif] <file>
+       fir fix <file>
+       fir parse [--emit=cst|ast-json|fir|sexp] <file>
+       fir doc [--out=<dir>] <project dir|file>
+       fir test [--backend=vm|rust] [-O0|-O1|-O2] [--no-parallel] <dir|file> [<filter>]
+       fir explain [<code>]";
 
 fn main() {
-    // let args: Vec<String> = env::args().collect();
-    // let file_path = &args[1];
-    // let contents = fs::read_to_string(file_path).expect("Should have been able to read the file");
-    // let mut parser = parser::Parser::new(&contents);
-    // for _ in 0..14 {
-    //     println!("---------------------------------");
-    //     dbg!(parser.next(), parser.text());
-    //     println!("---------------------------------");
-    //     println!();
-    // }
-    let imp = "fn addasdasd(x: String, y: String) {
-        let a = lsss
-    }";
-    // dbg!(parser::Parser::parse(imp));
-    println!("{:#?}", parser::Parser::parse(imp));
-    println!("Hello world");
+    let args: Vec<String> = env::args().skip(1).collect();
+    let command = match args.first().map(String::as_str) {
+        Some(
+            command @ ("build" | "run" | "disasm" | "check" | "fix" | "parse" | "doc" | "test"),
+        ) => command,
+        Some("explain") => return explain(args.get(1).map(String::as_str)),
+        _ => exit(USAGE),
+    };
+
+    let mut emit = None;
+    let mut message_format = None;
+    let mut out = None;
+    let mut backend = None;
+    let mut filter = None;
+    let mut parallel = true;
+    let mut level = 0;
+    let mut file_path = None;
+    for arg in &args[1..] {
+        if arg == "--no-parallel" {
+            parallel = false;
+        } else if let Some(kind) = arg.strip_prefix("--emit=") {
+            emit = Some(kind);
+        } else if let Some(format) = arg.strip_prefix("--message-format=") {
+            message_format = Some(format);
+        } else if let Some(dir) = arg.strip_prefix("--out=") {
+            out = Some(dir);
+        } else if let Some(name) = arg.strip_prefix("--backend=") {
+            backend = Some(name);
+        } else if let Some(digits) = arg.strip_prefix("-O") {
+            level = match digits {
+                "" => 2,
+                digits => digits.parse().unwrap_or_else(|_| exit(USAGE)),
+            };
+        } else if command == "test" && file_path.is_some() {
+            filter = Some(arg.as_str());
+        } else {
+            file_path = Some(arg.as_str());
+        }
+    }
+    let file_path = file_path.unwrap_or_else(|| exit(USAGE));
+
+    match command {
+        "run" => {
+            let module = load(file_path, level).unwrap_or_else(|err| exit(&err.to_string()));
+            if let Err(err) = bytecode::vm::run(&module, &mut io::stdout().lock()) {
+                exit(&err.to_string());
+            }
+            return;
+        }
+        "disasm" => {
+            let module = load(file_path, level).unwrap_or_else(|err| exit(&err.to_string()));
+            print!("{}", module);
+            return;
+        }
+        "check" => {
+            let format = message_format.unwrap_or("human");
+            if !matches!(format, "human" | "json" | "sarif") {
+                exit(&format!("unknown message format `{}`\n{}", format, USAGE));
+            }
+            let (path, text, errors) = match manifest(file_path) {
+                Ok(manifest) => {
+                    let text = read(file_path);
+                    let mut db = db::Database::default();
+                    let file = db.add_file(text.clone());
+                    let mut errors = lint::check(&text, &db.items(file), &manifest.lints);
+                    errors.extend(db.errors(file));
+                    (file_path.to_string(), text, errors)
+                }
+                Err(found) => {
+                    let (path, text, err) = *found;
+                    (path, text, vec![err])
+                }
+            };
+            let diagnostics: Vec<_> = errors
+                .iter()
+                .map(|error| error::report::Diagnostic {
+                    path: &path,
+                    text: &text,
+                    error,
+                })
+                .collect();
+            match format {
+                "json" => {
+                    for diagnostic in &diagnostics {
+                        println!("{}", error::report::json(diagnostic));
+                    }
+                }
+                "sarif" => println!("{}", error::report::sarif(&diagnostics)),
+                _ if path == file_path => {
+                    for err in &errors {
+                        eprintln!("{}", err);
+                    }
+                }
+                _ => {
+                    for err in &errors {
+                        eprintln!("{}: {}", path, err);
+                    }
+                }
+            }
+            if errors
+                .iter()
+                .any(|err| err.severity() == error::Severity::Error)
+            {
+                process::exit(1);
+            }
+            return;
+        }
+        "fix" => {
+            let (text, applied) = fix::fix(&read(file_path));
+            if applied > 0 {
+                // Written next to the file first, so an error can not leave
+                // it half written.
+                let temp = format!("{}.fixing", file_path);
+                if let Err(err) =
+                    fs::write(&temp, &text).and_then(|()| fs::rename(&temp, file_path))
+                {
+                    exit(&format!("could not write `{}`: {}", file_path, err));
+                }
+            }
+            eprintln!("applied {} fixes to `{}`", applied, file_path);
+            let errors = cst::parse(&text).errors;
+            for err in &errors {
+                eprintln!("{}", err);
+            }
+            if !errors.is_empty() {
+                process::exit(1);
+            }
+            return;
+        }
+        "parse" => {
+            let text = read(file_path);
+            let parse = cst::parse(&text);
+            match emit.unwrap_or("cst") {
+                "cst" => print!("{}", parse.syntax().dump()),
+                kind @ ("ast-json" | "fir" | "sexp") => {
+                    let mut db = db::Database::default();
+                    let file = db.add_file(text);
+                    let items = db.items(file);
+                    match kind {
+                        "ast-json" => println!("{}", ast::json::write(&items)),
+                        "fir" => print!("{}", ast::print::source(&items)),
+                        _ => print!("{}", ast::print::sexp(&items)),
+                    }
+                }
+                kind => exit(&format!("unknown output kind `{}`\n{}", kind, USAGE)),
+            }
+            for err in &parse.errors {
+                eprintln!("{}", err);
+            }
+            if !parse.errors.is_empty() {
+                process::exit(1);
+            }
+            return;
+        }
+        "doc" => {
+            let root = project(Path::new(file_path));
+            let out = out.map_or_else(|| root.join("target").join("doc"), PathBuf::from);
+            let mut paths = Vec::new();
+            if let Err(err) = sources(&root, &out, &mut paths) {
+                exit(&format!("could not read `{}`: {}", root.display(), err));
+            }
+            paths.sort();
+
+            let mut db = db::Database::default();
+            let items: Vec<_> = paths
+                .iter()
+                .map(|path| {
+                    let file = db.add_file(read(&path.display().to_string()));
+                    db.items(file)
+                })
+                .collect();
+            let modules: Vec<_> = paths
+                .iter()
+                .zip(&items)
+                .map(|(path, items)| doc::Module {
+                    name: module_name(&root, path),
+                    path: module_path(&root, path),
+                    items,
+                })
+                .collect();
+            for (name, contents) in doc::site(&modules) {
+                let path = out.join(name);
+                if let Err(err) = fs::create_dir_all(&out).and_then(|()| fs::write(&path, contents))
+                {
+                    exit(&format!("could not write `{}`: {}", path.display(), err));
+                }
+            }
+            eprintln!(
+                "documented {} modules in `{}`",
+                modules.len(),
+                out.display()
+            );
+            return;
+        }
+        "test" => {
+            let path = Path::new(file_path);
+            let backend = backend.unwrap_or("vm");
+            if !matches!(backend, "vm" | "rust") {
+                exit(&format!("unknown backend `{}`\n{}", backend, USAGE));
+            }
+            // A directory is tested module by module, like `fir doc` documents
+            // it.
+            let mut paths = Vec::new();
+            if path.is_dir() {
+                if let Err(err) = sources(path, &path.join("target"), &mut paths) {
+                    exit(&format!("could not read `{}`: {}", path.display(), err));
+                }
+                paths.sort();
+            } else {
+                paths.push(path.to_path_buf());
+            }
+
+            let mut db = db::Database::default();
+            let mut vm = Vec::new();
+            let mut rust = Vec::new();
+            for source in &paths {
+                let module = if path.is_dir() {
+                    module_name(path, source)
+                } else {
+                    String::new()
+                };
+                let file = open(&mut db, &source.display().to_string());
+                let tests = testing::tests(&db.items(file));
+                if tests.is_empty() {
+                    continue;
+                }
+                // The Rust backend does not check types itself.
+                let program = lower(&mut db, file, level).unwrap_or_else(|err| {
+                    exit(&format!("{}: {}", source.display(), err));
+                });
+                if backend == "vm" {
+                    vm.push(testing::Suite {
+                        module,
+                        tests,
+                        code: program,
+                    });
+                } else {
+                    let code = emit_rust(&mut db, file, parallel).unwrap_or_else(|err| {
+                        exit(&format!("{}: {}", source.display(), err));
+                    });
+                    rust.push(testing::Suite {
+                        module,
+                        tests,
+                        code,
+                    });
+                }
+            }
+            let passed = if backend == "vm" {
+                testing::run(&vm, filter, parallel)
+            } else {
+                let dir = project(path).join("target").join("test");
+                testing::run_rust(&rust, &dir, filter, parallel).unwrap_or_else(|err| exit(&err))
+            };
+            if !passed {
+                process::exit(1);
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    let mut db = db::Database::default();
+    let file = open(&mut db, file_path);
+    let items = db.items(file);
+    let output = match emit.unwrap_or("rust") {
+        // The Rust backend works on the AST, for the ownership and the
+        // parallel plan, but only once the program checks like the MIR does.
+        "rust" => lower(&mut db, file, level)
+            .and_then(|_| emit_rust(&mut db, file, parallel))
+            .map(String::into_bytes),
+        "llvm" => lower(&mut db, file, level)
+            .and_then(|program| codegen::llvm::emit(&program))
+            .map(String::into_bytes),
+        "mir" => lower(&mut db, file, level).map(|program| program.to_string().into_bytes()),
+        "firc" => lower(&mut db, file, level)
+            .and_then(|program| bytecode::compile(&program))
+            .map(|module| bytecode::firc::write(&module)),
+        "ownership" => db
+            .ownership(file)
+            .as_ref()
+            .as_ref()
+            .map(|ownership| ownership.to_string().into_bytes())
+            .map_err(Clone::clone),
+        "parallel" => db
+            .ownership(file)
+            .as_ref()
+            .as_ref()
+            .map_err(Clone::clone)
+            .and_then(|ownership| parallel::analyze(&items, ownership, parallel))
+            .map(|plan| plan.to_string().into_bytes()),
+        kind => exit(&format!("unknown output kind `{}`\n{}", kind, USAGE)),
+    };
+    match output {
+        Ok(output) => {
+            if let Err(err) = io::stdout().write_all(&output) {
+                exit(&format!("could not write the output: {}", err));
+            }
+        }
+        Err(err) => exit(&err.to_string()),
+    }
+}
+
+/// Prints the explanation of an error code, or lists every code.
+fn explain(code: Option<&str>) {
+    let code = match code {
+        Some(code) => code,
+        None => {
+            for code in error::codes::CODES {
+                println!("{}: {}", code.code, code.title);
+            }
+            return;
+        }
+    };
+    match error::codes::find(code) {
+        Some(code) => print!(
+            "{}[{}]: {}\n\n{}",
+            code.severity, code.code, code.title, code.explanation
+        ),
+        None => exit(&format!(
+            "`{}` is not an error code, `fir explain` lists them",
+            code
+        )),
+    }
+}
+
+fn read(file_path: &str) -> String {
+    fs::read_to_string(file_path)
+        .unwrap_or_else(|err| exit(&format!("could not read `{}`: {}", file_path, err)))
+}
+
+/// Loads a `.firc` file, or compiles a source or AST JSON file to bytecode
+/// at `level`.
+fn load(file_path: &str, level: u8) -> Result<bytecode::Module, error::Error> {
+    if file_path.ends_with(".firc") {
+        let bytes = fs::read(file_path)
+            .unwrap_or_else(|err| exit(&format!("could not read `{}`: {}", file_path, err)));
+        return bytecode::firc::read(&bytes);
+    }
+    let mut db = db::Database::default();
+    let file = open(&mut db, file_path);
+    bytecode::compile(&lower(&mut db, file, level)?)
+}
+
+/// Adds a source file, or the AST JSON of one, to `db`. The lints of a
+/// source file are printed, and if one of them is denied this exits.
+fn open(db: &mut db::Database, file_path: &str) -> db::FileId {
+    if file_path.ends_with(".json") {
+        let items = ast::json::read(&read(file_path)).unwrap_or_else(|err| exit(&err.to_string()));
+        return db.add_items(items);
+    }
+    let file = db.add_file(read(file_path));
+    if lint(db, file, file_path) {
+        process::exit(1);
+    }
+    file
+}
+
+/// The directory of the project `path` is in: `path` itself if it is a
+/// directory, or else the one with the manifest or the one of the file.
+fn project(path: &Path) -> PathBuf {
+    if path.is_dir() {
+        return path.to_path_buf();
+    }
+    match manifest::find(path) {
+        Ok(Some((manifest, _))) => manifest.parent().unwrap().to_path_buf(),
+        Ok(None) => fs::canonicalize(path)
+            .map(|path| path.parent().unwrap().to_path_buf())
+            .unwrap_or_else(|err| exit(&format!("could not read `{}`: {}", path.display(), err))),
+        Err(err) => exit(&format!("could not read the manifest: {}", err)),
+    }
+}
+
+/// Adds the `.fir` files in `dir` and the directories below it to `paths`,
+/// leaving out `out` and hidden directories.
+fn sources(dir: &Path, out: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if path.is_dir() {
+            if !hidden && path != out {
+                sources(&path, out, paths)?;
+            }
+        } else if path.extension().is_some_and(|ext| ext == "fir") {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The name of the module at `path` in the project at `root`, e.g.
+/// `geometry::point` for `geometry/point.fir`.
+fn module_name(root: &Path, path: &Path) -> String {
+    module_path(root, path).join("::")
+}
+
+/// The directories and the file of `path` in the project at `root`,
+/// without the `.fir`.
+fn module_path(root: &Path, path: &Path) -> Vec<String> {
+    let path = path.strip_prefix(root).unwrap_or(path).with_extension("");
+    path.iter()
+        .map(|part| part.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// The manifest of the project `file_path` is in, or the path and text of
+/// the manifest together with the error in it.
+fn manifest(file_path: &str) -> Result<manifest::Manifest, Box<(String, String, error::Error)>> {
+    match manifest::find(Path::new(file_path)) {
+        Ok(Some((path, text))) => {
+            manifest::parse(&text).map_err(|err| Box::new((path.display().to_string(), text, err)))
+        }
+        Ok(None) => Ok(Default::default()),
+        Err(err) => exit(&format!("could not read the manifest: {}", err)),
+    }
+}
+
+/// Prints the lints that fire in `file`, with the levels set in the
+/// manifest of the project, and returns whether one of them is denied.
+fn lint(db: &mut db::Database, file: db::FileId, file_path: &str) -> bool {
+    let levels = match manifest(file_path) {
+        Ok(manifest) => manifest.lints,
+        Err(found) => exit(&format!("{}: {}", found.0, found.2)),
+    };
+    let findings = lint::check(&db.text(file), &db.items(file), &levels);
+    for finding in &findings {
+        eprintln!("{}", finding);
+    }
+    findings
+        .iter()
+        .any(|finding| finding.severity() == error::Severity::Error)
+}
+
+/// The MIR of `file`, optimized at `level`.
+fn lower(db: &mut db::Database, file: db::FileId, level: u8) -> Result<mir::Program, error::Error> {
+    (*db.mir(file, level)).clone()
+}
+
+/// The Rust source of `file`, which has to check.
+fn emit_rust(
+    db: &mut db::Database,
+    file: db::FileId,
+    parallel: bool,
+) -> Result<String, error::Error> {
+    let exprs = db.expr_types(file)?;
+    let ownership = db.ownership(file);
+    let ownership = ownership.as_ref().as_ref().map_err(Clone::clone)?;
+    codegen::rust::emit(&db.items(file), &exprs, ownership, parallel)
+}
+
+fn exit(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    process::exit(1)
 }
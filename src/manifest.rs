@@ -0,0 +1,159 @@
+//! The project manifest, `fir.toml`.
+//!
+//! The manifest is a small subset of TOML: `[section]` headers followed by
+//! `key = "value"` lines, and `#` comments. Only the `[lints]` section is
+//! read so far, which sets the level of lints by name:
+//!
+//! ```toml
+//! [lints]
+//! shadowing = "warn"
+//! unused_parameters = "allow"
+//! ```
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use logos::Span;
+
+use crate::{
+    error::{codes, Error},
+    lint::{self, Level},
+};
+
+pub const FILE_NAME: &str = "fir.toml";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    /// The levels set for lints, by the name of the lint.
+    pub lints: HashMap<&'static str, Level>,
+}
+
+/// The manifest in the directory of `source` or the closest directory above
+/// it that has one, together with its path.
+pub fn find(source: &Path) -> io::Result<Option<(PathBuf, String)>> {
+    let source = fs::canonicalize(source)?;
+    for dir in source.ancestors().skip(1) {
+        let path = dir.join(FILE_NAME);
+        if path.is_file() {
+            let text = fs::read_to_string(&path)?;
+            return Ok(Some((path, text)));
+        }
+    }
+    Ok(None)
+}
+
+pub fn parse(text: &str) -> Result<Manifest, Error> {
+    let mut manifest = Manifest::default();
+    let mut section = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let line = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => line,
+        };
+        let trimmed = line.trim();
+        let span = start + line.find(trimmed).unwrap_or(0)..start + line.trim_end().len();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(name) = trimmed
+            .strip_prefix('[')
+            .and_then(|name| name.strip_suffix(']'))
+        {
+            section = Some(name.trim());
+            continue;
+        }
+
+        let (key, value) = match trimmed.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => {
+                return Err(error(
+                    format!("Expected `key = \"value\"`, but found `{}`", trimmed),
+                    span,
+                ))
+            }
+        };
+        let value = match value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+        {
+            Some(value) => value,
+            None => {
+                return Err(error(
+                    format!("The value of `{}` must be a string in quotes", key),
+                    span,
+                ))
+            }
+        };
+        match section {
+            Some("lints") => {
+                let lint = lint::find(key).ok_or_else(|| {
+                    error(format!("There is no lint named `{}`", key), span.clone())
+                })?;
+                let level = Level::parse(value).ok_or_else(|| {
+                    error(
+                        format!(
+                            "`{}` is not a lint level, which are `allow`, `warn` and `deny`",
+                            value
+                        ),
+                        span.clone(),
+                    )
+                })?;
+                manifest.lints.insert(lint.name, level);
+            }
+            Some(_) => {}
+            None => return Err(error(format!("`{}` is not in a section", key), span)),
+        }
+    }
+    Ok(manifest)
+}
+
+fn error(msg: String, span: Span) -> Error {
+    Error::new(msg, codes::E012, span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lint_levels_are_read() {
+        let manifest = parse(
+            "# The project's lints.
+[package]
+name = \"demo\"
+
+[lints]
+shadowing = \"deny\"  # no shadowing at all
+unused_parameters = \"allow\"
+",
+        )
+        .unwrap();
+        assert_eq!(
+            manifest.lints,
+            HashMap::from([
+                ("shadowing", Level::Deny),
+                ("unused_parameters", Level::Allow)
+            ])
+        );
+    }
+
+    #[test]
+    fn mistakes_are_errors_at_their_line() {
+        let cases = [
+            ("[lints]\nshadow = \"deny\"\n", "shadow = \"deny\""),
+            ("[lints]\nshadowing = \"never\"\n", "shadowing = \"never\""),
+            ("[lints]\nshadowing = deny\n", "shadowing = deny"),
+            ("[lints]\nshadowing\n", "shadowing"),
+            ("shadowing = \"deny\"\n", "shadowing = \"deny\""),
+        ];
+        for (text, line) in cases {
+            let err = parse(text).unwrap_err();
+            assert_eq!(err.code().code, "E012", "{}", text);
+            assert_eq!(&text[err.span()], line, "{}", text);
+        }
+    }
+}
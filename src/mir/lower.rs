@@ -0,0 +1,483 @@
+use std::collections::HashMap;
+
+use logos::Span;
+
+use crate::{
+    ast::{self, print, Spanned},
+    error::{codes, Error},
+    lexer::TokenKind,
+    ownership::{self, FnOwnership, Mode},
+    types::{self, Ty, Types},
+};
+
+use super::{
+    BinOp, Block, BlockId, Const, Function, Local, LocalId, Operand, Rvalue, Stmt, Terminator, UnOp,
+};
+
+/// Lowers `item` if it is a function, which must have passed the type
+/// checker. `func` is its ownership and `signatures` are the parameter
+/// modes of all functions, as `ownership::analyze` decided them.
+///
+/// Parameters taken as `&mut` become `by_ref` locals, and a `let` ownership
+/// decided to share is lowered as another name for the local it copies, so
+/// every name of an object sees assignments to it. Plain values are always
+/// copied, whatever their mode.
+pub fn lower_function(
+    types: &Types,
+    func: &FnOwnership,
+    signatures: &HashMap<String, Vec<Mode>>,
+    item: &ast::Spanned<ast::Item>,
+) -> Result<Option<Function>, Error> {
+    let (name, parameters, body) = match &item.node {
+        ast::Item::Function {
+            name,
+            parameters,
+            body,
+            ..
+        } => (name, parameters, body),
+        ast::Item::Struct { .. } => return Ok(None),
+    };
+    let sig = &types.functions[name.as_str()];
+    let mut builder = Builder {
+        types,
+        signatures,
+        func,
+        body,
+        lets: parameters.len(),
+        locals: Vec::new(),
+        blocks: vec![(Vec::new(), None)],
+        current: 0,
+        scopes: vec![HashMap::new()],
+    };
+    for (index, ((param, _), ty)) in parameters.iter().zip(&sig.params).enumerate() {
+        builder.locals.push(Local {
+            name: Some(param.to_string()),
+            ty: ty.clone(),
+            by_ref: func.bindings[index].mode == Mode::BorrowMut,
+        });
+        builder.scopes[0].insert(param.to_string(), index);
+    }
+
+    builder.stmts(&body.block)?;
+    if builder.blocks[builder.current].1.is_none() {
+        let terminator = if sig.ret == Ty::Unit {
+            Terminator::Return(None)
+        } else {
+            Terminator::Unreachable
+        };
+        builder.terminate(terminator);
+    }
+
+    Ok(Some(Function {
+        name: name.to_string(),
+        params: parameters.len(),
+        ret: sig.ret.clone(),
+        locals: builder.locals,
+        blocks: builder
+            .blocks
+            .into_iter()
+            .map(|(stmts, terminator)| Block {
+                stmts,
+                terminator: terminator.unwrap_or(Terminator::Unreachable),
+            })
+            .collect(),
+        span: item.span.clone(),
+    }))
+}
+
+struct Builder<'a> {
+    types: &'a Types,
+    signatures: &'a HashMap<String, Vec<Mode>>,
+    func: &'a FnOwnership,
+    body: &'a ast::Body,
+    /// Index of the binding of the next `let`. `ownership` records parameters
+    /// first and then every `let` in the order they appear.
+    lets: usize,
+    locals: Vec<Local>,
+    /// Blocks and their terminators, which are missing while the block is
+    /// still being built.
+    blocks: Vec<(Vec<Spanned<Stmt>>, Option<Terminator>)>,
+    current: BlockId,
+    scopes: Vec<HashMap<String, LocalId>>,
+}
+
+impl<'a> Builder<'a> {
+    fn local(&mut self, name: Option<String>, ty: Ty) -> LocalId {
+        self.locals.push(Local {
+            name,
+            ty,
+            by_ref: false,
+        });
+        self.locals.len() - 1
+    }
+
+    fn block(&mut self) -> BlockId {
+        self.blocks.push((Vec::new(), None));
+        self.blocks.len() - 1
+    }
+
+    /// Adds `stmt`, lowered from the source at `span`, to the current block.
+    /// Code following a terminator, e.g. after a `return`, goes to a new
+    /// block nothing jumps to.
+    fn push(&mut self, stmt: Stmt, span: Span) {
+        if self.blocks[self.current].1.is_some() {
+            self.current = self.block();
+        }
+        self.blocks[self.current].0.push(Spanned::new(stmt, span));
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        if self.blocks[self.current].1.is_some() {
+            self.current = self.block();
+        }
+        self.blocks[self.current].1 = Some(terminator);
+    }
+
+    fn terminated(&self) -> bool {
+        self.blocks[self.current].1.is_some()
+    }
+
+    fn lookup(&self, name: &str) -> LocalId {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+            .expect("names are resolved by the type checker")
+    }
+
+    fn stmts(&mut self, stmts: &[ast::StmtId]) -> Result<(), Error> {
+        self.scopes.push(HashMap::new());
+        for stmt in stmts {
+            self.stmt(self.body.stmt(*stmt)?)?;
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &ast::Stmt) -> Result<(), Error> {
+        match stmt {
+            ast::Stmt::Let { var_name, value } => {
+                let value = self.body.expr(*value)?;
+                let binding = &self.func.bindings[self.lets];
+                self.lets += 1;
+                let local = match &value.node {
+                    ast::Expr::Ident(source)
+                        if binding.mode == Mode::Shared && !ownership::is_copy(&binding.ty) =>
+                    {
+                        self.lookup(source)
+                    }
+                    _ => {
+                        let span = value.span.clone();
+                        let value = self.rvalue(value)?;
+                        let ty = self.rvalue_ty(&value);
+                        let local = self.local(Some(var_name.to_string()), ty);
+                        self.push(Stmt::Assign(local, value), span);
+                        local
+                    }
+                };
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .insert(var_name.to_string(), local);
+            }
+            ast::Stmt::Assignment { var_name, value } => {
+                let value = self.body.expr(*value)?;
+                let span = value.span.clone();
+                let value = self.rvalue(value)?;
+                let local = self.lookup(var_name);
+                self.push(Stmt::Assign(local, value), span);
+            }
+            ast::Stmt::IfStmt {
+                condition,
+                body,
+                else_stmt,
+            } => {
+                let condition = self.operand(self.body.expr(*condition)?)?;
+                let then = self.block();
+                let otherwise = self.block();
+                let end = if else_stmt.is_some() {
+                    self.block()
+                } else {
+                    otherwise
+                };
+                self.terminate(Terminator::Branch {
+                    condition,
+                    then,
+                    otherwise,
+                });
+
+                self.current = then;
+                self.stmts(body)?;
+                if !self.terminated() {
+                    self.terminate(Terminator::Goto(end));
+                }
+                if let Some(else_stmt) = else_stmt {
+                    self.current = otherwise;
+                    self.scopes.push(HashMap::new());
+                    self.stmt(self.body.stmt(*else_stmt)?)?;
+                    self.scopes.pop();
+                    if !self.terminated() {
+                        self.terminate(Terminator::Goto(end));
+                    }
+                }
+                self.current = end;
+            }
+            ast::Stmt::Block { stmts } => self.stmts(stmts)?,
+            ast::Stmt::Expr { expr } => {
+                let expr = self.body.expr(*expr)?;
+                let value = self.rvalue(expr)?;
+                self.push(Stmt::Eval(value), expr.span.clone());
+            }
+            ast::Stmt::Return { value: None } => self.terminate(Terminator::Return(None)),
+            ast::Stmt::Return { value: Some(value) } => {
+                let value = self.body.expr(*value)?;
+                let span = value.span.clone();
+                let value = self.rvalue(value)?;
+                if self.rvalue_ty(&value) == Ty::Unit {
+                    self.push(Stmt::Eval(value), span);
+                    self.terminate(Terminator::Return(None));
+                } else {
+                    let value = self.spill(value, span);
+                    self.terminate(Terminator::Return(Some(value)));
+                }
+            }
+            ast::Stmt::Error(err) => return Err(err.clone()),
+        }
+        Ok(())
+    }
+
+    fn rvalue_ty(&self, rvalue: &Rvalue) -> Ty {
+        let operand_ty = |operand: &Operand| match operand {
+            Operand::Local(local) | Operand::Ref(local) => self.locals[*local].ty.clone(),
+            Operand::Const(constant) => constant.ty(),
+        };
+        match rvalue {
+            Rvalue::Use(operand) | Rvalue::Unary(UnOp::Neg, operand) => operand_ty(operand),
+            Rvalue::Unary(UnOp::Not, _) => Ty::Bool,
+            Rvalue::Binary(op, _, _) if op.is_comparison() => Ty::Bool,
+            Rvalue::Binary(_, lhs, _) => operand_ty(lhs),
+            Rvalue::Call { func, .. } => match self.types.functions.get(func) {
+                Some(sig) => sig.ret.clone(),
+                None => types::builtin_ret(func),
+            },
+            Rvalue::Struct { name, .. } => Ty::Struct(name.clone()),
+            Rvalue::Member(operand, index) => match operand_ty(operand) {
+                Ty::Struct(name) => self.types.members(&name).unwrap()[*index].1.clone(),
+                ty => unreachable!("a `{}` has no members", ty),
+            },
+        }
+    }
+
+    /// Stores `rvalue`, lowered from the source at `span`, in a new
+    /// temporary, unless it already is an operand.
+    fn spill(&mut self, rvalue: Rvalue, span: Span) -> Operand {
+        match rvalue {
+            Rvalue::Use(operand) => operand,
+            rvalue => {
+                let ty = self.rvalue_ty(&rvalue);
+                let temp = self.local(None, ty);
+                self.push(Stmt::Assign(temp, rvalue), span);
+                Operand::Local(temp)
+            }
+        }
+    }
+
+    fn operand(&mut self, expr: &Spanned<ast::Expr>) -> Result<Operand, Error> {
+        let rvalue = self.rvalue(expr)?;
+        Ok(self.spill(rvalue, expr.span.clone()))
+    }
+
+    /// Like `operand`, but copies locals into a temporary when `later` code,
+    /// evaluated before the operand is used, may assign to them.
+    fn operand_before(
+        &mut self,
+        expr: &Spanned<ast::Expr>,
+        later: &[ast::ExprId],
+    ) -> Result<Operand, Error> {
+        let operand = self.operand(expr)?;
+        match operand {
+            Operand::Local(local) if later.iter().any(|expr| has_call(self.body, *expr)) => {
+                let temp = self.local(None, self.locals[local].ty.clone());
+                self.push(Stmt::Assign(temp, Rvalue::Use(operand)), expr.span.clone());
+                Ok(Operand::Local(temp))
+            }
+            operand => Ok(operand),
+        }
+    }
+
+    fn rvalue(&mut self, expr: &Spanned<ast::Expr>) -> Result<Rvalue, Error> {
+        Ok(match &expr.node {
+            ast::Expr::Literal(lit) => Rvalue::Use(Operand::Const(match lit {
+                ast::Lit::Int(int) => Const::Int(*int as i64),
+                ast::Lit::Float(float) => Const::Float(*float),
+                ast::Lit::Bool(bool) => Const::Bool(*bool),
+                ast::Lit::Str(string) => {
+                    Const::Str(string.replace("\\\"", "\"").replace("\\\\", "\\"))
+                }
+            })),
+            ast::Expr::Ident(name) => Rvalue::Use(Operand::Local(self.lookup(name))),
+            ast::Expr::FnCall { fn_name, args } => {
+                let modes = self
+                    .signatures
+                    .get(fn_name.as_str())
+                    .cloned()
+                    .unwrap_or_default();
+                let mut operands = Vec::new();
+                for (index, arg) in args.iter().enumerate() {
+                    let arg = self.body.expr(*arg)?;
+                    if modes.get(index) != Some(&Mode::BorrowMut) {
+                        operands.push(self.operand_before(arg, &args[index + 1..])?);
+                        continue;
+                    }
+                    let local = match &arg.node {
+                        ast::Expr::Ident(name) => self.lookup(name),
+                        _ => {
+                            let value = self.rvalue(arg)?;
+                            let ty = self.rvalue_ty(&value);
+                            let temp = self.local(None, ty);
+                            self.push(Stmt::Assign(temp, value), arg.span.clone());
+                            temp
+                        }
+                    };
+                    operands.push(Operand::Ref(local));
+                }
+                if matches!(fn_name.as_str(), "assert" | "assert_eq") {
+                    for arg in args {
+                        let source = print::expr_source(self.body, *arg);
+                        operands.push(Operand::Const(Const::Str(source)));
+                    }
+                }
+                Rvalue::Call {
+                    func: fn_name.to_string(),
+                    args: operands,
+                }
+            }
+            ast::Expr::PrefixOp { op, expr } => {
+                let operand = self.operand(self.body.expr(*expr)?)?;
+                match op {
+                    TokenKind::Minus => Rvalue::Unary(UnOp::Neg, operand),
+                    TokenKind::Bang => Rvalue::Unary(UnOp::Not, operand),
+                    _ => Rvalue::Use(operand),
+                }
+            }
+            ast::Expr::PostfixOp { op, .. } => {
+                return Err(Error::new(
+                    format!("Postfix `{}` can not be lowered", op),
+                    codes::E003,
+                    expr.span.clone(),
+                ))
+            }
+            ast::Expr::Member { expr, member } => {
+                let operand = self.operand(self.body.expr(*expr)?)?;
+                let index = match self.rvalue_ty(&Rvalue::Use(operand.clone())) {
+                    Ty::Struct(name) => self
+                        .types
+                        .members(&name)
+                        .unwrap()
+                        .iter()
+                        .position(|(name, _)| name == member.as_str())
+                        .unwrap(),
+                    ty => unreachable!("a `{}` has no members", ty),
+                };
+                Rvalue::Member(operand, index)
+            }
+            // The members are evaluated in the order they are written, and
+            // then put in declaration order.
+            ast::Expr::StructLit {
+                struct_name,
+                fields,
+            } => {
+                let mut values = Vec::new();
+                for (index, (member, value)) in fields.iter().enumerate() {
+                    let later: Vec<_> = fields[index + 1..]
+                        .iter()
+                        .map(|(_, value)| *value)
+                        .collect();
+                    let operand = self.operand_before(self.body.expr(*value)?, &later)?;
+                    values.push((member.as_str(), operand));
+                }
+                let members = self
+                    .types
+                    .members(struct_name)
+                    .unwrap()
+                    .iter()
+                    .map(|(name, _)| {
+                        let position = values
+                            .iter()
+                            .position(|(member, _)| member == name)
+                            .unwrap();
+                        values[position].1.clone()
+                    })
+                    .collect();
+                Rvalue::Struct {
+                    name: struct_name.to_string(),
+                    members,
+                }
+            }
+            ast::Expr::InfixOp { op, lhs, rhs } if matches!(op, TokenKind::And | TokenKind::Or) => {
+                // The result holds the left operand, and is only overwritten
+                // with the right one when the left one does not decide it.
+                let lhs = self.body.expr(*lhs)?;
+                let span = lhs.span.clone();
+                let lhs = self.rvalue(lhs)?;
+                let result = self.local(None, Ty::Bool);
+                self.push(Stmt::Assign(result, lhs), span);
+                let rhs_block = self.block();
+                let end = self.block();
+                let (then, otherwise) = if *op == TokenKind::And {
+                    (rhs_block, end)
+                } else {
+                    (end, rhs_block)
+                };
+                self.terminate(Terminator::Branch {
+                    condition: Operand::Local(result),
+                    then,
+                    otherwise,
+                });
+                self.current = rhs_block;
+                let rhs = self.body.expr(*rhs)?;
+                let span = rhs.span.clone();
+                let rhs = self.rvalue(rhs)?;
+                self.push(Stmt::Assign(result, rhs), span);
+                self.terminate(Terminator::Goto(end));
+                self.current = end;
+                Rvalue::Use(Operand::Local(result))
+            }
+            ast::Expr::InfixOp { op, lhs, rhs } => {
+                let lhs = self.operand_before(self.body.expr(*lhs)?, &[*rhs])?;
+                let rhs = self.operand(self.body.expr(*rhs)?)?;
+                let op = match op {
+                    TokenKind::Plus => BinOp::Add,
+                    TokenKind::Minus => BinOp::Sub,
+                    TokenKind::Times => BinOp::Mul,
+                    TokenKind::Slash => BinOp::Div,
+                    TokenKind::Caret => BinOp::Pow,
+                    TokenKind::Eqq => BinOp::Eq,
+                    TokenKind::Neq => BinOp::Ne,
+                    TokenKind::LAngle => BinOp::Lt,
+                    TokenKind::RAngle => BinOp::Gt,
+                    TokenKind::Leq => BinOp::Le,
+                    _ => BinOp::Ge,
+                };
+                Rvalue::Binary(op, lhs, rhs)
+            }
+        })
+    }
+}
+
+fn has_call(body: &ast::Body, expr: ast::ExprId) -> bool {
+    let has = |expr: &ast::ExprId| has_call(body, *expr);
+    let expr = match &body[expr] {
+        Ok(expr) => &expr.node,
+        Err(_) => return false,
+    };
+    match expr {
+        ast::Expr::Literal(_) | ast::Expr::Ident(_) => false,
+        ast::Expr::FnCall { .. } => true,
+        ast::Expr::PrefixOp { expr, .. }
+        | ast::Expr::PostfixOp { expr, .. }
+        | ast::Expr::Member { expr, .. } => has(expr),
+        ast::Expr::InfixOp { lhs, rhs, .. } => has(lhs) || has(rhs),
+        ast::Expr::StructLit { fields, .. } => fields.iter().any(|(_, value)| has(value)),
+    }
+}
@@ -0,0 +1,322 @@
+//! The mid-level IR: a control-flow graph of basic blocks per function, with
+//! every intermediate value stored in an explicit, typed local.
+//!
+//! It is built one function at a time from type checked items by
+//! `lower_function`, optimized by `optimize` and checked by `verify`; the
+//! `mir` query of `db` does all three for a file.
+//! Backends that don't need the structure of the source work from here. The
+//! Rust backend still emits from the AST, since it reproduces that
+//! structure.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use logos::Span;
+
+use crate::ast::Spanned;
+use crate::types::{Derive, Ty};
+
+pub mod lower;
+pub mod opt;
+pub mod verify;
+
+pub use lower::lower_function;
+pub use opt::optimize;
+pub use verify::verify;
+
+pub type LocalId = usize;
+pub type BlockId = usize;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    /// Structs and the types of their members, in declaration order.
+    pub structs: Vec<(String, Vec<(String, Ty)>)>,
+    /// What each struct derives, which decides what its values can do.
+    pub derives: HashMap<String, Vec<Derive>>,
+    pub functions: Vec<Function>,
+}
+
+impl Program {
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|func| func.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    /// The first `params` locals are the parameters.
+    pub params: usize,
+    pub ret: Ty,
+    pub locals: Vec<Local>,
+    /// The first block is the entry block.
+    pub blocks: Vec<Block>,
+    /// The source of the function, which is where running off its end
+    /// without returning a value is reported.
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Local {
+    /// The source name, `None` for temporaries.
+    pub name: Option<String>,
+    pub ty: Ty,
+    /// Parameters that refer to the caller's storage, so assignments to them
+    /// are seen by the caller.
+    pub by_ref: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    /// The statements, each with the span of the source it was lowered
+    /// from, which is where errors running it are reported.
+    pub stmts: Vec<Spanned<Stmt>>,
+    pub terminator: Terminator,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Assign(LocalId, Rvalue),
+    /// Evaluates an rvalue for its effects, e.g. calls that return nothing.
+    Eval(Rvalue),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rvalue {
+    Use(Operand),
+    Unary(UnOp, Operand),
+    Binary(BinOp, Operand, Operand),
+    /// A call to a function of the program or a builtin. Calls to `assert`
+    /// and `assert_eq` pass the source of each operand after the operands,
+    /// for the message they fail with.
+    Call {
+        func: String,
+        args: Vec<Operand>,
+    },
+    /// A value of the struct `name`, with its members in declaration order.
+    Struct {
+        name: String,
+        members: Vec<Operand>,
+    },
+    /// Member `index` of a struct, in declaration order.
+    Member(Operand, usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Local(LocalId),
+    /// The storage of a local, passed to a parameter that is `by_ref`.
+    Ref(LocalId),
+    Const(Const),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Const {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+}
+
+impl Const {
+    pub fn ty(&self) -> Ty {
+        match self {
+            Const::Int(_) => Ty::Int,
+            Const::Float(_) => Ty::Float,
+            Const::Bool(_) => Ty::Bool,
+            Const::Str(_) => Ty::Str,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+
+impl BinOp {
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Goto(BlockId),
+    Branch {
+        condition: Operand,
+        then: BlockId,
+        otherwise: BlockId,
+    },
+    Return(Option<Operand>),
+    Unreachable,
+}
+
+impl Function {
+    /// The function with every span in it moved by `delta` bytes, like
+    /// `Error::moved`.
+    pub fn moved(mut self, delta: isize) -> Self {
+        let moved = |span: &mut Span| {
+            *span = (span.start as isize + delta) as usize..(span.end as isize + delta) as usize;
+        };
+        moved(&mut self.span);
+        for block in &mut self.blocks {
+            block
+                .stmts
+                .iter_mut()
+                .for_each(|stmt| moved(&mut stmt.span));
+        }
+        self
+    }
+
+    pub fn operand_ty(&self, operand: &Operand) -> Ty {
+        match operand {
+            Operand::Local(local) | Operand::Ref(local) => self.locals[*local].ty.clone(),
+            Operand::Const(constant) => constant.ty(),
+        }
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, members) in &self.structs {
+            match self.derives.get(name) {
+                Some(derives) if !derives.is_empty() => {
+                    let derives: Vec<_> = derives.iter().map(Derive::to_string).collect();
+                    writeln!(f, "#[derive({})]", derives.join(", "))?;
+                }
+                _ => {}
+            }
+            writeln!(f, "struct {} {{", name)?;
+            for (member, ty) in members {
+                writeln!(f, "    {}: {},", member, ty)?;
+            }
+            writeln!(f, "}}")?;
+            writeln!(f)?;
+        }
+        for (index, func) in self.functions.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", func)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params: Vec<String> = self.locals[..self.params]
+            .iter()
+            .enumerate()
+            .map(|(index, local)| {
+                let by_ref = if local.by_ref { "&mut " } else { "" };
+                format!("_{}: {}{}", index, by_ref, local.ty)
+            })
+            .collect();
+        writeln!(
+            f,
+            "fn {}({}) -> {} {{",
+            self.name,
+            params.join(", "),
+            self.ret
+        )?;
+        for (index, local) in self.locals.iter().enumerate() {
+            let name = match &local.name {
+                Some(name) => format!(" // {}", name),
+                None => String::new(),
+            };
+            if index < self.params {
+                if !name.is_empty() {
+                    writeln!(f, "    _{}{}", index, name)?;
+                }
+            } else {
+                writeln!(f, "    let _{}: {};{}", index, local.ty, name)?;
+            }
+        }
+        for (index, block) in self.blocks.iter().enumerate() {
+            writeln!(f)?;
+            writeln!(f, "    bb{}: {{", index)?;
+            for stmt in &block.stmts {
+                match &stmt.node {
+                    Stmt::Assign(local, rvalue) => writeln!(f, "        _{} = {};", local, rvalue)?,
+                    Stmt::Eval(rvalue) => writeln!(f, "        {};", rvalue)?,
+                }
+            }
+            writeln!(f, "        {};", block.terminator)?;
+            writeln!(f, "    }}")?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+impl fmt::Display for Rvalue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rvalue::Use(operand) => write!(f, "{}", operand),
+            Rvalue::Unary(op, operand) => write!(f, "{:?}({})", op, operand),
+            Rvalue::Binary(op, lhs, rhs) => write!(f, "{:?}({}, {})", op, lhs, rhs),
+            Rvalue::Call { func, args } => {
+                let args: Vec<String> = args.iter().map(Operand::to_string).collect();
+                write!(f, "{}({})", func, args.join(", "))
+            }
+            Rvalue::Struct { name, members } => {
+                let members: Vec<String> = members
+                    .iter()
+                    .enumerate()
+                    .map(|(index, member)| format!("{}: {}", index, member))
+                    .collect();
+                write!(f, "{} {{ {} }}", name, members.join(", "))
+            }
+            Rvalue::Member(operand, index) => write!(f, "{}.{}", operand, index),
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Local(local) => write!(f, "_{}", local),
+            Operand::Ref(local) => write!(f, "&mut _{}", local),
+            Operand::Const(Const::Int(int)) => write!(f, "{}", int),
+            Operand::Const(Const::Float(float)) => write!(f, "{:?}", float),
+            Operand::Const(Const::Bool(bool)) => write!(f, "{}", bool),
+            Operand::Const(Const::Str(string)) => write!(f, "{:?}", string),
+        }
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Terminator::Goto(block) => write!(f, "goto bb{}", block),
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => write!(f, "branch {}, bb{}, bb{}", condition, then, otherwise),
+            Terminator::Return(Some(value)) => write!(f, "return {}", value),
+            Terminator::Return(None) => write!(f, "return"),
+            Terminator::Unreachable => write!(f, "unreachable"),
+        }
+    }
+}
@@ -0,0 +1,646 @@
+//! Optimizations on MIR.
+//!
+//! `-O1` folds and propagates constants and copies, turns branches on
+//! constants into jumps, merges straight-line blocks and removes unreachable
+//! blocks and dead stores. `-O2` also eliminates common subexpressions
+//! within blocks and inlines small functions into their callers, callees
+//! first, so that what was inlined into a callee is inlined along with it.
+//! Every pass keeps the program valid for `verify`.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{ast::Spanned, types::Ty};
+
+use super::{
+    BinOp, Block, BlockId, Const, Function, Local, LocalId, Operand, Program, Rvalue, Stmt,
+    Terminator, UnOp,
+};
+
+/// Callees with at most this many statements are inlined at `-O2`.
+const INLINE_LIMIT: usize = 8;
+
+pub fn optimize(program: &mut Program, level: u8) {
+    if level == 0 {
+        return;
+    }
+    if level >= 2 {
+        inline(program, level);
+    }
+    for func in &mut program.functions {
+        simplify(func, level);
+    }
+}
+
+/// Runs the passes on `func` until none of them changes it anymore.
+fn simplify(func: &mut Function, level: u8) {
+    loop {
+        let before = func.clone();
+        propagate(func);
+        propagate_copies(func);
+        fold(func);
+        branches(func);
+        if level >= 2 {
+            eliminate_common(func);
+        }
+        merge_blocks(func);
+        remove_unreachable(func);
+        remove_dead_stores(func);
+        if *func == before {
+            break;
+        }
+    }
+}
+
+fn operands_mut(rvalue: &mut Rvalue) -> Vec<&mut Operand> {
+    match rvalue {
+        Rvalue::Use(operand) | Rvalue::Unary(_, operand) => vec![operand],
+        Rvalue::Binary(_, lhs, rhs) => vec![lhs, rhs],
+        Rvalue::Call { args, .. } => args.iter_mut().collect(),
+        Rvalue::Struct { members, .. } => members.iter_mut().collect(),
+        Rvalue::Member(operand, _) => vec![operand],
+    }
+}
+
+fn operands(rvalue: &Rvalue) -> Vec<&Operand> {
+    match rvalue {
+        Rvalue::Use(operand) | Rvalue::Unary(_, operand) => vec![operand],
+        Rvalue::Binary(_, lhs, rhs) => vec![lhs, rhs],
+        Rvalue::Call { args, .. } => args.iter().collect(),
+        Rvalue::Struct { members, .. } => members.iter().collect(),
+        Rvalue::Member(operand, _) => vec![operand],
+    }
+}
+
+fn terminator_operand(terminator: &mut Terminator) -> Option<&mut Operand> {
+    match terminator {
+        Terminator::Branch { condition, .. } => Some(condition),
+        Terminator::Return(value) => value.as_mut(),
+        Terminator::Goto(_) | Terminator::Unreachable => None,
+    }
+}
+
+fn successors(terminator: &Terminator) -> Vec<BlockId> {
+    match terminator {
+        Terminator::Goto(target) => vec![*target],
+        Terminator::Branch {
+            then, otherwise, ..
+        } => vec![*then, *otherwise],
+        Terminator::Return(_) | Terminator::Unreachable => Vec::new(),
+    }
+}
+
+/// Locals whose storage is passed to a call, which may assign to them.
+fn referenced(func: &Function) -> HashSet<LocalId> {
+    let mut referenced = HashSet::new();
+    for block in &func.blocks {
+        for Spanned { node: stmt, .. } in &block.stmts {
+            let (Stmt::Assign(_, rvalue) | Stmt::Eval(rvalue)) = stmt;
+            for operand in operands(rvalue) {
+                if let Operand::Ref(local) = operand {
+                    referenced.insert(*local);
+                }
+            }
+        }
+    }
+    referenced
+}
+
+/// Replaces reads of locals known to hold a constant by the constant.
+///
+/// A local assigned a constant once, and never passed by reference, holds
+/// it wherever it is read, since lowering only reads names in scope. Other
+/// locals are known from an assignment of a constant to their next
+/// assignment within the same block.
+fn propagate(func: &mut Function) {
+    let referenced = referenced(func);
+    let mut assignments: HashMap<LocalId, Vec<Option<Const>>> = HashMap::new();
+    for block in &func.blocks {
+        for Spanned { node: stmt, .. } in &block.stmts {
+            if let Stmt::Assign(local, rvalue) = stmt {
+                let constant = match rvalue {
+                    Rvalue::Use(Operand::Const(constant)) => Some(constant.clone()),
+                    _ => None,
+                };
+                assignments.entry(*local).or_default().push(constant);
+            }
+        }
+    }
+    let global: HashMap<LocalId, Const> = assignments
+        .into_iter()
+        .filter(|(local, _)| *local >= func.params && !referenced.contains(local))
+        .filter_map(|(local, constants)| match constants.as_slice() {
+            [Some(constant)] => Some((local, constant.clone())),
+            _ => None,
+        })
+        .collect();
+
+    for block in &mut func.blocks {
+        let mut known = global.clone();
+        let substitute = |operand: &mut Operand, known: &HashMap<LocalId, Const>| {
+            if let Operand::Local(local) = operand {
+                if let Some(constant) = known.get(local) {
+                    *operand = Operand::Const(constant.clone());
+                }
+            }
+        };
+        for Spanned { node: stmt, .. } in &mut block.stmts {
+            let (Stmt::Assign(_, rvalue) | Stmt::Eval(rvalue)) = stmt;
+            for operand in operands_mut(rvalue) {
+                substitute(operand, &known);
+                if let Operand::Ref(local) = operand {
+                    known.remove(local);
+                }
+            }
+            if let Stmt::Assign(local, rvalue) = stmt {
+                match rvalue {
+                    Rvalue::Use(Operand::Const(constant)) => {
+                        known.insert(*local, constant.clone());
+                    }
+                    _ => {
+                        known.remove(local);
+                    }
+                }
+            }
+        }
+        if let Some(operand) = terminator_operand(&mut block.terminator) {
+            substitute(operand, &known);
+        }
+    }
+}
+
+/// Replaces reads of a local holding a copy of another one, `_3` after
+/// `_3 = _2`, by reads of the other one, within a block and as long as
+/// neither of them is assigned or passed by reference in between.
+fn propagate_copies(func: &mut Function) {
+    for block in &mut func.blocks {
+        let mut copies: HashMap<LocalId, LocalId> = HashMap::new();
+        let substitute = |operand: &mut Operand, copies: &HashMap<LocalId, LocalId>| {
+            if let Operand::Local(local) = operand {
+                if let Some(source) = copies.get(local) {
+                    *local = *source;
+                }
+            }
+        };
+        for Spanned { node: stmt, .. } in &mut block.stmts {
+            let (Stmt::Assign(_, rvalue) | Stmt::Eval(rvalue)) = stmt;
+            let mut changed = Vec::new();
+            for operand in operands_mut(rvalue) {
+                substitute(operand, &copies);
+                if let Operand::Ref(local) = operand {
+                    changed.push(*local);
+                }
+            }
+            if let Stmt::Assign(local, _) = stmt {
+                changed.push(*local);
+            }
+            copies.retain(|copy, source| !changed.contains(copy) && !changed.contains(source));
+            if let Stmt::Assign(local, Rvalue::Use(Operand::Local(source))) = stmt {
+                if local != source {
+                    copies.insert(*local, *source);
+                }
+            }
+        }
+        if let Some(operand) = terminator_operand(&mut block.terminator) {
+            substitute(operand, &copies);
+        }
+    }
+}
+
+/// Evaluates operations whose operands are all constants.
+fn fold(func: &mut Function) {
+    for block in &mut func.blocks {
+        for Spanned { node: stmt, .. } in &mut block.stmts {
+            let (Stmt::Assign(_, rvalue) | Stmt::Eval(rvalue)) = stmt;
+            let constant = match rvalue {
+                Rvalue::Unary(op, Operand::Const(value)) => fold_unary(*op, value),
+                Rvalue::Binary(op, Operand::Const(lhs), Operand::Const(rhs)) => {
+                    fold_binary(*op, lhs, rhs)
+                }
+                _ => None,
+            };
+            if let Some(constant) = constant {
+                *rvalue = Rvalue::Use(Operand::Const(constant));
+            }
+        }
+    }
+}
+
+fn fold_unary(op: UnOp, value: &Const) -> Option<Const> {
+    match (op, value) {
+        (UnOp::Neg, Const::Int(int)) => int32(*int)?.checked_neg().map(i64::from).map(Const::Int),
+        (UnOp::Neg, Const::Float(float)) => Some(Const::Float(-float)),
+        (UnOp::Not, Const::Bool(bool)) => Some(Const::Bool(!bool)),
+        _ => None,
+    }
+}
+
+/// `int`s are 32 bits wide in every backend, so folding only happens when the
+/// result fits.
+fn int32(int: i64) -> Option<i32> {
+    i32::try_from(int).ok()
+}
+
+fn fold_binary(op: BinOp, lhs: &Const, rhs: &Const) -> Option<Const> {
+    if op.is_comparison() {
+        let ordering = match (lhs, rhs) {
+            (Const::Int(lhs), Const::Int(rhs)) => lhs.partial_cmp(rhs),
+            (Const::Float(lhs), Const::Float(rhs)) => lhs.partial_cmp(rhs),
+            (Const::Bool(lhs), Const::Bool(rhs)) => lhs.partial_cmp(rhs),
+            (Const::Str(lhs), Const::Str(rhs)) => lhs.partial_cmp(rhs),
+            _ => return None,
+        };
+        // Comparisons involving NaN are false, except for `!=`.
+        let result = match ordering {
+            Some(ordering) => match op {
+                BinOp::Eq => ordering.is_eq(),
+                BinOp::Ne => ordering.is_ne(),
+                BinOp::Lt => ordering.is_lt(),
+                BinOp::Gt => ordering.is_gt(),
+                BinOp::Le => ordering.is_le(),
+                _ => ordering.is_ge(),
+            },
+            None => op == BinOp::Ne,
+        };
+        return Some(Const::Bool(result));
+    }
+
+    match (lhs, rhs) {
+        (Const::Int(lhs), Const::Int(rhs)) => {
+            let (lhs, rhs) = (int32(*lhs)?, int32(*rhs)?);
+            let result = match op {
+                BinOp::Add => lhs.checked_add(rhs),
+                BinOp::Sub => lhs.checked_sub(rhs),
+                BinOp::Mul => lhs.checked_mul(rhs),
+                BinOp::Div => lhs.checked_div(rhs),
+                _ => lhs.checked_pow(u32::try_from(rhs).ok()?),
+            };
+            result.map(|int| Const::Int(int.into()))
+        }
+        (Const::Float(lhs), Const::Float(rhs)) => Some(Const::Float(match op {
+            BinOp::Add => lhs + rhs,
+            BinOp::Sub => lhs - rhs,
+            BinOp::Mul => lhs * rhs,
+            BinOp::Div => lhs / rhs,
+            _ => lhs.powf(*rhs),
+        })),
+        (Const::Str(lhs), Const::Str(rhs)) if op == BinOp::Add => {
+            Some(Const::Str(format!("{}{}", lhs, rhs)))
+        }
+        _ => None,
+    }
+}
+
+/// Turns branches on a constant condition into jumps.
+fn branches(func: &mut Function) {
+    for block in &mut func.blocks {
+        if let Terminator::Branch {
+            condition: Operand::Const(Const::Bool(condition)),
+            then,
+            otherwise,
+        } = block.terminator
+        {
+            block.terminator = Terminator::Goto(if condition { then } else { otherwise });
+        }
+    }
+}
+
+/// Reuses the result of a pure operation computed earlier in the same block,
+/// as long as neither its operands nor the local holding it changed since.
+fn eliminate_common(func: &mut Function) {
+    for block in &mut func.blocks {
+        let mut available: Vec<(Rvalue, LocalId)> = Vec::new();
+        for Spanned { node: stmt, .. } in &mut block.stmts {
+            let mut changed = Vec::new();
+            if let Stmt::Assign(local, rvalue) = stmt {
+                if matches!(rvalue, Rvalue::Unary(..) | Rvalue::Binary(..)) {
+                    if let Some((_, holder)) = available.iter().find(|(other, _)| other == rvalue) {
+                        *rvalue = Rvalue::Use(Operand::Local(*holder));
+                    }
+                }
+                changed.push(*local);
+            }
+            let (Stmt::Assign(_, rvalue) | Stmt::Eval(rvalue)) = stmt;
+            for operand in operands(rvalue) {
+                if let Operand::Ref(local) = operand {
+                    changed.push(*local);
+                }
+            }
+            available.retain(|(rvalue, holder)| {
+                !changed.contains(holder)
+                    && !operands(rvalue).iter().any(|operand| {
+                        matches!(operand, Operand::Local(local) if changed.contains(local))
+                    })
+            });
+            if let Stmt::Assign(local, rvalue) = stmt {
+                let reads_itself = operands(rvalue)
+                    .iter()
+                    .any(|operand| **operand == Operand::Local(*local));
+                if matches!(rvalue, Rvalue::Unary(..) | Rvalue::Binary(..)) && !reads_itself {
+                    available.push((rvalue.clone(), *local));
+                }
+            }
+        }
+    }
+}
+
+fn predecessors(func: &Function) -> Vec<usize> {
+    let mut predecessors = vec![0; func.blocks.len()];
+    for block in &func.blocks {
+        for target in successors(&block.terminator) {
+            predecessors[target] += 1;
+        }
+    }
+    predecessors
+}
+
+/// Appends blocks that are only jumped to from one other block to it.
+fn merge_blocks(func: &mut Function) {
+    let mut predecessors = predecessors(func);
+    for index in 0..func.blocks.len() {
+        while let Terminator::Goto(target) = func.blocks[index].terminator {
+            if target == index || target == 0 || predecessors[target] != 1 {
+                break;
+            }
+            let merged = std::mem::replace(
+                &mut func.blocks[target],
+                Block {
+                    stmts: Vec::new(),
+                    terminator: Terminator::Unreachable,
+                },
+            );
+            predecessors[target] = 0;
+            func.blocks[index].stmts.extend(merged.stmts);
+            func.blocks[index].terminator = merged.terminator;
+        }
+    }
+}
+
+/// Removes blocks that can not be reached from the entry block.
+fn remove_unreachable(func: &mut Function) {
+    let mut reachable = vec![false; func.blocks.len()];
+    let mut stack = vec![0];
+    while let Some(block) = stack.pop() {
+        if !reachable[block] {
+            reachable[block] = true;
+            stack.extend(successors(&func.blocks[block].terminator));
+        }
+    }
+
+    let mut renumbered = Vec::new();
+    let mut next = 0;
+    for reachable in &reachable {
+        renumbered.push(next);
+        if *reachable {
+            next += 1;
+        }
+    }
+    let blocks = std::mem::take(&mut func.blocks);
+    for (index, mut block) in blocks.into_iter().enumerate() {
+        if !reachable[index] {
+            continue;
+        }
+        match &mut block.terminator {
+            Terminator::Goto(target) => *target = renumbered[*target],
+            Terminator::Branch {
+                then, otherwise, ..
+            } => {
+                *then = renumbered[*then];
+                *otherwise = renumbered[*otherwise];
+            }
+            Terminator::Return(_) | Terminator::Unreachable => {}
+        }
+        func.blocks.push(block);
+    }
+}
+
+/// Removes assignments to locals that are never read, keeping the calls
+/// among them, and then the locals that are no longer used at all.
+fn remove_dead_stores(func: &mut Function) {
+    loop {
+        let mut read = HashSet::new();
+        for block in &mut func.blocks {
+            for Spanned { node: stmt, .. } in &block.stmts {
+                let (Stmt::Assign(_, rvalue) | Stmt::Eval(rvalue)) = stmt;
+                for operand in operands(rvalue) {
+                    if let Operand::Local(local) | Operand::Ref(local) = operand {
+                        read.insert(*local);
+                    }
+                }
+            }
+            if let Some(Operand::Local(local)) = terminator_operand(&mut block.terminator) {
+                read.insert(*local);
+            }
+        }
+
+        let mut changed = false;
+        let by_ref: Vec<bool> = func.locals.iter().map(|local| local.by_ref).collect();
+        for block in &mut func.blocks {
+            block.stmts.retain_mut(|stmt| match &mut stmt.node {
+                Stmt::Assign(local, rvalue) if !read.contains(local) && !by_ref[*local] => {
+                    changed = true;
+                    match rvalue {
+                        Rvalue::Call { .. } => {
+                            stmt.node = Stmt::Eval(rvalue.clone());
+                            true
+                        }
+                        _ => false,
+                    }
+                }
+                Stmt::Eval(rvalue) if !matches!(rvalue, Rvalue::Call { .. }) => {
+                    changed = true;
+                    false
+                }
+                _ => true,
+            });
+        }
+        if !changed {
+            break;
+        }
+    }
+    remove_unused_locals(func);
+}
+
+fn remove_unused_locals(func: &mut Function) {
+    let mut used = vec![false; func.locals.len()];
+    used[..func.params].fill(true);
+    for block in &mut func.blocks {
+        for Spanned { node: stmt, .. } in &mut block.stmts {
+            if let Stmt::Assign(local, _) = stmt {
+                used[*local] = true;
+            }
+            let (Stmt::Assign(_, rvalue) | Stmt::Eval(rvalue)) = stmt;
+            for operand in operands(rvalue) {
+                if let Operand::Local(local) | Operand::Ref(local) = operand {
+                    used[*local] = true;
+                }
+            }
+        }
+        if let Some(Operand::Local(local)) = terminator_operand(&mut block.terminator) {
+            used[*local] = true;
+        }
+    }
+
+    let mut renumbered = Vec::new();
+    let mut next = 0;
+    for used in &used {
+        renumbered.push(next);
+        if *used {
+            next += 1;
+        }
+    }
+    let renumber = |operand: &mut Operand| {
+        if let Operand::Local(local) | Operand::Ref(local) = operand {
+            *local = renumbered[*local];
+        }
+    };
+    for block in &mut func.blocks {
+        for Spanned { node: stmt, .. } in &mut block.stmts {
+            if let Stmt::Assign(local, _) = stmt {
+                *local = renumbered[*local];
+            }
+            let (Stmt::Assign(_, rvalue) | Stmt::Eval(rvalue)) = stmt;
+            operands_mut(rvalue).into_iter().for_each(renumber);
+        }
+        if let Some(operand) = terminator_operand(&mut block.terminator) {
+            renumber(operand);
+        }
+    }
+    let mut index = 0;
+    func.locals.retain(|_| {
+        index += 1;
+        used[index - 1]
+    });
+}
+
+/// Whether calls to `func` are replaced by its body: it must be a single
+/// block that returns, and have at most `INLINE_LIMIT` statements.
+fn inlinable(func: &Function) -> bool {
+    matches!(
+        func.blocks.as_slice(),
+        [Block {
+            stmts,
+            terminator: Terminator::Return(_),
+        }] if stmts.len() <= INLINE_LIMIT
+    )
+}
+
+/// The indices of the functions of `program`, each after the functions it
+/// calls, except for calls that go back to a function of a cycle.
+fn bottom_up(program: &Program) -> Vec<usize> {
+    fn visit(program: &Program, index: usize, seen: &mut [bool], order: &mut Vec<usize>) {
+        if seen[index] {
+            return;
+        }
+        seen[index] = true;
+        for block in &program.functions[index].blocks {
+            for Spanned { node: stmt, .. } in &block.stmts {
+                let (Stmt::Assign(_, rvalue) | Stmt::Eval(rvalue)) = stmt;
+                if let Rvalue::Call { func, .. } = rvalue {
+                    if let Some(callee) = program.functions.iter().position(|f| f.name == *func) {
+                        visit(program, callee, seen, order);
+                    }
+                }
+            }
+        }
+        order.push(index);
+    }
+
+    let mut seen = vec![false; program.functions.len()];
+    let mut order = Vec::new();
+    for index in 0..program.functions.len() {
+        visit(program, index, &mut seen, &mut order);
+    }
+    order
+}
+
+/// Replaces calls to small functions by their bodies, going through the
+/// functions bottom up: each is simplified after what it calls was inlined,
+/// and then inlined into its callers if it is small enough.
+fn inline(program: &mut Program, level: u8) {
+    let mut callees: HashMap<String, Function> = HashMap::new();
+    for index in bottom_up(program) {
+        let func = &mut program.functions[index];
+        inline_calls(func, &callees);
+        simplify(func, level);
+        if inlinable(func) {
+            callees.insert(func.name.clone(), func.clone());
+        }
+    }
+}
+
+/// Replaces the calls in `func` to any of `callees` by their bodies.
+/// Parameters that are `by_ref` become the caller's local passed to them,
+/// the other locals of the callee get fresh locals in the caller.
+fn inline_calls(func: &mut Function, callees: &HashMap<String, Function>) {
+    for index in 0..func.blocks.len() {
+        let stmts = std::mem::take(&mut func.blocks[index].stmts);
+        let mut inlined = Vec::new();
+        for stmt in stmts {
+            let span = stmt.span.clone();
+            let (result, callee, args) = match &stmt.node {
+                Stmt::Assign(result, Rvalue::Call { func: callee, args }) => {
+                    (Some(*result), callee, args)
+                }
+                Stmt::Eval(Rvalue::Call { func: callee, args }) => (None, callee, args),
+                _ => {
+                    inlined.push(stmt);
+                    continue;
+                }
+            };
+            let callee = match callees.get(callee) {
+                Some(callee) if callee.name != func.name => callee,
+                _ => {
+                    inlined.push(stmt);
+                    continue;
+                }
+            };
+
+            let mut locals = Vec::new();
+            for (param, arg) in callee.locals.iter().zip(args) {
+                match arg {
+                    Operand::Ref(local) if param.by_ref => locals.push(*local),
+                    arg => {
+                        let local = fresh(&mut func.locals, &param.ty);
+                        let stmt = Stmt::Assign(local, Rvalue::Use(arg.clone()));
+                        inlined.push(Spanned::new(stmt, span.clone()));
+                        locals.push(local);
+                    }
+                }
+            }
+            for local in &callee.locals[callee.params..] {
+                locals.push(fresh(&mut func.locals, &local.ty));
+            }
+            let remap = |operand: &mut Operand| {
+                if let Operand::Local(local) | Operand::Ref(local) = operand {
+                    *local = locals[*local];
+                }
+            };
+
+            let block = &callee.blocks[0];
+            for stmt in &block.stmts {
+                let mut stmt = stmt.clone();
+                if let Stmt::Assign(local, _) = &mut stmt.node {
+                    *local = locals[*local];
+                }
+                let (Stmt::Assign(_, rvalue) | Stmt::Eval(rvalue)) = &mut stmt.node;
+                operands_mut(rvalue).into_iter().for_each(remap);
+                inlined.push(stmt);
+            }
+            if let (Some(result), Terminator::Return(Some(value))) = (result, &block.terminator) {
+                let mut value = value.clone();
+                remap(&mut value);
+                inlined.push(Spanned::new(Stmt::Assign(result, Rvalue::Use(value)), span));
+            }
+        }
+        func.blocks[index].stmts = inlined;
+    }
+}
+
+fn fresh(locals: &mut Vec<Local>, ty: &Ty) -> LocalId {
+    locals.push(Local {
+        name: None,
+        ty: ty.clone(),
+        by_ref: false,
+    });
+    locals.len() - 1
+}
@@ -0,0 +1,297 @@
+use crate::{
+    error::{codes, Error},
+    ownership,
+    types::{self, Derive, Ty},
+};
+
+use super::{BinOp, Const, Function, Operand, Program, Rvalue, Stmt, Terminator, UnOp};
+
+/// Checks the invariants backends rely on: every block and local referred to
+/// exists, every assignment and return is well typed, conditions are `bool`s,
+/// integer constants fit in an `int`, calls match the signature of their
+/// callee, passing storage exactly to the parameters that are `by_ref`, and
+/// structs are built from and taken apart into members of their types.
+pub fn verify(program: &Program) -> Result<(), Error> {
+    for func in &program.functions {
+        Verifier { program, func }.function()?;
+    }
+    Ok(())
+}
+
+struct Verifier<'a> {
+    program: &'a Program,
+    func: &'a Function,
+}
+
+impl<'a> Verifier<'a> {
+    fn error(&self, msg: String) -> Error {
+        Error::new(
+            format!("Invalid MIR in `{}`: {}", self.func.name, msg),
+            codes::E005,
+            0..0,
+        )
+    }
+
+    fn supports(&self, ty: &Ty, derive: Derive) -> bool {
+        types::supports(&self.program.derives, ty, derive)
+    }
+
+    fn function(&self) -> Result<(), Error> {
+        if self.func.params > self.func.locals.len() {
+            return Err(self.error(format!(
+                "{} parameters, but only {} locals",
+                self.func.params,
+                self.func.locals.len()
+            )));
+        }
+        if let Some(index) = self.func.locals[self.func.params..]
+            .iter()
+            .position(|local| local.by_ref)
+        {
+            return Err(self.error(format!(
+                "_{} is `by_ref` but not a parameter",
+                self.func.params + index
+            )));
+        }
+        if self.func.blocks.is_empty() {
+            return Err(self.error("there is no entry block".to_string()));
+        }
+
+        for (index, block) in self.func.blocks.iter().enumerate() {
+            for stmt in &block.stmts {
+                match &stmt.node {
+                    Stmt::Assign(local, rvalue) => {
+                        self.local(*local)?;
+                        let ty = self.rvalue(rvalue)?;
+                        let expected = &self.func.locals[*local].ty;
+                        if &ty != expected {
+                            return Err(self.error(format!(
+                                "bb{} assigns a `{}` to _{}, which is a `{}`",
+                                index, ty, local, expected
+                            )));
+                        }
+                    }
+                    Stmt::Eval(rvalue) => {
+                        self.rvalue(rvalue)?;
+                    }
+                }
+            }
+            self.terminator(index, &block.terminator)?;
+        }
+        Ok(())
+    }
+
+    fn local(&self, local: usize) -> Result<(), Error> {
+        if local >= self.func.locals.len() {
+            return Err(self.error(format!("_{} is not a local", local)));
+        }
+        Ok(())
+    }
+
+    fn target(&self, from: usize, to: usize) -> Result<(), Error> {
+        if to >= self.func.blocks.len() {
+            return Err(self.error(format!(
+                "bb{} jumps to bb{}, which does not exist",
+                from, to
+            )));
+        }
+        Ok(())
+    }
+
+    fn operand(&self, operand: &Operand) -> Result<Ty, Error> {
+        match operand {
+            Operand::Local(local) => {
+                self.local(*local)?;
+                Ok(self.func.locals[*local].ty.clone())
+            }
+            Operand::Ref(local) => Err(self.error(format!(
+                "&mut _{} is used outside of a call to a `by_ref` parameter",
+                local
+            ))),
+            Operand::Const(Const::Int(int)) if i32::try_from(*int).is_err() => {
+                Err(self.error(format!("{} does not fit in an `int`", int)))
+            }
+            Operand::Const(constant) => Ok(constant.ty()),
+        }
+    }
+
+    fn rvalue(&self, rvalue: &Rvalue) -> Result<Ty, Error> {
+        Ok(match rvalue {
+            Rvalue::Use(operand) => self.operand(operand)?,
+            Rvalue::Unary(op, operand) => {
+                let ty = self.operand(operand)?;
+                match op {
+                    UnOp::Neg if ty.is_numeric() => ty,
+                    UnOp::Not if ty == Ty::Bool => ty,
+                    op => return Err(self.error(format!("{:?} is applied to a `{}`", op, ty))),
+                }
+            }
+            Rvalue::Binary(op, lhs, rhs) => {
+                let lhs = self.operand(lhs)?;
+                let rhs = self.operand(rhs)?;
+                let valid = lhs == rhs
+                    && match op {
+                        BinOp::Add => lhs.is_numeric() || lhs == Ty::Str,
+                        BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Pow => lhs.is_numeric(),
+                        BinOp::Eq | BinOp::Ne => self.supports(&lhs, Derive::Eq),
+                        BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => {
+                            lhs.is_numeric() || lhs == Ty::Char
+                        }
+                    };
+                if !valid {
+                    return Err(self.error(format!(
+                        "{:?} is applied to a `{}` and a `{}`",
+                        op, lhs, rhs
+                    )));
+                }
+                if op.is_comparison() {
+                    Ty::Bool
+                } else {
+                    lhs
+                }
+            }
+            Rvalue::Call { func, args } if ownership::is_builtin(func) => {
+                let mut types = Vec::new();
+                for arg in args {
+                    let ty = self.operand(arg)?;
+                    if ty == Ty::Unit {
+                        return Err(self.error(format!("a `()` is passed to `{}`", func)));
+                    }
+                    types.push(ty);
+                }
+                // Assertions are followed by the source of their operands.
+                let valid = match (func.as_str(), &types[..]) {
+                    ("assert", [Ty::Bool, Ty::Str]) => true,
+                    ("assert_eq", [lhs, rhs, Ty::Str, Ty::Str]) => {
+                        lhs == rhs && self.supports(lhs, Derive::Eq)
+                    }
+                    ("print", types) => types.iter().all(|ty| self.supports(ty, Derive::Print)),
+                    ("hash", [ty]) => self.supports(ty, Derive::Hash),
+                    _ => false,
+                };
+                if !valid {
+                    return Err(self.error(format!(
+                        "`{}` is given {} arguments that it does not take",
+                        func,
+                        args.len()
+                    )));
+                }
+                types::builtin_ret(func)
+            }
+            Rvalue::Call { func, args } => {
+                let callee = self
+                    .program
+                    .function(func)
+                    .ok_or_else(|| self.error(format!("`{}` is not a function", func)))?;
+                if args.len() != callee.params {
+                    return Err(self.error(format!(
+                        "`{}` takes {} arguments, but is given {}",
+                        func,
+                        callee.params,
+                        args.len()
+                    )));
+                }
+                for (index, (arg, param)) in args.iter().zip(&callee.locals).enumerate() {
+                    let ty = match arg {
+                        Operand::Ref(local) if param.by_ref => {
+                            self.local(*local)?;
+                            self.func.locals[*local].ty.clone()
+                        }
+                        _ if param.by_ref => {
+                            return Err(self.error(format!(
+                                "argument {} of `{}` must be passed as `&mut`",
+                                index + 1,
+                                func
+                            )))
+                        }
+                        arg => self.operand(arg)?,
+                    };
+                    if ty != param.ty {
+                        return Err(self.error(format!(
+                            "argument {} of `{}` is a `{}`, but should be a `{}`",
+                            index + 1,
+                            func,
+                            ty,
+                            param.ty
+                        )));
+                    }
+                }
+                callee.ret.clone()
+            }
+            Rvalue::Struct { name, members } => {
+                let (_, expected) = self
+                    .program
+                    .structs
+                    .iter()
+                    .find(|(other, _)| other == name)
+                    .ok_or_else(|| self.error(format!("`{}` is not a struct", name)))?;
+                if members.len() != expected.len() {
+                    return Err(self.error(format!(
+                        "`{}` has {} members, but is given {}",
+                        name,
+                        expected.len(),
+                        members.len()
+                    )));
+                }
+                for (member, (member_name, ty)) in members.iter().zip(expected) {
+                    let given = self.operand(member)?;
+                    if given != *ty {
+                        return Err(self.error(format!(
+                            "member `{}` of `{}` is given a `{}`, but is a `{}`",
+                            member_name, name, given, ty
+                        )));
+                    }
+                }
+                Ty::Struct(name.clone())
+            }
+            Rvalue::Member(operand, index) => {
+                let ty = self.operand(operand)?;
+                let members = match &ty {
+                    Ty::Struct(name) => self
+                        .program
+                        .structs
+                        .iter()
+                        .find(|(other, _)| other == name)
+                        .map(|(_, members)| members.as_slice()),
+                    _ => None,
+                };
+                match members.and_then(|members| members.get(*index)) {
+                    Some((_, ty)) => ty.clone(),
+                    None => return Err(self.error(format!("a `{}` has no member {}", ty, index))),
+                }
+            }
+        })
+    }
+
+    fn terminator(&self, index: usize, terminator: &Terminator) -> Result<(), Error> {
+        match terminator {
+            Terminator::Goto(target) => self.target(index, *target)?,
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let ty = self.operand(condition)?;
+                if ty != Ty::Bool {
+                    return Err(self.error(format!("bb{} branches on a `{}`", index, ty)));
+                }
+                self.target(index, *then)?;
+                self.target(index, *otherwise)?;
+            }
+            Terminator::Return(value) => {
+                let ty = match value {
+                    Some(value) => self.operand(value)?,
+                    None => Ty::Unit,
+                };
+                if ty != self.func.ret {
+                    return Err(self.error(format!(
+                        "bb{} returns a `{}`, but should return a `{}`",
+                        index, ty, self.func.ret
+                    )));
+                }
+            }
+            Terminator::Unreachable => {}
+        }
+        Ok(())
+    }
+}
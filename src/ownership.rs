@@ -0,0 +1,609 @@
+//! Ownership inference for the Rust backend.
+//!
+//! fir has no borrows or lifetimes, so before a function is lowered to Rust
+//! every binding and every use of a binding is classified here. The rules
+//! follow fir's value model:
+//!
+//! - `int`, `float`, `bool` and `char` are plain values and are copied.
+//! - Everything else is an object. A parameter names the caller's object, so
+//!   assigning to a parameter overwrites the caller's value, and `let b = a`
+//!   makes `b` a second name for `a`'s object.
+//!
+//! Liveness is computed over the order in which uses appear in the function
+//! body. Since there are no loops, that order over-approximates every path
+//! through the function, so a value is only moved when no later use exists on
+//! any path.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::{
+    ast,
+    error::Error,
+    types::{ExprTypes, Ty, Types},
+};
+
+/// How a binding is stored or how one of its uses is passed along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Passed or stored by value, the type is `Copy`.
+    Copy,
+    /// Ownership is handed over, the source is not used afterwards.
+    Move,
+    /// Shared borrow, `&T`.
+    Borrow,
+    /// Mutable borrow, `&mut T`.
+    BorrowMut,
+    /// A fresh copy, `.clone()`.
+    Clone,
+    /// Shared ownership with interior mutability, `Rc<RefCell<T>>`.
+    Shared,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Mode::Copy => "copy",
+                Mode::Move => "move",
+                Mode::Borrow => "&",
+                Mode::BorrowMut => "&mut",
+                Mode::Clone => "clone",
+                Mode::Shared => "Rc<RefCell<_>>",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub name: String,
+    pub ty: Ty,
+    pub is_param: bool,
+    /// `Copy`, `Borrow` or `BorrowMut` for parameters, `Move` (an owned
+    /// value) or `Shared` for locals.
+    pub mode: Mode,
+    /// Whether the binding has to be declared `mut`.
+    pub mutable: bool,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventKind {
+    /// Operand of an operator or condition, the value is only looked at.
+    Read,
+    /// Argument `index` of a call to `callee`.
+    Arg { callee: String, index: usize },
+    /// The whole value of a `let` or an assignment.
+    Source,
+    /// The whole value of a member in a struct literal.
+    Member,
+    /// A `let` introducing the binding.
+    Declare,
+    /// Assignment to the binding.
+    Write,
+}
+
+/// One occurrence of a name in a function body, in evaluation order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub kind: EventKind,
+    /// `None` for names that do not resolve to a parameter or local.
+    pub binding: Option<usize>,
+    pub mode: Mode,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FnOwnership {
+    pub name: String,
+    pub bindings: Vec<Binding>,
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Ownership {
+    pub functions: Vec<FnOwnership>,
+}
+
+impl Ownership {
+    pub fn function(&self, name: &str) -> Option<&FnOwnership> {
+        self.functions.iter().find(|func| func.name == name)
+    }
+
+    pub fn param_modes(&self, name: &str) -> Option<Vec<Mode>> {
+        self.function(name).map(FnOwnership::param_modes)
+    }
+
+    /// The parameter modes of every function, which is all a caller needs.
+    pub fn signatures(&self) -> HashMap<String, Vec<Mode>> {
+        self.functions
+            .iter()
+            .map(|func| (func.name.clone(), func.param_modes()))
+            .collect()
+    }
+}
+
+impl FnOwnership {
+    pub fn param_modes(&self) -> Vec<Mode> {
+        self.bindings
+            .iter()
+            .filter(|binding| binding.is_param)
+            .map(|binding| binding.mode)
+            .collect()
+    }
+}
+
+pub fn is_copy(ty: &Ty) -> bool {
+    matches!(ty, Ty::Int | Ty::Float | Ty::Bool | Ty::Char)
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    matches!(name, "print" | "assert" | "assert_eq" | "hash")
+}
+
+/// Infers the ownership of every function of `items`, which have to check:
+/// the types of bindings are the ones `types` and the types of the
+/// expressions of each function, by name, give.
+pub fn analyze(
+    items: &[Result<ast::Spanned<ast::Item>, Error>],
+    types: &Types,
+    exprs: &HashMap<String, Rc<ExprTypes>>,
+) -> Result<Ownership, Error> {
+    let mut functions = Vec::new();
+    for item in items {
+        if let ast::Item::Function {
+            name,
+            parameters,
+            body,
+            ..
+        } = &item.as_ref().map_err(Clone::clone)?.node
+        {
+            let mut collector = Collector {
+                body,
+                exprs: &exprs[name.as_str()],
+                bindings: Vec::new(),
+                events: Vec::new(),
+                scopes: Vec::new(),
+            };
+            for ((param, _), ty) in parameters
+                .iter()
+                .zip(&types.functions[name.as_str()].params)
+            {
+                let (mode, reason) = if is_copy(ty) {
+                    (Mode::Copy, "plain value")
+                } else {
+                    (Mode::Borrow, "only read")
+                };
+                collector.bindings.push(Binding {
+                    name: param.to_string(),
+                    ty: ty.clone(),
+                    is_param: true,
+                    mode,
+                    mutable: false,
+                    reason: reason.to_string(),
+                });
+            }
+            collector.scopes.push(
+                parameters
+                    .iter()
+                    .enumerate()
+                    .map(|(index, (param, _))| (param.to_string(), index))
+                    .collect(),
+            );
+            collector.stmts(&body.block)?;
+            functions.push(FnOwnership {
+                name: name.to_string(),
+                bindings: collector.bindings,
+                events: collector.events,
+            });
+        }
+    }
+
+    let mut ownership = Ownership { functions };
+    infer_params(&mut ownership);
+    for index in 0..ownership.functions.len() {
+        let signatures = ownership.signatures();
+        decide(&mut ownership.functions[index], &signatures);
+    }
+    Ok(ownership)
+}
+
+/// A non-`Copy` parameter that is written, directly or by passing it on to a
+/// parameter that is written, is taken as `&mut`. Callees may be defined after
+/// their callers, so this runs until nothing changes.
+fn infer_params(ownership: &mut Ownership) {
+    loop {
+        let mut changed = false;
+        for index in 0..ownership.functions.len() {
+            let signatures: HashMap<String, Vec<Mode>> = ownership
+                .functions
+                .iter()
+                .map(|func| (func.name.clone(), func.param_modes()))
+                .collect();
+            let func = &mut ownership.functions[index];
+            for event in &func.events {
+                let binding = match event.binding {
+                    Some(binding) if func.bindings[binding].is_param => binding,
+                    _ => continue,
+                };
+                let reason = match &event.kind {
+                    EventKind::Write => "assigned in the body".to_string(),
+                    EventKind::Arg { callee, index } => {
+                        match signatures.get(callee).and_then(|modes| modes.get(*index)) {
+                            Some(Mode::BorrowMut) => format!("passed on to `{}` as `&mut`", callee),
+                            _ => continue,
+                        }
+                    }
+                    _ => continue,
+                };
+                let param = &mut func.bindings[binding];
+                if param.mode == Mode::Copy {
+                    if !param.mutable {
+                        param.mutable = true;
+                        param.reason = format!("copied, {}", reason);
+                    }
+                } else if param.mode != Mode::BorrowMut {
+                    param.mode = Mode::BorrowMut;
+                    param.reason = reason;
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+fn decide(func: &mut FnOwnership, signatures: &HashMap<String, Vec<Mode>>) {
+    let shared = shared_bindings(func, signatures);
+
+    for binding in 0..func.bindings.len() {
+        if func.bindings[binding].is_param {
+            continue;
+        }
+        if shared.contains(&binding) {
+            func.bindings[binding].mode = Mode::Shared;
+            func.bindings[binding].reason =
+                "aliased by `let` and mutated while another name is still in use".to_string();
+        } else {
+            func.bindings[binding].mode = Mode::Move;
+            func.bindings[binding].reason = "owned".to_string();
+        }
+    }
+
+    for position in 0..func.events.len() {
+        let event = &func.events[position];
+        let binding = match event.binding {
+            Some(binding) => binding,
+            None => {
+                func.events[position].mode = Mode::Borrow;
+                func.events[position].reason = "unknown name".to_string();
+                continue;
+            }
+        };
+        let info = &func.bindings[binding];
+        let copy = is_copy(&info.ty);
+        let (mode, reason) = match &event.kind {
+            EventKind::Read | EventKind::Declare | EventKind::Write => {
+                if copy {
+                    (Mode::Copy, "plain value".to_string())
+                } else if info.mode == Mode::Shared {
+                    (Mode::Shared, "shared object".to_string())
+                } else {
+                    (Mode::Borrow, "only looked at".to_string())
+                }
+            }
+            EventKind::Arg { callee, index } => {
+                match signatures.get(callee).and_then(|modes| modes.get(*index)) {
+                    Some(Mode::Copy) => (Mode::Copy, format!("`{}` copies it", callee)),
+                    Some(Mode::BorrowMut) => {
+                        (Mode::BorrowMut, format!("`{}` assigns to it", callee))
+                    }
+                    Some(_) => (Mode::Borrow, format!("`{}` only reads it", callee)),
+                    None if copy => (Mode::Copy, "plain value".to_string()),
+                    None => (Mode::Borrow, format!("`{}` is not a fir function", callee)),
+                }
+            }
+            kind @ (EventKind::Source | EventKind::Member) => {
+                let target = func.events.get(position + 1).and_then(|next| next.binding);
+                if copy {
+                    (Mode::Copy, "plain value".to_string())
+                } else if *kind == EventKind::Source
+                    && info.mode == Mode::Shared
+                    && target.is_some_and(|target| shared.contains(&target))
+                    && func.events[position + 1].kind == EventKind::Declare
+                {
+                    (Mode::Shared, "aliased by `let`".to_string())
+                } else if info.is_param || info.mode == Mode::Shared {
+                    (
+                        Mode::Clone,
+                        "the object stays with its other names".to_string(),
+                    )
+                } else if live_after(func, binding, position) {
+                    (Mode::Clone, format!("`{}` is used afterwards", info.name))
+                } else {
+                    (Mode::Move, format!("last use of `{}`", info.name))
+                }
+            }
+        };
+        func.events[position].mode = mode;
+        func.events[position].reason = reason;
+    }
+
+    for position in 0..func.events.len() {
+        let event = &func.events[position];
+        if let Some(binding) = event.binding {
+            let mutated = event.kind == EventKind::Write || event.mode == Mode::BorrowMut;
+            let binding = &mut func.bindings[binding];
+            if mutated && !binding.is_param && binding.mode != Mode::Shared {
+                binding.mutable = true;
+            }
+        }
+    }
+}
+
+/// Whether the next thing that happens to `binding` after `position` reads it.
+fn live_after(func: &FnOwnership, binding: usize, position: usize) -> bool {
+    func.events[position + 1..]
+        .iter()
+        .find(|event| event.binding == Some(binding))
+        .is_some_and(|event| !matches!(event.kind, EventKind::Write | EventKind::Declare))
+}
+
+/// Locals aliased through `let b = a` form groups. A group has to share its
+/// object when one name is mutated and another name is used after that.
+fn shared_bindings(func: &FnOwnership, signatures: &HashMap<String, Vec<Mode>>) -> Vec<usize> {
+    let mut group: Vec<usize> = (0..func.bindings.len()).collect();
+    fn root(group: &mut [usize], binding: usize) -> usize {
+        let mut binding = binding;
+        while group[binding] != binding {
+            binding = group[binding];
+        }
+        binding
+    }
+
+    let mut alias_start = HashMap::new();
+    for position in 0..func.events.len().saturating_sub(1) {
+        let (event, next) = (&func.events[position], &func.events[position + 1]);
+        if let (EventKind::Source, EventKind::Declare, Some(source), Some(target)) =
+            (&event.kind, &next.kind, event.binding, next.binding)
+        {
+            let info = &func.bindings[source];
+            if info.is_param || is_copy(&info.ty) {
+                continue;
+            }
+            let (source, target) = (root(&mut group, source), root(&mut group, target));
+            group[target] = source;
+            alias_start.entry(source).or_insert(position);
+        }
+    }
+
+    let mutates = |event: &Event| match &event.kind {
+        EventKind::Write => true,
+        EventKind::Arg { callee, index } => {
+            signatures.get(callee).and_then(|modes| modes.get(*index)) == Some(&Mode::BorrowMut)
+        }
+        _ => false,
+    };
+
+    let mut shared = Vec::new();
+    for binding in 0..func.bindings.len() {
+        let group_root = root(&mut group, binding);
+        let start = match alias_start.get(&group_root) {
+            Some(start) => *start,
+            None => continue,
+        };
+        let members: Vec<usize> = (0..func.bindings.len())
+            .filter(|member| root(&mut group, *member) == group_root)
+            .collect();
+        let observed = func
+            .events
+            .iter()
+            .enumerate()
+            .skip(start)
+            .any(|(write, event)| {
+                mutates(event)
+                    && event
+                        .binding
+                        .is_some_and(|writer| members.contains(&writer))
+                    && func.events[write + 1..].iter().any(|later| {
+                        later.binding.is_some_and(|reader| {
+                            reader != event.binding.unwrap() && members.contains(&reader)
+                        })
+                    })
+            });
+        if observed {
+            shared.push(binding);
+        }
+    }
+    shared
+}
+
+struct Collector<'a> {
+    body: &'a ast::Body,
+    exprs: &'a ExprTypes,
+    bindings: Vec<Binding>,
+    events: Vec<Event>,
+    scopes: Vec<HashMap<String, usize>>,
+}
+
+impl Collector<'_> {
+    fn lookup(&self, name: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+    }
+
+    fn push(&mut self, kind: EventKind, binding: Option<usize>) {
+        self.events.push(Event {
+            kind,
+            binding,
+            mode: Mode::Borrow,
+            reason: String::new(),
+        });
+    }
+
+    fn stmts(&mut self, stmts: &[ast::StmtId]) -> Result<(), Error> {
+        self.scopes.push(HashMap::new());
+        for stmt in stmts {
+            self.stmt(self.body.stmt(*stmt)?)?;
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &ast::Stmt) -> Result<(), Error> {
+        match stmt {
+            ast::Stmt::Let { var_name, value } => {
+                self.value(*value)?;
+                self.bindings.push(Binding {
+                    name: var_name.to_string(),
+                    ty: self.exprs[*value].clone(),
+                    is_param: false,
+                    mode: Mode::Move,
+                    mutable: false,
+                    reason: String::new(),
+                });
+                let binding = self.bindings.len() - 1;
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .insert(var_name.to_string(), binding);
+                self.push(EventKind::Declare, Some(binding));
+            }
+            ast::Stmt::Assignment { var_name, value } => {
+                self.value(*value)?;
+                let binding = self.lookup(var_name);
+                self.push(EventKind::Write, binding);
+            }
+            ast::Stmt::IfStmt {
+                condition,
+                body,
+                else_stmt,
+            } => {
+                self.expr(*condition)?;
+                self.stmts(body)?;
+                if let Some(else_stmt) = else_stmt {
+                    self.stmt(self.body.stmt(*else_stmt)?)?;
+                }
+            }
+            ast::Stmt::Block { stmts } => self.stmts(stmts)?,
+            ast::Stmt::Expr { expr } => self.expr(*expr)?,
+            ast::Stmt::Return { value } => {
+                if let Some(value) = value {
+                    self.value(*value)?;
+                }
+            }
+            ast::Stmt::Error(err) => return Err(err.clone()),
+        }
+        Ok(())
+    }
+
+    /// The value of a `let`, an assignment or a `return`.
+    fn value(&mut self, id: ast::ExprId) -> Result<(), Error> {
+        if let ast::Expr::Ident(name) = &self.body.expr(id)?.node {
+            let binding = self.lookup(name);
+            self.push(EventKind::Source, binding);
+            Ok(())
+        } else {
+            self.expr(id)
+        }
+    }
+
+    fn expr(&mut self, id: ast::ExprId) -> Result<(), Error> {
+        match &self.body.expr(id)?.node {
+            ast::Expr::Literal(_) => {}
+            ast::Expr::Ident(name) => {
+                let binding = self.lookup(name);
+                self.push(EventKind::Read, binding);
+            }
+            ast::Expr::FnCall { fn_name, args } => {
+                for (index, arg) in args.iter().enumerate() {
+                    match &self.body.expr(*arg)?.node {
+                        ast::Expr::Ident(name) if !is_builtin(fn_name) => {
+                            let binding = self.lookup(name);
+                            self.push(
+                                EventKind::Arg {
+                                    callee: fn_name.to_string(),
+                                    index,
+                                },
+                                binding,
+                            );
+                        }
+                        _ => self.expr(*arg)?,
+                    }
+                }
+            }
+            ast::Expr::PrefixOp { expr, .. }
+            | ast::Expr::PostfixOp { expr, .. }
+            | ast::Expr::Member { expr, .. } => self.expr(*expr)?,
+            ast::Expr::InfixOp { lhs, rhs, .. } => {
+                self.expr(*lhs)?;
+                self.expr(*rhs)?;
+            }
+            ast::Expr::StructLit { fields, .. } => {
+                for (_, value) in fields {
+                    match &self.body.expr(*value)?.node {
+                        ast::Expr::Ident(name) => {
+                            let binding = self.lookup(name);
+                            self.push(EventKind::Member, binding);
+                        }
+                        _ => self.expr(*value)?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for FnOwnership {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "fn {}", self.name)?;
+        for binding in &self.bindings {
+            writeln!(
+                f,
+                "  {} {}{}: {} -> {} ({})",
+                if binding.is_param { "param" } else { "let" },
+                if binding.mutable { "mut " } else { "" },
+                binding.name,
+                binding.ty,
+                binding.mode,
+                binding.reason
+            )?;
+        }
+        for event in &self.events {
+            let name = match event.binding {
+                Some(binding) => self.bindings[binding].name.as_str(),
+                None => continue,
+            };
+            let context = match &event.kind {
+                EventKind::Arg { callee, index } => format!("argument {} of `{}`", index, callee),
+                EventKind::Source => "value of `let`/assignment".to_string(),
+                EventKind::Member => "member of a struct literal".to_string(),
+                _ => continue,
+            };
+            writeln!(
+                f,
+                "  use `{}` as {} -> {} ({})",
+                name, context, event.mode, event.reason
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Ownership {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for func in &self.functions {
+            write!(f, "{}", func)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,496 @@
+//! Detection of statements that can run on separate threads.
+//!
+//! Within every block, consecutive `let` statements whose values call fir
+//! functions are grouped when they are independent of each other: no statement
+//! writes a name another one reads or writes, and none of them prints or calls
+//! something that does. fir has no loops yet, so blocks are the only place
+//! where this looks for work.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Range;
+
+use crate::{
+    ast,
+    error::Error,
+    ownership::{self, Mode, Ownership},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockPlan {
+    /// Where the block is, e.g. `body > if > else`.
+    pub path: String,
+    /// Ranges of statement indices that run in parallel.
+    pub groups: Vec<Range<usize>>,
+    /// Why statements that do work were left to run in order.
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FnPlan {
+    pub name: String,
+    /// Every block of the function, in the order they appear in the source.
+    pub blocks: Vec<BlockPlan>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Plan {
+    pub functions: Vec<FnPlan>,
+}
+
+impl Plan {
+    pub fn function(&self, name: &str) -> Option<&FnPlan> {
+        self.functions.iter().find(|func| func.name == name)
+    }
+}
+
+pub fn analyze(
+    items: &[Result<ast::Spanned<ast::Item>, Error>],
+    ownership: &Ownership,
+    enabled: bool,
+) -> Result<Plan, Error> {
+    let mut bodies = Vec::new();
+    for item in items {
+        if let ast::Item::Function { name, body, .. } = &item.as_ref().map_err(Clone::clone)?.node {
+            bodies.push((name, body));
+        }
+    }
+    let functions: HashSet<String> = bodies.iter().map(|(name, _)| name.to_string()).collect();
+
+    let mut calls = HashMap::new();
+    for (name, body) in &bodies {
+        let mut effects = Effects::default();
+        for stmt in &body.block {
+            effects.stmt(body, body.stmt(*stmt)?, ownership)?;
+        }
+        calls.insert(name.to_string(), effects.calls);
+    }
+    let mut impure = HashMap::new();
+    loop {
+        let mut changed = false;
+        for (name, calls) in &calls {
+            if impure.contains_key(name) {
+                continue;
+            }
+            if let Some(reason) = impurity(calls, &functions, &impure) {
+                impure.insert(name.clone(), reason);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut plans = Vec::new();
+    for (name, body) in bodies {
+        let shared = ownership.function(name).map_or_else(HashSet::new, |func| {
+            func.bindings
+                .iter()
+                .filter(|binding| binding.mode == Mode::Shared)
+                .map(|binding| binding.name.clone())
+                .collect()
+        });
+        let mut planner = Planner {
+            body,
+            ownership,
+            functions: &functions,
+            impure: &impure,
+            shared,
+            enabled,
+            blocks: Vec::new(),
+        };
+        planner.block("body".to_string(), &body.block)?;
+        plans.push(FnPlan {
+            name: name.to_string(),
+            blocks: planner.blocks,
+        });
+    }
+    Ok(Plan { functions: plans })
+}
+
+/// Why code making `calls` may have effects other than on the names passed
+/// to the callees, if it may.
+fn impurity(
+    calls: &[String],
+    functions: &HashSet<String>,
+    impure: &HashMap<String, String>,
+) -> Option<String> {
+    calls.iter().find_map(|callee| {
+        if callee == "hash" {
+            None
+        } else if callee == "print" {
+            Some(format!("`{}` does I/O", callee))
+        } else if ownership::is_builtin(callee) {
+            Some(format!("`{}` may stop the program", callee))
+        } else if !functions.contains(callee) {
+            Some(format!("`{}` is not a fir function", callee))
+        } else {
+            impure
+                .get(callee)
+                .map(|reason| format!("`{}` is not pure: {}", callee, reason))
+        }
+    })
+}
+
+/// Names a statement reads and writes, and the functions it calls.
+#[derive(Debug, Default)]
+struct Effects {
+    reads: HashSet<String>,
+    writes: HashSet<String>,
+    calls: Vec<String>,
+}
+
+impl Effects {
+    fn stmt(
+        &mut self,
+        body: &ast::Body,
+        stmt: &ast::Stmt,
+        ownership: &Ownership,
+    ) -> Result<(), Error> {
+        match stmt {
+            ast::Stmt::Let { var_name, value } => {
+                self.expr(body, body.expr(*value)?, ownership)?;
+                self.writes.insert(var_name.to_string());
+            }
+            ast::Stmt::Assignment { var_name, value } => {
+                self.expr(body, body.expr(*value)?, ownership)?;
+                self.writes.insert(var_name.to_string());
+            }
+            ast::Stmt::IfStmt {
+                condition,
+                body: then,
+                else_stmt,
+            } => {
+                self.expr(body, body.expr(*condition)?, ownership)?;
+                for stmt in then {
+                    self.stmt(body, body.stmt(*stmt)?, ownership)?;
+                }
+                if let Some(else_stmt) = else_stmt {
+                    self.stmt(body, body.stmt(*else_stmt)?, ownership)?;
+                }
+            }
+            ast::Stmt::Block { stmts } => {
+                for stmt in stmts {
+                    self.stmt(body, body.stmt(*stmt)?, ownership)?;
+                }
+            }
+            ast::Stmt::Expr { expr } => self.expr(body, body.expr(*expr)?, ownership)?,
+            ast::Stmt::Return { value } => {
+                if let Some(value) = value {
+                    self.expr(body, body.expr(*value)?, ownership)?
+                }
+            }
+            ast::Stmt::Error(err) => return Err(err.clone()),
+        }
+        Ok(())
+    }
+
+    fn expr(
+        &mut self,
+        body: &ast::Body,
+        expr: &ast::Expr,
+        ownership: &Ownership,
+    ) -> Result<(), Error> {
+        match expr {
+            ast::Expr::Literal(_) => {}
+            ast::Expr::Ident(name) => {
+                self.reads.insert(name.to_string());
+            }
+            ast::Expr::FnCall { fn_name, args } => {
+                let modes = ownership.param_modes(fn_name).unwrap_or_default();
+                for (index, arg) in args.iter().enumerate() {
+                    match &body.expr(*arg)?.node {
+                        ast::Expr::Ident(name) if modes.get(index) == Some(&Mode::BorrowMut) => {
+                            self.writes.insert(name.to_string());
+                        }
+                        arg => self.expr(body, arg, ownership)?,
+                    }
+                }
+                self.calls.push(fn_name.to_string());
+            }
+            ast::Expr::PrefixOp { expr, .. }
+            | ast::Expr::PostfixOp { expr, .. }
+            | ast::Expr::Member { expr, .. } => self.expr(body, body.expr(*expr)?, ownership)?,
+            ast::Expr::InfixOp { lhs, rhs, .. } => {
+                self.expr(body, body.expr(*lhs)?, ownership)?;
+                self.expr(body, body.expr(*rhs)?, ownership)?;
+            }
+            ast::Expr::StructLit { fields, .. } => {
+                for (_, value) in fields {
+                    self.expr(body, body.expr(*value)?, ownership)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The first name through which `self` and `other` depend on each other.
+    fn conflict(&self, other: &Effects) -> Option<String> {
+        let mut names: Vec<&String> = self
+            .writes
+            .intersection(&other.reads)
+            .chain(self.writes.intersection(&other.writes))
+            .chain(self.reads.intersection(&other.writes))
+            .collect();
+        names.sort();
+        names.first().map(|name| name.to_string())
+    }
+}
+
+struct Planner<'a> {
+    body: &'a ast::Body,
+    ownership: &'a Ownership,
+    functions: &'a HashSet<String>,
+    impure: &'a HashMap<String, String>,
+    shared: HashSet<String>,
+    enabled: bool,
+    blocks: Vec<BlockPlan>,
+}
+
+impl<'a> Planner<'a> {
+    fn block(&mut self, path: String, stmts: &[ast::StmtId]) -> Result<(), Error> {
+        let index = self.blocks.len();
+        self.blocks.push(BlockPlan {
+            path: path.clone(),
+            groups: Vec::new(),
+            notes: Vec::new(),
+        });
+
+        let mut groups = Vec::new();
+        let mut notes = Vec::new();
+        let mut group: Vec<(usize, Effects)> = Vec::new();
+        for (position, stmt) in stmts.iter().enumerate() {
+            let stmt = self.body.stmt(*stmt)?;
+            self.nested(&path, stmt)?;
+
+            let mut effects = Effects::default();
+            effects.stmt(self.body, stmt, self.ownership)?;
+            if effects.calls.is_empty() {
+                close(&mut group, &mut groups);
+                continue;
+            }
+            let blocker = if !self.enabled {
+                Some("parallelization is disabled".to_string())
+            } else if let Some(reason) = impurity(&effects.calls, self.functions, self.impure) {
+                Some(reason)
+            } else if !matches!(stmt.node, ast::Stmt::Let { .. }) {
+                Some("only `let` statements run in parallel".to_string())
+            } else {
+                let mut names: Vec<&String> = effects
+                    .reads
+                    .union(&effects.writes)
+                    .filter(|name| self.shared.contains(*name))
+                    .collect();
+                names.sort();
+                names.first().map(|name| {
+                    format!(
+                        "`{}` is shared through `Rc<RefCell<_>>`, which is not thread-safe",
+                        name
+                    )
+                })
+            };
+            if let Some(reason) = blocker {
+                close(&mut group, &mut groups);
+                notes.push(format!(
+                    "statement {} runs in order: {}",
+                    position + 1,
+                    reason
+                ));
+                continue;
+            }
+
+            let conflict = group.iter().find_map(|(other, other_effects)| {
+                other_effects.conflict(&effects).map(|name| (*other, name))
+            });
+            if let Some((other, name)) = conflict {
+                notes.push(format!(
+                    "statement {} runs after statement {}: both use `{}`",
+                    position + 1,
+                    other + 1,
+                    name
+                ));
+                close(&mut group, &mut groups);
+            }
+            group.push((position, effects));
+        }
+        close(&mut group, &mut groups);
+
+        self.blocks[index].groups = groups;
+        self.blocks[index].notes = notes;
+        Ok(())
+    }
+
+    fn nested(&mut self, path: &str, stmt: &ast::Stmt) -> Result<(), Error> {
+        match stmt {
+            ast::Stmt::IfStmt {
+                body, else_stmt, ..
+            } => {
+                self.block(format!("{} > if", path), body)?;
+                if let Some(else_stmt) = else_stmt {
+                    let else_stmt = self.body.stmt(*else_stmt)?;
+                    match &else_stmt.node {
+                        ast::Stmt::Block { stmts } => {
+                            self.block(format!("{} > else", path), stmts)?
+                        }
+                        stmt => self.nested(&format!("{} > else", path), stmt)?,
+                    }
+                }
+            }
+            ast::Stmt::Block { stmts } => self.block(format!("{} > block", path), stmts)?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Ends the current group, keeping it if there is more than one statement to
+/// run at the same time.
+fn close(group: &mut Vec<(usize, Effects)>, groups: &mut Vec<Range<usize>>) {
+    if group.len() > 1 {
+        groups.push(group[0].0..group[group.len() - 1].0 + 1);
+    }
+    group.clear();
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for func in &self.functions {
+            writeln!(f, "fn {}", func.name)?;
+            for block in &func.blocks {
+                if block.groups.is_empty() && block.notes.is_empty() {
+                    continue;
+                }
+                writeln!(f, "  {}", block.path)?;
+                for group in &block.groups {
+                    writeln!(
+                        f,
+                        "    statements {} to {} run in parallel",
+                        group.start + 1,
+                        group.end
+                    )?;
+                }
+                for note in &block.notes {
+                    writeln!(f, "    {}", note)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    const FUNCTIONS: &str = "fn square(x: int) -> int {
+    return x * x;
+}
+
+fn shout(x: int) -> int {
+    print(x);
+    return x;
+}
+";
+
+    fn plan(main: &str, enabled: bool) -> Plan {
+        let mut db = Database::default();
+        let file = db.add_file(format!("{}\nfn main() {{\n{}}}\n", FUNCTIONS, main));
+        let items = db.items(file);
+        let ownership = db.ownership(file);
+        analyze(&items, ownership.as_ref().as_ref().unwrap(), enabled).unwrap()
+    }
+
+    /// A plan with `groups` given as the start and end of each.
+    fn block(path: &str, groups: &[(usize, usize)], notes: &[&str]) -> BlockPlan {
+        BlockPlan {
+            path: path.to_string(),
+            groups: groups.iter().map(|(start, end)| *start..*end).collect(),
+            notes: notes.iter().map(|note| note.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn independent_statements_run_in_parallel() {
+        let plan = plan(
+            "    let a = square(2);
+    let b = square(3);
+    let c = square(4);
+    print(a + b + c);
+",
+            true,
+        );
+        assert_eq!(
+            plan.function("main").unwrap().blocks,
+            [block(
+                "body",
+                &[(0, 3)],
+                &["statement 4 runs in order: `print` does I/O"]
+            )]
+        );
+        assert_eq!(
+            plan.to_string(),
+            "fn square
+fn shout
+  body
+    statement 1 runs in order: `print` does I/O
+fn main
+  body
+    statements 1 to 3 run in parallel
+    statement 4 runs in order: `print` does I/O
+"
+        );
+    }
+
+    #[test]
+    fn dependent_statements_run_in_order() {
+        let plan = plan(
+            "    let a = square(2);
+    let b = square(a);
+    let c = shout(3);
+    if (b > c) {
+        let d = square(b);
+        let e = square(c);
+        print(d + e);
+    }
+",
+            true,
+        );
+        assert_eq!(
+            plan.function("main").unwrap().blocks,
+            [
+                block(
+                    "body",
+                    &[],
+                    &[
+                        "statement 2 runs after statement 1: both use `a`",
+                        "statement 3 runs in order: `shout` is not pure: `print` does I/O",
+                        "statement 4 runs in order: `print` does I/O",
+                    ]
+                ),
+                block(
+                    "body > if",
+                    &[(0, 2)],
+                    &["statement 3 runs in order: `print` does I/O"]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn nothing_runs_in_parallel_when_disabled() {
+        let plan = plan("    let a = square(2);\n    let b = square(3);\n", false);
+        assert_eq!(
+            plan.function("main").unwrap().blocks,
+            [block(
+                "body",
+                &[],
+                &[
+                    "statement 1 runs in order: parallelization is disabled",
+                    "statement 2 runs in order: parallelization is disabled",
+                ]
+            )]
+        );
+    }
+}
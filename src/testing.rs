@@ -0,0 +1,206 @@
+//! The test runner of `fir test`.
+//!
+//! Tests are the functions marked `#[test]`, named by the module they are
+//! in, as in `fir doc`, and their own name, e.g. `geometry::point::origin`.
+//! A filter keeps the tests whose name contains it.
+//!
+//! `run` runs tests on the bytecode VM, on as many threads as there are
+//! cores unless told not to. A test passes unless it stops with an error,
+//! such as a failed `assert`, and what it prints is only shown if it fails.
+//! `run_rust` builds every module with tests to run as Rust, with
+//! `rustc --test`, and leaves running them to the Rust test harness, which
+//! reports in the same format.
+
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::{ast, bytecode, db::Items, mir};
+
+/// The tests of a module, and its code for a backend: MIR for the VM, or
+/// Rust source.
+pub struct Suite<T> {
+    pub module: String,
+    pub tests: Vec<String>,
+    pub code: T,
+}
+
+impl<T> Suite<T> {
+    fn name(&self, test: &str) -> String {
+        if self.module.is_empty() {
+            test.to_string()
+        } else {
+            format!("{}::{}", self.module, test)
+        }
+    }
+
+    /// The tests `filter` keeps.
+    fn selected<'a>(&'a self, filter: Option<&str>) -> Vec<&'a str> {
+        self.tests
+            .iter()
+            .filter(|test| filter.is_none_or(|filter| self.name(test).contains(filter)))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// The names of the tests in `items`, in order.
+pub fn tests(items: &Items) -> Vec<String> {
+    items
+        .iter()
+        .flatten()
+        .filter_map(|item| match &item.node {
+            ast::Item::Function { name, .. } if item.is_test() => Some(name.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A test that failed, with what it printed and the error it stopped with.
+struct Failure {
+    index: usize,
+    name: String,
+    output: String,
+    msg: String,
+}
+
+/// Runs the tests of `suites` that `filter` keeps on the VM, printing each
+/// result as it comes in and a summary at the end. Returns whether all of
+/// them passed.
+pub fn run(suites: &[Suite<mir::Program>], filter: Option<&str>, parallel: bool) -> bool {
+    let mut jobs = Vec::new();
+    let mut filtered = 0;
+    for suite in suites {
+        let selected = suite.selected(filter);
+        filtered += suite.tests.len() - selected.len();
+        jobs.extend(selected.into_iter().map(|test| (suite, test)));
+    }
+    println!();
+    let plural = if jobs.len() == 1 { "" } else { "s" };
+    println!("running {} test{}", jobs.len(), plural);
+
+    let threads = if parallel {
+        thread::available_parallelism().map_or(1, NonZeroUsize::get)
+    } else {
+        1
+    };
+    let next = AtomicUsize::new(0);
+    let failures = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..threads.min(jobs.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let (suite, test) = match jobs.get(index) {
+                    Some(job) => *job,
+                    None => break,
+                };
+                let name = suite.name(test);
+                let mut output = Vec::new();
+                match run_test(&suite.code, test, &mut output) {
+                    Ok(()) => println!("test {} ... ok", name),
+                    Err(msg) => {
+                        println!("test {} ... FAILED", name);
+                        failures.lock().unwrap().push(Failure {
+                            index,
+                            name,
+                            output: String::from_utf8_lossy(&output).into_owned(),
+                            msg,
+                        });
+                    }
+                }
+            });
+        }
+    });
+
+    let mut failures = failures.into_inner().unwrap();
+    failures.sort_by_key(|failure| failure.index);
+    if !failures.is_empty() {
+        println!();
+        println!("failures:");
+        for failure in &failures {
+            println!();
+            println!("---- {} ----", failure.name);
+            print!("{}", failure.output);
+            println!("{}", failure.msg);
+        }
+        println!();
+        println!("failures:");
+        for failure in &failures {
+            println!("    {}", failure.name);
+        }
+    }
+    println!();
+    println!(
+        "test result: {}. {} passed; {} failed; {} filtered out",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        jobs.len() - failures.len(),
+        failures.len(),
+        filtered
+    );
+    println!();
+    failures.is_empty()
+}
+
+/// Runs the test `name` of `program`, printing to `out`. Modules hold `Rc`s
+/// and can't be shared between threads, so each test compiles its own.
+fn run_test(program: &mir::Program, name: &str, out: &mut Vec<u8>) -> Result<(), String> {
+    let module = bytecode::compile(program).map_err(|err| err.msg().to_string())?;
+    let func = module
+        .function(name)
+        .ok_or_else(|| format!("There is no function `{}`", name))?;
+    bytecode::vm::run_function(&module, func, out).map_err(|err| err.msg().to_string())
+}
+
+/// Builds each suite with tests that `filter` keeps as a Rust test binary in
+/// `dir` and runs those tests, on one thread unless `parallel`. Returns
+/// whether all of them passed, or why a suite could not be built.
+pub fn run_rust(
+    suites: &[Suite<String>],
+    dir: &Path,
+    filter: Option<&str>,
+    parallel: bool,
+) -> Result<bool, String> {
+    let mut passed = true;
+    for suite in suites {
+        let selected = suite.selected(filter);
+        if selected.is_empty() {
+            continue;
+        }
+        let name = if suite.module.is_empty() {
+            "tests".to_string()
+        } else {
+            suite.module.replace("::", "_")
+        };
+        let source = dir.join(&name).with_extension("rs");
+        let binary = dir.join(&name);
+        fs::create_dir_all(dir)
+            .and_then(|()| fs::write(&source, &suite.code))
+            .map_err(|err| format!("could not write `{}`: {}", source.display(), err))?;
+        // Warnings are about the emitted Rust, not the fir source.
+        let built = Command::new("rustc")
+            .args(["--edition=2021", "--test", "-A", "warnings", "-o"])
+            .arg(&binary)
+            .arg(&source)
+            .status()
+            .map_err(|err| format!("could not run `rustc`: {}", err))?;
+        if !built.success() {
+            return Err(format!("`rustc` could not build `{}`", source.display()));
+        }
+        let mut command = Command::new(&binary);
+        command.arg("--exact").args(&selected);
+        if !parallel {
+            command.arg("--test-threads=1");
+        }
+        // A backtrace would be of the emitted Rust.
+        let status = command
+            .env("RUST_BACKTRACE", "0")
+            .status()
+            .map_err(|err| format!("could not run `{}`: {}", binary.display(), err))?;
+        passed &= status.success();
+    }
+    Ok(passed)
+}
@@ -0,0 +1,906 @@
+//! Type checking of fir items. Backends that need to know the type of every
+//! value run this first and may then assume the program is well typed.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Index;
+
+use logos::Span;
+
+use crate::{
+    ast,
+    attrs::{self, Target},
+    error::{codes, Error},
+    lexer::TokenKind,
+    ownership,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ty {
+    Int,
+    Float,
+    Bool,
+    Char,
+    Str,
+    Unit,
+    Struct(String),
+}
+
+impl Ty {
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Ty::Int | Ty::Float)
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::Int => write!(f, "int"),
+            Ty::Float => write!(f, "float"),
+            Ty::Bool => write!(f, "bool"),
+            Ty::Char => write!(f, "char"),
+            Ty::Str => write!(f, "string"),
+            Ty::Unit => write!(f, "()"),
+            Ty::Struct(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// What a struct can do with `#[derive(...)]`: be compared with `==`,
+/// printed, hashed or cloned. The other types can do what they support
+/// without it, see `Types::supports`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Derive {
+    Eq,
+    Print,
+    Hash,
+    Clone,
+}
+
+impl Derive {
+    pub fn parse(name: &str) -> Option<Derive> {
+        match name {
+            "Eq" => Some(Derive::Eq),
+            "Print" => Some(Derive::Print),
+            "Hash" => Some(Derive::Hash),
+            "Clone" => Some(Derive::Clone),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Derive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FnSig {
+    pub params: Vec<Ty>,
+    pub ret: Ty,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Types {
+    /// Structs and the types of their members, in declaration order.
+    pub structs: Vec<(String, Vec<(String, Ty)>)>,
+    /// What each struct derives.
+    pub derives: HashMap<String, Vec<Derive>>,
+    pub functions: HashMap<String, FnSig>,
+}
+
+impl Types {
+    /// Whether values of type `ty` can do `derive`. Floats can not be
+    /// hashed, and structs only do what they derive.
+    pub fn supports(&self, ty: &Ty, derive: Derive) -> bool {
+        supports(&self.derives, ty, derive)
+    }
+
+    /// The members of the struct `name` and their types, in declaration
+    /// order, if there is such a struct.
+    pub fn members(&self, name: &str) -> Option<&[(String, Ty)]> {
+        self.structs
+            .iter()
+            .find(|(other, _)| other == name)
+            .map(|(_, members)| members.as_slice())
+    }
+
+    /// The type named by `ty`.
+    pub fn resolve(&self, ty: &ast::Spanned<ast::Type>) -> Result<Ty, Error> {
+        if !ty.generics.is_empty() {
+            return Err(Error::new(
+                format!("Generic type `{}` is not supported yet", ty.name),
+                codes::E039,
+                ty.span.clone(),
+            ));
+        }
+        Ok(match ty.name.as_str() {
+            "int" => Ty::Int,
+            "float" => Ty::Float,
+            "bool" => Ty::Bool,
+            "char" => Ty::Char,
+            "string" => Ty::Str,
+            name if self.structs.iter().any(|(other, _)| other == name) => {
+                Ty::Struct(name.to_string())
+            }
+            name => {
+                return Err(Error::new(
+                    format!("Unknown type `{}`", name),
+                    codes::E022,
+                    ty.name.span.clone(),
+                ))
+            }
+        })
+    }
+}
+
+/// The type of every expression of a function body, kept by the id of the
+/// expression as `ast::arena` describes, so that backends need not work
+/// them out again.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ExprTypes {
+    types: Vec<Option<Ty>>,
+}
+
+impl ExprTypes {
+    fn insert(&mut self, id: ast::ExprId, ty: Ty) {
+        if self.types.len() <= id.index() {
+            self.types.resize(id.index() + 1, None);
+        }
+        self.types[id.index()] = Some(ty);
+    }
+}
+
+impl Index<ast::ExprId> for ExprTypes {
+    type Output = Ty;
+
+    fn index(&self, id: ast::ExprId) -> &Ty {
+        self.types[id.index()]
+            .as_ref()
+            .expect("only checked bodies have expression types")
+    }
+}
+
+/// `Types::supports`, for the MIR, which has the same `derives`.
+pub fn supports(derives: &HashMap<String, Vec<Derive>>, ty: &Ty, derive: Derive) -> bool {
+    match ty {
+        Ty::Unit => false,
+        Ty::Float => derive != Derive::Hash,
+        Ty::Struct(name) => derives
+            .get(name)
+            .is_some_and(|derives| derives.contains(&derive)),
+        _ => true,
+    }
+}
+
+/// The note on an error about a value of type `ty` that can't do `derive`.
+fn missing(ty: &Ty, derive: Derive) -> Option<String> {
+    match ty {
+        Ty::Struct(name) => Some(format!(
+            "`{}` does not derive `{}`, see `#[derive]`",
+            name, derive
+        )),
+        _ => None,
+    }
+}
+
+/// `types` separated by commas, as in a signature.
+fn list(types: &[Ty]) -> String {
+    types
+        .iter()
+        .map(Ty::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The type calls to the builtin `name` return: `hash` gives an `int`, the
+/// others nothing.
+pub fn builtin_ret(name: &str) -> Ty {
+    match name {
+        "hash" => Ty::Int,
+        _ => Ty::Unit,
+    }
+}
+
+/// Checks the arguments of a call to a builtin and returns its type.
+/// `print` takes any values it can print.
+fn builtin(types: &Types, name: &ast::Ident, args: &[Ty], span: Span) -> Result<Ty, Error> {
+    let expected = match name.as_str() {
+        "assert" => "(bool)",
+        "assert_eq" => "two values of a type `==` compares",
+        "hash" => {
+            return match args {
+                [ty] if types.supports(ty, Derive::Hash) => Ok(Ty::Int),
+                _ => {
+                    let err = Error::new(
+                        format!(
+                            "`hash` takes one value it can hash, but was given ({})",
+                            list(args)
+                        ),
+                        codes::E050,
+                        span,
+                    );
+                    Err(match args {
+                        [ty] => err.with_note(
+                            missing(ty, Derive::Hash)
+                                .unwrap_or_else(|| format!("A `{}` can not be hashed", ty)),
+                            None,
+                        ),
+                        _ => err,
+                    })
+                }
+            }
+        }
+        _ => {
+            return match args.iter().find(|ty| !types.supports(ty, Derive::Print)) {
+                Some(ty) => Err(Error::new(
+                    format!("`{}` can not print a `{}`", name, ty),
+                    codes::E023,
+                    span,
+                )
+                .with_note(missing(ty, Derive::Print).unwrap(), None)),
+                None => Ok(Ty::Unit),
+            }
+        }
+    };
+    let valid = match args {
+        [Ty::Bool] => name.as_str() == "assert",
+        // The types `==` compares.
+        [lhs, rhs] => name.as_str() == "assert_eq" && lhs == rhs && types.supports(lhs, Derive::Eq),
+        _ => false,
+    };
+    if !valid {
+        let err = Error::new(
+            format!(
+                "`{}` takes {}, but was given ({})",
+                name,
+                expected,
+                list(args)
+            ),
+            codes::E024,
+            span,
+        );
+        return Err(match args.first().and_then(|ty| missing(ty, Derive::Eq)) {
+            Some(note) if name.as_str() == "assert_eq" => err.with_note(note, None),
+            _ => err,
+        });
+    }
+    Ok(Ty::Unit)
+}
+
+/// The structs and function signatures of `items`, which is all that the
+/// bodies of other functions can see of them.
+pub fn declare(items: &[Result<ast::Spanned<ast::Item>, Error>]) -> Result<Types, Error> {
+    let mut types = Types::default();
+    for item in items {
+        if let ast::Item::Struct { attrs, name, .. } = &item.as_ref().map_err(Clone::clone)?.node {
+            attrs::check(attrs, Target::Struct)?;
+            let name = &name.as_ref().map_err(Clone::clone)?.name;
+            if types
+                .structs
+                .iter()
+                .any(|(other, _)| other.as_str() == name.as_str())
+            {
+                let first = items.iter().flatten().find_map(|item| match &item.node {
+                    ast::Item::Struct { name: Ok(ty), .. } if *ty.name == **name => {
+                        Some(ty.name.span.clone())
+                    }
+                    _ => None,
+                });
+                return Err(Error::new(
+                    format!("Struct `{}` is defined twice", name),
+                    codes::E025,
+                    name.span.clone(),
+                )
+                .with_note("First defined here".to_string(), first));
+            }
+            types.structs.push((name.to_string(), Vec::new()));
+            let derives = attrs::get(attrs, "derive")
+                .into_iter()
+                .flat_map(attrs::names)
+                .filter_map(|(derive, _)| Derive::parse(derive))
+                .collect();
+            types.derives.insert(name.to_string(), derives);
+        }
+    }
+    let mut index = 0;
+    for item in items {
+        if let ast::Item::Struct { attrs, members, .. } = &item.as_ref().map_err(Clone::clone)?.node
+        {
+            for member in members {
+                attrs::check(&member.attrs, Target::Member)?;
+                let ty = types.resolve(member.ty.as_ref().map_err(Clone::clone)?)?;
+                types.structs[index].1.push((member.name.to_string(), ty));
+            }
+            derive(&types, &types.structs[index], attrs, members)?;
+            index += 1;
+        }
+    }
+
+    for item in items {
+        let item = &item.as_ref().map_err(Clone::clone)?.node;
+        if let ast::Item::Function {
+            attrs,
+            name,
+            parameters,
+            return_type,
+            ..
+        } = item
+        {
+            attrs::check(attrs, Target::Function)?;
+            if ownership::is_builtin(name) {
+                return Err(Error::new(
+                    format!("Function `{}` is defined twice", name),
+                    codes::E026,
+                    name.span.clone(),
+                )
+                .with_note(format!("`{}` is built in", name), None));
+            }
+            if item.is_test() && (!parameters.is_empty() || return_type.is_some()) {
+                return Err(Error::new(
+                    format!("Test `{}` can not take parameters or return a value", name),
+                    codes::E027,
+                    name.span.clone(),
+                )
+                .with_note("`fir test` calls it without arguments".to_string(), None));
+            }
+            if types.functions.contains_key(name.as_str()) {
+                let first = items.iter().flatten().find_map(|item| match &item.node {
+                    ast::Item::Function { name: other, .. } if **other == **name => {
+                        Some(other.span.clone())
+                    }
+                    _ => None,
+                });
+                return Err(Error::new(
+                    format!("Function `{}` is defined twice", name),
+                    codes::E026,
+                    name.span.clone(),
+                )
+                .with_note("First defined here".to_string(), first));
+            }
+            let params = parameters
+                .iter()
+                .map(|(_, ty)| types.resolve(ty.as_ref().map_err(Clone::clone)?))
+                .collect::<Result<_, _>>()?;
+            let ret = match return_type {
+                Some(ty) => types.resolve(ty.as_ref().map_err(Clone::clone)?)?,
+                None => Ty::Unit,
+            };
+            types
+                .functions
+                .insert(name.to_string(), FnSig { params, ret });
+        }
+    }
+    Ok(types)
+}
+
+/// Checks that every member of a struct can do what the struct derives.
+fn derive(
+    types: &Types,
+    (name, member_types): &(String, Vec<(String, Ty)>),
+    attrs: &[ast::Spanned<ast::Attr>],
+    members: &[ast::Member],
+) -> Result<(), Error> {
+    let derives = attrs::get(attrs, "derive")
+        .into_iter()
+        .flat_map(attrs::names);
+    for (derive, span) in derives {
+        let derive = Derive::parse(derive).unwrap();
+        for (member, (_, ty)) in members.iter().zip(member_types) {
+            if types.supports(ty, derive) {
+                continue;
+            }
+            let note = missing(ty, derive).unwrap_or_else(|| match derive {
+                Derive::Hash => format!("A `{}` can not be hashed", ty),
+                derive => format!("A `{}` does not support `{}`", ty, derive),
+            });
+            return Err(Error::new(
+                format!(
+                    "`{}` can not derive `{}`, as its member `{}` is a `{}`",
+                    name, derive, member.name, ty
+                ),
+                codes::E021,
+                span.clone(),
+            )
+            .with_note(note, Some(member.name.span.clone())));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the body of `item` if it is a function, whose signature must be
+/// in `types`, and returns the types of its expressions.
+pub fn check_function(types: &Types, item: &ast::Spanned<ast::Item>) -> Result<ExprTypes, Error> {
+    if let ast::Item::Function {
+        name,
+        parameters,
+        body,
+        ..
+    } = &item.node
+    {
+        let sig = &types.functions[name.as_str()];
+        let mut checker = Checker {
+            types,
+            body,
+            ret: sig.ret.clone(),
+            scopes: vec![parameters
+                .iter()
+                .map(|(param, _)| param.to_string())
+                .zip(sig.params.iter().cloned())
+                .collect()],
+            exprs: ExprTypes::default(),
+        };
+        checker.stmts(&body.block)?;
+        if sig.ret != Ty::Unit && !returns(body, &body.block)? {
+            return Err(Error::new(
+                format!(
+                    "Function `{}` does not return a `{}` on every path",
+                    name, sig.ret
+                ),
+                codes::E028,
+                name.span.clone(),
+            ));
+        }
+        return Ok(checker.exprs);
+    }
+    Ok(ExprTypes::default())
+}
+
+/// Whether every path through `stmts` ends in a `return`.
+pub fn returns(body: &ast::Body, stmts: &[ast::StmtId]) -> Result<bool, Error> {
+    for stmt in stmts {
+        let returned = match &body.stmt(*stmt)?.node {
+            ast::Stmt::Return { .. } => true,
+            ast::Stmt::Block { stmts } => returns(body, stmts)?,
+            ast::Stmt::IfStmt {
+                body: then,
+                else_stmt: Some(else_stmt),
+                ..
+            } => returns(body, then)? && returns(body, std::slice::from_ref(else_stmt))?,
+            _ => false,
+        };
+        if returned {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+struct Checker<'a> {
+    types: &'a Types,
+    body: &'a ast::Body,
+    ret: Ty,
+    scopes: Vec<HashMap<String, Ty>>,
+    exprs: ExprTypes,
+}
+
+impl<'a> Checker<'a> {
+    fn stmts(&mut self, stmts: &[ast::StmtId]) -> Result<(), Error> {
+        self.scopes.push(HashMap::new());
+        for stmt in stmts {
+            attrs::check(self.body.attrs(*stmt), Target::Stmt)?;
+            self.stmt(self.body.stmt(*stmt)?)?;
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &ast::Spanned<ast::Stmt>) -> Result<(), Error> {
+        match &stmt.node {
+            ast::Stmt::Let { var_name, value } => {
+                let ty = self.expr(*value)?;
+                if ty == Ty::Unit {
+                    return Err(Error::new(
+                        format!("`{}` can not be bound to a value of type `()`", var_name),
+                        codes::E029,
+                        self.body.expr(*value)?.span.clone(),
+                    ));
+                }
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .insert(var_name.to_string(), ty);
+            }
+            ast::Stmt::Assignment { var_name, value } => {
+                let ty = self.expr(*value)?;
+                let expected = self.lookup(var_name)?;
+                if ty != expected {
+                    return Err(Error::new(
+                        format!(
+                            "Can not assign a `{}` to `{}`, which is a `{}`",
+                            ty, var_name, expected
+                        ),
+                        codes::E030,
+                        self.body.expr(*value)?.span.clone(),
+                    ));
+                }
+            }
+            ast::Stmt::IfStmt {
+                condition,
+                body,
+                else_stmt,
+            } => {
+                let ty = self.expr(*condition)?;
+                if ty != Ty::Bool {
+                    return Err(Error::new(
+                        format!("Expected a `bool` as condition, but found a `{}`", ty),
+                        codes::E031,
+                        self.body.expr(*condition)?.span.clone(),
+                    ));
+                }
+                self.stmts(body)?;
+                if let Some(else_stmt) = else_stmt {
+                    self.stmt(self.body.stmt(*else_stmt)?)?;
+                }
+            }
+            ast::Stmt::Block { stmts } => self.stmts(stmts)?,
+            ast::Stmt::Expr { expr } => {
+                self.expr(*expr)?;
+            }
+            ast::Stmt::Return { value } => {
+                let ty = match value {
+                    Some(value) => self.expr(*value)?,
+                    None => Ty::Unit,
+                };
+                if ty != self.ret {
+                    let span = match value {
+                        Some(value) => self.body.expr(*value)?.span.clone(),
+                        None => stmt.span.clone(),
+                    };
+                    return Err(Error::new(
+                        format!("Expected to return a `{}`, but found a `{}`", self.ret, ty),
+                        codes::E032,
+                        span,
+                    ));
+                }
+            }
+            ast::Stmt::Error(err) => return Err(err.clone()),
+        }
+        Ok(())
+    }
+
+    fn lookup(&self, name: &ast::Ident) -> Result<Ty, Error> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name.as_str()).cloned())
+            .ok_or_else(|| {
+                Error::new(
+                    format!("Unknown name `{}`", name),
+                    codes::E033,
+                    name.span.clone(),
+                )
+            })
+    }
+
+    /// The type of the expression `id`, which is also kept in `exprs`.
+    fn expr(&mut self, id: ast::ExprId) -> Result<Ty, Error> {
+        let ty = self.infer(self.body.expr(id)?)?;
+        self.exprs.insert(id, ty.clone());
+        Ok(ty)
+    }
+
+    fn infer(&mut self, expr: &ast::Spanned<ast::Expr>) -> Result<Ty, Error> {
+        Ok(match &expr.node {
+            ast::Expr::Literal(ast::Lit::Int(int)) if *int > i32::MAX as usize => {
+                return Err(Error::new(
+                    format!(
+                        "`{}` does not fit in an `int`, which is at most {}",
+                        int,
+                        i32::MAX
+                    ),
+                    codes::E034,
+                    expr.span.clone(),
+                ))
+            }
+            ast::Expr::Literal(ast::Lit::Int(_)) => Ty::Int,
+            ast::Expr::Literal(ast::Lit::Float(_)) => Ty::Float,
+            ast::Expr::Literal(ast::Lit::Str(_)) => Ty::Str,
+            ast::Expr::Literal(ast::Lit::Bool(_)) => Ty::Bool,
+            ast::Expr::Ident(name) => self.lookup(name)?,
+            ast::Expr::FnCall { fn_name, args } => {
+                let mut arg_types = Vec::new();
+                for arg in args {
+                    let ty = self.expr(*arg)?;
+                    if ty == Ty::Unit {
+                        return Err(Error::new(
+                            format!("Can not pass a value of type `()` to `{}`", fn_name),
+                            codes::E035,
+                            self.body.expr(*arg)?.span.clone(),
+                        ));
+                    }
+                    arg_types.push(ty);
+                }
+                if ownership::is_builtin(fn_name) {
+                    return builtin(self.types, fn_name, &arg_types, expr.span.clone());
+                }
+                let sig = self.types.functions.get(fn_name.as_str()).ok_or_else(|| {
+                    Error::new(
+                        format!("Unknown function `{}`", fn_name),
+                        codes::E036,
+                        fn_name.span.clone(),
+                    )
+                })?;
+                if sig.params != arg_types {
+                    return Err(Error::new(
+                        format!(
+                            "`{}` takes ({}), but was given ({})",
+                            fn_name,
+                            list(&sig.params),
+                            list(&arg_types)
+                        ),
+                        codes::E004,
+                        expr.span.clone(),
+                    ));
+                }
+                sig.ret.clone()
+            }
+            ast::Expr::PrefixOp { op, expr: operand } => {
+                let ty = self.expr(*operand)?;
+                match op {
+                    TokenKind::Bang if ty == Ty::Bool => ty,
+                    TokenKind::Plus | TokenKind::Minus if ty.is_numeric() => ty,
+                    op => {
+                        return Err(Error::new(
+                            format!("Prefix `{}` can not be applied to a `{}`", op, ty),
+                            codes::E037,
+                            expr.span.clone(),
+                        ))
+                    }
+                }
+            }
+            ast::Expr::PostfixOp { op, .. } => {
+                return Err(Error::new(
+                    format!("Postfix `{}` is not supported yet", op),
+                    codes::E003,
+                    expr.span.clone(),
+                ))
+            }
+            ast::Expr::Member {
+                expr: operand,
+                member,
+            } => {
+                let ty = self.expr(*operand)?;
+                let members = match &ty {
+                    Ty::Struct(name) => self.types.members(name).unwrap_or_default(),
+                    _ => &[],
+                };
+                members
+                    .iter()
+                    .find(|(name, _)| name == member.as_str())
+                    .map(|(_, ty)| ty.clone())
+                    .ok_or_else(|| {
+                        Error::new(
+                            format!("`{}` has no member `{}`", ty, member),
+                            codes::E047,
+                            member.span.clone(),
+                        )
+                    })?
+            }
+            ast::Expr::StructLit {
+                struct_name,
+                fields,
+            } => {
+                let members = self.types.members(struct_name).ok_or_else(|| {
+                    Error::new(
+                        format!("Unknown struct `{}`", struct_name),
+                        codes::E022,
+                        struct_name.span.clone(),
+                    )
+                })?;
+                for (index, (member, value)) in fields.iter().enumerate() {
+                    let ty = self.expr(*value)?;
+                    let expected = members
+                        .iter()
+                        .find(|(name, _)| name == member.as_str())
+                        .map(|(_, ty)| ty)
+                        .ok_or_else(|| {
+                            Error::new(
+                                format!("`{}` has no member `{}`", struct_name, member),
+                                codes::E047,
+                                member.span.clone(),
+                            )
+                        })?;
+                    if let Some((first, _)) = fields[..index]
+                        .iter()
+                        .find(|(other, _)| other.as_str() == member.as_str())
+                    {
+                        return Err(Error::new(
+                            format!("Member `{}` is given twice", member),
+                            codes::E048,
+                            member.span.clone(),
+                        )
+                        .with_note("First given here".to_string(), Some(first.span.clone())));
+                    }
+                    if ty != *expected {
+                        return Err(Error::new(
+                            format!(
+                                "Member `{}` of `{}` is a `{}`, but was given a `{}`",
+                                member, struct_name, expected, ty
+                            ),
+                            codes::E049,
+                            self.body.expr(*value)?.span.clone(),
+                        ));
+                    }
+                }
+                if let Some((name, _)) = members.iter().find(|(name, _)| {
+                    !fields
+                        .iter()
+                        .any(|(member, _)| member.as_str() == name.as_str())
+                }) {
+                    return Err(Error::new(
+                        format!("`{}` is missing its member `{}`", struct_name, name),
+                        codes::E048,
+                        expr.span.clone(),
+                    ));
+                }
+                Ty::Struct(struct_name.to_string())
+            }
+            ast::Expr::InfixOp { op, lhs, rhs } => {
+                let lhs = self.expr(*lhs)?;
+                let rhs = self.expr(*rhs)?;
+                let ty = match op {
+                    TokenKind::Plus if lhs == Ty::Str && rhs == Ty::Str => Some(Ty::Str),
+                    TokenKind::Plus
+                    | TokenKind::Minus
+                    | TokenKind::Times
+                    | TokenKind::Slash
+                    | TokenKind::Caret
+                        if lhs == rhs && lhs.is_numeric() =>
+                    {
+                        Some(lhs.clone())
+                    }
+                    TokenKind::Eqq | TokenKind::Neq
+                        if lhs == rhs && self.types.supports(&lhs, Derive::Eq) =>
+                    {
+                        Some(Ty::Bool)
+                    }
+                    TokenKind::LAngle | TokenKind::RAngle | TokenKind::Leq | TokenKind::Geq
+                        if lhs == rhs && (lhs.is_numeric() || lhs == Ty::Char) =>
+                    {
+                        Some(Ty::Bool)
+                    }
+                    TokenKind::And | TokenKind::Or if lhs == Ty::Bool && rhs == Ty::Bool => {
+                        Some(Ty::Bool)
+                    }
+                    _ => None,
+                };
+                ty.ok_or_else(|| {
+                    let err = Error::new(
+                        format!("`{}` can not be applied to a `{}` and a `{}`", op, lhs, rhs),
+                        codes::E038,
+                        expr.span.clone(),
+                    );
+                    match missing(&lhs, Derive::Eq) {
+                        Some(note)
+                            if lhs == rhs && matches!(op, TokenKind::Eqq | TokenKind::Neq) =>
+                        {
+                            err.with_note(note, None)
+                        }
+                        _ => err,
+                    }
+                })?
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::visit::{walk_expr, Visitor};
+    use crate::cst;
+
+    #[test]
+    fn every_expression_has_a_type() {
+        let items = cst::lower::items(&cst::parse(
+            "fn sq(x: int) -> int {
+    return x * x;
+}
+
+fn main() {
+    let n = sq(2);
+    print(n > 3, \"n\" + \"!\", -1.5);
+}
+",
+        ));
+        let types = declare(&items).unwrap();
+        let exprs = check_function(&types, items[1].as_ref().unwrap()).unwrap();
+        // After their operands, like the checker gets to them.
+        struct Sources<'a>(&'a ExprTypes, Vec<(String, String)>);
+        impl Visitor for Sources<'_> {
+            fn visit_expr(
+                &mut self,
+                body: &ast::Body,
+                id: ast::ExprId,
+                expr: &ast::Spanned<ast::Expr>,
+            ) {
+                walk_expr(self, body, expr);
+                self.1
+                    .push((ast::print::expr_source(body, id), self.0[id].to_string()));
+            }
+        }
+        let mut sources = Sources(&exprs, Vec::new());
+        sources.visit_item(items[1].as_ref().unwrap());
+        assert_eq!(
+            sources.1,
+            [
+                ("2", "int"),
+                ("sq(2)", "int"),
+                ("n", "int"),
+                ("3", "int"),
+                ("n > 3", "bool"),
+                ("\"n\"", "string"),
+                ("\"!\"", "string"),
+                ("\"n\" + \"!\"", "string"),
+                ("1.5", "float"),
+                ("-1.5", "float"),
+                ("print(n > 3, \"n\" + \"!\", -1.5)", "()"),
+            ]
+            .map(|(source, ty)| (source.to_string(), ty.to_string()))
+        );
+    }
+
+    #[test]
+    fn struct_literals_and_members() {
+        let check = |stmts: &str| {
+            let text = format!(
+                "#[derive(Hash)]
+struct Point {{
+    x: int,
+    y: int,
+}}
+
+struct Plain {{
+    x: int,
+}}
+
+fn main() {{
+    {}
+}}
+",
+                stmts
+            );
+            let items = cst::lower::items(&cst::parse(&text));
+            let types = declare(&items).unwrap();
+            check_function(&types, items[2].as_ref().unwrap())
+                .map(|_| ())
+                .map_err(|err| (err.code().code, text[err.span()].to_string()))
+        };
+        assert_eq!(check("print(Point { y: 2, x: 1 }.x + 1);"), Ok(()));
+        assert_eq!(check("print(hash(Point { x: 1, y: 2 }), hash(1));"), Ok(()));
+        assert_eq!(
+            check("print(Point { x: 1, y: 2 }.z);"),
+            Err(("E047", "z".to_string()))
+        );
+        assert_eq!(
+            check("print(Point { x: 1, z: 2 });"),
+            Err(("E047", "z".to_string()))
+        );
+        assert_eq!(
+            check("print(Point { x: 1, x: 2, y: 3 });"),
+            Err(("E048", "x".to_string()))
+        );
+        assert_eq!(
+            check("print(Point { x: 1 });"),
+            Err(("E048", "Point { x: 1 }".to_string()))
+        );
+        assert_eq!(
+            check("print(Point { x: 1, y: true });"),
+            Err(("E049", "true".to_string()))
+        );
+        assert_eq!(
+            check("print(Line { x: 1 });"),
+            Err(("E022", "Line".to_string()))
+        );
+        assert_eq!(
+            check("print(hash(Plain { x: 1 }));"),
+            Err(("E050", "hash(Plain { x: 1 })".to_string()))
+        );
+        assert_eq!(
+            check("print(hash(1.5));"),
+            Err(("E050", "hash(1.5)".to_string()))
+        );
+    }
+}
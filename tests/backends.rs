@@ -0,0 +1,206 @@
+//! Programs print the same on every backend: the VM at each `-O` level, the
+//! Rust output built with rustc and, when llc and cc are installed, the
+//! LLVM output built with them and `runtime/fir.c`.
+//!
+//! The programs in `tests/llvm` also have snapshots of their LLVM IR: each
+//! `<name>.fir` is built with `--emit=llvm -O0`, where nothing is optimized
+//! away, and compared with `<name>.ll`. Setting `UPDATE_SNAPSHOTS=1` writes
+//! the snapshots instead.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn fir(args: &[&str], path: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_fir"))
+        .args(args)
+        .arg(path)
+        .output()
+        .unwrap()
+}
+
+fn stdout(what: &str, output: Output) -> String {
+    assert!(
+        output.status.success(),
+        "{}: {}",
+        what,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn installed(tool: &str) -> bool {
+    Command::new(tool).arg("--version").output().is_ok()
+}
+
+/// Runs `text` on every backend and checks that each prints `expected`.
+fn run_everywhere(name: &str, text: &str, expected: &str) {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("backends")
+        .join(name);
+    fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("main.fir");
+    fs::write(&source, text).unwrap();
+
+    for level in ["-O0", "-O1", "-O2"] {
+        let output = stdout(&format!("vm {}", level), fir(&["run", level], &source));
+        assert_eq!(output, expected, "vm {}", level);
+    }
+
+    let rust = dir.join("main.rs");
+    let code = stdout("rust", fir(&["build", "--emit=rust"], &source));
+    fs::write(&rust, code).unwrap();
+    let binary = dir.join("rust");
+    let output = Command::new("rustc")
+        .args(["--edition=2021", "-o"])
+        .arg(&binary)
+        .arg(&rust)
+        .output()
+        .unwrap();
+    stdout("rustc", output);
+    let output = stdout("rust", Command::new(&binary).output().unwrap());
+    assert_eq!(output, expected, "rust");
+
+    if !installed("llc") || !installed("cc") {
+        return;
+    }
+    let ir = dir.join("main.ll");
+    let code = stdout("llvm", fir(&["build", "--emit=llvm", "-O2"], &source));
+    fs::write(&ir, code).unwrap();
+    let asm = dir.join("main.s");
+    let output = Command::new("llc")
+        .arg("-relocation-model=pic")
+        .arg(&ir)
+        .arg("-o")
+        .arg(&asm)
+        .output()
+        .unwrap();
+    stdout("llc", output);
+    let binary = dir.join("llvm");
+    let output = Command::new("cc")
+        .arg(&asm)
+        .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("runtime/fir.c"))
+        .arg("-o")
+        .arg(&binary)
+        .output()
+        .unwrap();
+    stdout("cc", output);
+    let output = stdout("llvm", Command::new(&binary).output().unwrap());
+    assert_eq!(output, expected, "llvm");
+}
+
+/// Runs `tests/llvm/<name>.fir` everywhere, and compares its LLVM IR with
+/// the snapshot.
+fn llvm_snapshot(name: &str, expected: &str) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/llvm");
+    let source = dir.join(format!("{}.fir", name));
+    run_everywhere(name, &fs::read_to_string(&source).unwrap(), expected);
+
+    let ir = stdout("llvm", fir(&["build", "--emit=llvm", "-O0"], &source));
+    let path = dir.join(format!("{}.ll", name));
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, &ir).unwrap();
+    } else {
+        let snapshot =
+            fs::read_to_string(&path).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+        assert_eq!(ir, snapshot, "{}", name);
+    }
+
+    // `run_everywhere` builds the optimized IR, this one must build too.
+    if installed("llc") {
+        let output = Command::new("llc")
+            .arg("-relocation-model=pic")
+            .arg(&path)
+            .arg("-o")
+            .arg(PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.O0.s", name)))
+            .output()
+            .unwrap();
+        stdout("llc -O0", output);
+    }
+}
+
+#[test]
+fn llvm_arithmetic() {
+    llvm_snapshot("arithmetic", "-5 3 0\n4 true\n");
+}
+
+#[test]
+fn llvm_strings() {
+    llvm_snapshot("strings", "hello fir\ntrue\n");
+}
+
+#[test]
+fn llvm_by_ref() {
+    llvm_snapshot("by_ref", "hey!!\n");
+}
+
+#[test]
+fn llvm_structs() {
+    llvm_snapshot("structs", "4 2.5 1\n");
+}
+
+#[test]
+fn call_results_are_copied() {
+    run_everywhere(
+        "call_results_are_copied",
+        "fn sq(x: int) -> int {
+    return x * x;
+}
+
+fn main() {
+    let n = sq(2);
+    let m = n;
+    m = 5;
+    print(n);
+    print(m);
+}
+",
+        "4\n5\n",
+    );
+}
+
+#[test]
+fn strings_from_calls_are_concatenated() {
+    run_everywhere(
+        "strings_from_calls_are_concatenated",
+        "fn name() -> string {
+    return \"fir\";
+}
+
+fn main() {
+    print(name() + name());
+    let d = name();
+    let e = name();
+    print(d + e);
+    print(d + \"-\" + e);
+}
+",
+        "firfir\nfirfir\nfir-fir\n",
+    );
+}
+
+#[test]
+fn struct_members_are_read() {
+    run_everywhere(
+        "struct_members_are_read",
+        "struct Pet {
+    name: string,
+    age: int,
+}
+
+fn older(pet: Pet) -> Pet {
+    return Pet { age: pet.age + 1, name: pet.name };
+}
+
+fn main() {
+    let name = \"Rex\";
+    let pet = Pet { name: name, age: 3 };
+    let old = older(pet);
+    print(old.name, \" \", old.age, \" \", pet.age);
+    print(Pet { name: \"Tom\", age: 1 }.name);
+}
+",
+        "Rex 4 3\nTom\n",
+    );
+}
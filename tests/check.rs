@@ -0,0 +1,154 @@
+//! `fir check` reports every error of a file, syntax errors included.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+use serde_json::Value;
+
+/// Writes `text` to a file of its own and runs `fir check` on it.
+fn check(name: &str, text: &str, format: &str) -> Output {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("main.fir");
+    fs::write(&path, text).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_fir"))
+        .arg("check")
+        .arg(format!("--message-format={}", format))
+        .arg(&path)
+        .output()
+        .unwrap()
+}
+
+const ERRORS: &str = "fn a() -> int {
+    return true;
+}
+
+fn b() {
+    let 5 = 3;
+}
+
+fn c() {
+    print(1 + \"s\");
+    print(2)
+}
+
+fn main() {
+    print(a());
+    b();
+    c();
+}
+";
+
+/// The code and line of each error, in the order they are reported.
+const EXPECTED: &[(&str, u64)] = &[("E008", 6), ("E001", 12), ("E032", 2), ("E038", 10)];
+
+fn errors(diagnostics: &[Value]) -> Vec<(&str, u64)> {
+    diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic["severity"] == "error")
+        .map(|diagnostic| {
+            (
+                diagnostic["code"].as_str().unwrap(),
+                diagnostic["span"]["line"].as_u64().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn json_has_every_error() {
+    let output = check("json_has_every_error", ERRORS, "json");
+    assert_eq!(output.status.code(), Some(1));
+    let diagnostics: Vec<Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let found = errors(&diagnostics);
+    for expected in EXPECTED {
+        assert!(
+            found.contains(expected),
+            "{:?} not in {:?}",
+            expected,
+            found
+        );
+    }
+    let first = |code: &str, line: u64| found.iter().position(|error| *error == (code, line));
+    // Syntax errors come first, then the function bodies in order.
+    assert!(first("E008", 6) < first("E032", 2));
+    assert!(first("E032", 2) < first("E038", 10));
+}
+
+#[test]
+fn sarif_has_every_error() {
+    let output = check("sarif_has_every_error", ERRORS, "sarif");
+    assert_eq!(output.status.code(), Some(1));
+    let log: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let results = log["runs"][0]["results"].as_array().unwrap();
+    let found: Vec<_> = results
+        .iter()
+        .filter(|result| result["level"] == "error")
+        .map(|result| {
+            (
+                result["ruleId"].as_str().unwrap(),
+                result["locations"][0]["physicalLocation"]["region"]["startLine"]
+                    .as_u64()
+                    .unwrap(),
+            )
+        })
+        .collect();
+    for expected in EXPECTED {
+        assert!(
+            found.contains(expected),
+            "{:?} not in {:?}",
+            expected,
+            found
+        );
+    }
+}
+
+#[test]
+fn syntax_errors_fail_the_check() {
+    for (name, text) in [
+        ("let_pattern", "fn main() {\n    let 5 = 3;\n}\n"),
+        ("fn_name", "fn 9() {}\n\nfn main() {}\n"),
+        (
+            "missing_semicolon",
+            "fn main() {\n    let x = 1\n    print(x);\n}\n",
+        ),
+    ] {
+        let output = check(name, text, "json");
+        assert_eq!(output.status.code(), Some(1), "{}", name);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            stdout.contains("\"severity\":\"error\""),
+            "{}: {}",
+            name,
+            stdout
+        );
+        assert!(output.stderr.is_empty(), "{}", name);
+    }
+}
+
+#[test]
+fn lints_denied_in_the_manifest_fail_the_check() {
+    let text = "fn main() {\n    let x = 1;\n}\n";
+    let output = check("lints_warn", text, "json");
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\"severity\":\"warning\""), "{}", stdout);
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("lints_deny");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("fir.toml"),
+        "[lints]\nunused_variables = \"deny\"\n",
+    )
+    .unwrap();
+    let output = check("lints_deny", text, "json");
+    assert_eq!(output.status.code(), Some(1));
+    let diagnostic: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(diagnostic["code"], "W001");
+    assert_eq!(diagnostic["severity"], "error");
+}
@@ -0,0 +1,261 @@
+//! Every code the compiler emits is registered, so `fir explain` knows it,
+//! every registered code has an explanation whose erroneous example gives
+//! that code, and no two kinds of error share a code.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn fir(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_fir"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// The codes `fir explain` lists.
+fn registered() -> Vec<String> {
+    let output = fir(&["explain"]);
+    assert!(output.status.success());
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| line.split(':').next().unwrap().to_string())
+        .collect()
+}
+
+/// The codes in the output of a command, whether printed by a human or as
+/// JSON.
+fn emitted(output: &Output) -> Vec<String> {
+    let text = String::from_utf8_lossy(&output.stdout) + String::from_utf8_lossy(&output.stderr);
+    let mut codes = Vec::new();
+    for (i, _) in text.match_indices(['E', 'W']) {
+        let digits = &text[i + 1..];
+        let len = digits
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(digits.len());
+        let before = text[..i].chars().last();
+        if len == 3 && matches!(before, Some('[' | '"')) {
+            codes.push(text[i..i + 4].to_string());
+        }
+    }
+    codes
+}
+
+fn dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("codes")
+        .join(name);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn assert_registered(codes: &[String], what: &str) {
+    let registered = registered();
+    for code in codes {
+        assert!(
+            registered.contains(code),
+            "{} emitted `{}`, which `fir explain` does not list",
+            what,
+            code
+        );
+    }
+}
+
+#[test]
+fn every_code_has_an_explanation() {
+    let codes = registered();
+    assert!(codes.len() > 20);
+    for code in &codes {
+        let output = fir(&["explain", code]);
+        assert!(output.status.success(), "{}", code);
+        let text = String::from_utf8(output.stdout).unwrap();
+        let (header, explanation) = text.split_once("\n\n").unwrap();
+        assert!(header.contains(&format!("[{}]", code)), "{}", header);
+        assert!(
+            !explanation.trim().is_empty(),
+            "{} has no explanation",
+            code
+        );
+    }
+    let output = fir(&["explain", "E999"]);
+    assert!(!output.status.success());
+}
+
+/// The first ```` ```fir ```` block of an explanation, which is the
+/// erroneous example.
+fn example(explanation: &str) -> Option<&str> {
+    let start = explanation.find("```fir\n")? + "```fir\n".len();
+    let len = explanation[start..].find("```")?;
+    Some(&explanation[start..start + len])
+}
+
+#[test]
+fn examples_emit_their_code() {
+    let explanations = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/error/explanations");
+    // `shadowing` is allowed by default.
+    fs::write(
+        dir("examples").join("fir.toml"),
+        "[lints]\nshadowing = \"warn\"\n",
+    )
+    .unwrap();
+    let mut tried = 0;
+    for code in registered() {
+        let explanation = fs::read_to_string(explanations.join(format!("{}.md", code))).unwrap();
+        let Some(example) = example(&explanation) else {
+            continue;
+        };
+        let path = dir("examples").join(format!("{}.fir", code));
+        fs::write(&path, example).unwrap();
+        let path = path.to_str().unwrap();
+        let mut codes = emitted(&fir(&["check", "--message-format=json", path]));
+        // Runtime errors only show up when the program runs, and those of a
+        // backend when building for it.
+        codes.extend(emitted(&fir(&["run", path])));
+        for emit in ["--emit=rust", "--emit=llvm"] {
+            codes.extend(emitted(&fir(&["build", emit, path])));
+        }
+        assert!(
+            codes.contains(&code),
+            "the example of {} gives {:?}",
+            code,
+            codes
+        );
+        assert_registered(&codes, &format!("the example of {}", code));
+        tried += 1;
+    }
+    assert!(tried > 20);
+}
+
+/// A program for every error the checker, a backend or the VM can stop a
+/// program with, the command that stops there and the code it gives.
+const SITES: &[(&str, &str, &str)] = &[
+    ("check", "fn f(x: Length) {}\n", "E022"),
+    (
+        "check",
+        "struct P {\n    x: int,\n}\n\nfn f(p: P) {\n    print(p);\n}\n",
+        "E023",
+    ),
+    ("check", "fn main() {\n    assert(1);\n}\n", "E024"),
+    (
+        "check",
+        "struct P {\n    x: int,\n}\n\nstruct P {\n    y: int,\n}\n",
+        "E025",
+    ),
+    ("check", "fn f() {}\n\nfn f() {}\n", "E026"),
+    ("check", "#[test]\nfn t(x: int) {}\n", "E027"),
+    (
+        "check",
+        "fn f(x: int) -> int {\n    if (x > 0) {\n        return 1;\n    }\n}\n",
+        "E028",
+    ),
+    ("check", "fn main() {\n    let x = print(1);\n}\n", "E029"),
+    (
+        "check",
+        "fn main() {\n    let x = 1;\n    x = true;\n}\n",
+        "E030",
+    ),
+    ("check", "fn main() {\n    if (1) {}\n}\n", "E031"),
+    ("check", "fn f() -> bool {\n    return 1;\n}\n", "E032"),
+    ("check", "fn main() {\n    print(y);\n}\n", "E033"),
+    ("check", "fn main() {\n    print(3000000000);\n}\n", "E034"),
+    ("check", "fn main() {\n    print(print(1));\n}\n", "E035"),
+    ("check", "fn main() {\n    g();\n}\n", "E036"),
+    (
+        "check",
+        "fn f(x: int) {}\n\nfn main() {\n    f(true);\n}\n",
+        "E004",
+    ),
+    ("check", "fn main() {\n    print(-true);\n}\n", "E037"),
+    ("check", "fn main() {\n    print(1 + 1.0);\n}\n", "E038"),
+    ("check", "fn f(x: list<int>) {}\n", "E039"),
+    ("check", "fn main() {\n    print(3!);\n}\n", "E003"),
+    (
+        "rust",
+        "struct P {\n    x: int,\n}\n\nfn f(p: P) -> P {\n    return p;\n}\n\nfn main() {}\n",
+        "E040",
+    ),
+    (
+        "llvm",
+        "#[derive(Eq)]\nstruct P {\n    x: int,\n}\n\nfn f(a: P, b: P) -> bool {\n    return a == b;\n}\n\nfn main() {}\n",
+        "E041",
+    ),
+    ("run", "fn f() {}\n", "E042"),
+    ("run", "fn main(x: int) {}\n", "E043"),
+    (
+        "run",
+        "fn f(x: int) -> int {\n    return f(x);\n}\n\nfn main() {\n    print(f(1));\n}\n",
+        "E006",
+    ),
+    (
+        "run",
+        "fn f(x: int) -> int {\n    return 1 / x;\n}\n\nfn main() {\n    print(f(0));\n}\n",
+        "E044",
+    ),
+    ("run", "fn main() {\n    assert_eq(1, 2);\n}\n", "E045"),
+];
+
+#[test]
+fn each_error_has_its_own_code() {
+    let dir = dir("sites");
+    let mut seen = Vec::new();
+    for (i, (command, text, code)) in SITES.iter().enumerate() {
+        let path = dir.join(format!("{}.fir", i));
+        fs::write(&path, text).unwrap();
+        let path = path.to_str().unwrap();
+        let output = match *command {
+            "check" => fir(&["check", "--message-format=json", path]),
+            "run" => fir(&["run", path]),
+            emit => fir(&["build", &format!("--emit={}", emit), path]),
+        };
+        let codes = emitted(&output);
+        let first = codes.iter().find(|code| code.starts_with('E'));
+        assert_eq!(first.map(String::as_str), Some(*code), "{}", text);
+        assert!(!seen.contains(code), "{} is given for two errors", code);
+        seen.push(*code);
+    }
+    assert_registered(
+        &seen.iter().map(|code| code.to_string()).collect::<Vec<_>>(),
+        "an error",
+    );
+}
+
+#[test]
+fn failed_output_has_its_own_code() {
+    let Ok(full) = fs::File::create("/dev/full") else {
+        return;
+    };
+    let path = dir("sites").join("output.fir");
+    fs::write(&path, "fn main() {\n    print(1);\n}\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_fir"))
+        .arg("run")
+        .arg(&path)
+        .stdout(full)
+        .output()
+        .unwrap();
+    assert_eq!(emitted(&output), ["E046"]);
+}
+
+#[test]
+fn other_inputs_emit_registered_codes() {
+    let dir = dir("inputs");
+    let firc = dir.join("bad.firc");
+    fs::write(&firc, "garbage").unwrap();
+    let json = dir.join("bad.json");
+    fs::write(&json, "{\"items\": 3}").unwrap();
+    let project = dir.join("project");
+    fs::create_dir_all(&project).unwrap();
+    fs::write(project.join("fir.toml"), "[lints]\nbogus\n").unwrap();
+    let source = project.join("main.fir");
+    fs::write(&source, "fn main() {}\n").unwrap();
+
+    for (args, code) in [
+        (["run", firc.to_str().unwrap()], "E007"),
+        (["run", json.to_str().unwrap()], "E014"),
+        (["check", source.to_str().unwrap()], "E012"),
+    ] {
+        let codes = emitted(&fir(&args));
+        assert!(codes.contains(&code.to_string()), "{:?}: {:?}", args, codes);
+        assert_registered(&codes, code);
+    }
+}
@@ -0,0 +1,154 @@
+//! The Rust backend emits what a struct derives, and nothing else, and the
+//! VM runs the derives the same way.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn fir(args: &[&str], name: &str, text: &str) -> Output {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("derive")
+        .join(name);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("main.fir");
+    fs::write(&path, text).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_fir"))
+        .args(args)
+        .arg(&path)
+        .output()
+        .unwrap()
+}
+
+fn emit_rust(name: &str, text: &str) -> Output {
+    fir(&["build", "--emit=rust"], name, text)
+}
+
+#[test]
+fn only_what_is_derived() {
+    let output = emit_rust(
+        "only_what_is_derived",
+        "#[derive(Eq, Print)]
+struct Point {
+    x: int,
+    y: int,
+}
+
+#[derive(Clone, Hash)]
+struct Size {
+    w: int,
+}
+
+struct Plain {
+    x: int,
+}
+
+fn main() {
+}
+",
+    );
+    assert!(output.status.success());
+    let rust = String::from_utf8(output.stdout).unwrap();
+    assert!(rust.contains("#[derive(Debug, PartialEq)]\npub struct Point {"));
+    assert!(rust.contains("impl std::fmt::Display for Point {"));
+    assert!(rust.contains("#[derive(Clone, Hash)]\npub struct Size {"));
+    assert!(rust.contains("}\n\npub struct Plain {"));
+}
+
+#[test]
+fn copying_needs_clone() {
+    let text = "struct Point {
+    x: int,
+}
+
+fn keep(p: Point) -> Point {
+    return p;
+}
+
+fn main() {
+}
+";
+    let output = emit_rust("copying_needs_clone", text);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("error[E040]: `p` is copied here, which needs `Point` to derive `Clone`"),
+        "{}",
+        stderr
+    );
+
+    let text = text.replace("struct Point", "#[derive(Clone)]\nstruct Point");
+    let output = emit_rust("copying_a_clone", &text);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("return p.clone();"));
+}
+
+const DERIVES: &str = "#[derive(Eq, Print, Hash, Clone)]
+struct Point {
+    x: int,
+    y: int,
+    label: string,
+}
+
+fn main() {
+    let p = Point { y: 2, x: 1, label: \"p\" };
+    let q = p;
+    print(p);
+    assert_eq(p, q);
+    print(p == Point { x: 1, y: 2, label: \"p\" }, p != q);
+    print(hash(p) == hash(q), hash(p) == hash(Point { x: 2, y: 1, label: \"p\" }));
+    print(hash(p));
+}
+";
+
+#[test]
+fn derives_run_in_the_vm() {
+    let output = fir(&["run"], "derives_run_in_the_vm", DERIVES);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let vm = String::from_utf8(output.stdout).unwrap();
+    let mut lines = vm.lines();
+    assert_eq!(lines.next(), Some("Point { x: 1, y: 2, label: p }"));
+    assert_eq!(lines.next(), Some("truefalse"));
+    assert_eq!(lines.next(), Some("truefalse"));
+
+    // `hash` gives what it does in Rust.
+    let output = emit_rust("derives_run_in_the_vm", DERIVES);
+    assert!(output.status.success());
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("derive/derives_run_in_the_vm");
+    fs::write(dir.join("main.rs"), output.stdout).unwrap();
+    let output = Command::new("rustc")
+        .args(["--edition=2021", "-o"])
+        .arg(dir.join("main"))
+        .arg(dir.join("main.rs"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let output = Command::new(dir.join("main")).output().unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), vm);
+}
+
+#[test]
+fn failed_assertions_show_structs() {
+    let text = DERIVES.replace(
+        "assert_eq(p, q);",
+        "assert_eq(p, Point { x: 3, y: 2, label: \"p\" });",
+    );
+    let output = fir(&["run"], "failed_assertions_show_structs", &text);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Point { x: 1, y: 2, label: p }")
+            && stderr.contains("Point { x: 3, y: 2, label: p }"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,26 @@
+//! `fir build` emits nothing for a file with syntax errors, whatever it is
+//! asked to emit, even where the AST could be built despite them.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn syntax_errors_stop_every_kind() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("emit");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("main.fir");
+    // The missing `;` leaves a statement the AST has no trouble with.
+    fs::write(&path, "fn main() {\n    let x = 1;\n    print(x)\n}\n").unwrap();
+    for kind in ["rust", "llvm", "mir", "firc", "ownership", "parallel"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_fir"))
+            .args(["build", &format!("--emit={}", kind)])
+            .arg(&path)
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1), "{}", kind);
+        assert!(output.stdout.is_empty(), "{}", kind);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains("error[E001]"), "{}: {}", kind, stderr);
+    }
+}
@@ -0,0 +1,102 @@
+//! Programs emitted as `.firc` files run like their source does, and damaged
+//! `.firc` files are reported, not panicked on.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn fir(args: &[&str], path: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_fir"))
+        .args(args)
+        .arg(path)
+        .output()
+        .unwrap()
+}
+
+fn dir() -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("firc");
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Builds `text` as a `.firc` file named `name`, and returns its path.
+fn emit(name: &str, text: &str) -> PathBuf {
+    let source = dir().join(format!("{}.fir", name));
+    fs::write(&source, text).unwrap();
+    let output = fir(&["build", "--emit=firc", "-O2"], &source);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let firc = dir().join(format!("{}.firc", name));
+    fs::write(&firc, output.stdout).unwrap();
+    firc
+}
+
+#[test]
+fn emitted_files_run_like_their_source() {
+    let text = "fn twice(text: string) -> string {
+    return text + text;
+}
+
+fn main() {
+    print(twice(\"ab\"), \" \", 2 ^ 10, \" \", 0.5 < 1.0);
+}
+";
+    let firc = emit("round_trip", text);
+    let source = firc.with_extension("fir");
+    for command in ["run", "disasm"] {
+        let expected = fir(&[command, "-O2"], &source);
+        let output = fir(&[command], &firc);
+        assert!(output.status.success(), "{}", command);
+        assert_eq!(output.stdout, expected.stdout, "{}", command);
+    }
+    let output = fir(&["run"], &firc);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "abab 1024 true\n"
+    );
+}
+
+#[test]
+fn runtime_errors_keep_their_spans() {
+    let text = "fn main() {\n    assert(1 > 2);\n}\n";
+    let firc = emit("spans", text);
+    let output = fir(&["run"], &firc);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error[E045]"), "{}", stderr);
+    assert!(stderr.contains("(16..29)"), "{}", stderr);
+    assert_eq!(&text[16..29], "assert(1 > 2)");
+}
+
+#[test]
+fn damaged_files_are_errors() {
+    let firc = emit("damaged", "fn main() {\n    print(1 + 2);\n}\n");
+    let bytes = fs::read(&firc).unwrap();
+    // `main` ends with `return`, followed by the two `u32`s of the span of
+    // each of its instructions.
+    let disasm = String::from_utf8(fir(&["disasm"], &firc).stdout).unwrap();
+    let instrs = disasm
+        .lines()
+        .filter(|line| line.starts_with("    0"))
+        .count();
+    let mut unknown_opcode = bytes.clone();
+    let position = bytes.len() - 8 * instrs - 1;
+    assert_eq!(unknown_opcode[position], 22);
+    unknown_opcode[position] = 200;
+    let cases = [
+        ("empty", Vec::new()),
+        ("magic", b"ELF\x7f\x02\x00".to_vec()),
+        ("truncated", bytes[..bytes.len() / 2].to_vec()),
+        ("unknown_opcode", unknown_opcode),
+    ];
+    for (name, bytes) in cases {
+        let path = dir().join(format!("{}.firc", name));
+        fs::write(&path, bytes).unwrap();
+        let output = fir(&["run"], &path);
+        assert_eq!(output.status.code(), Some(1), "{}", name);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.starts_with("error[E007]"), "{}: {}", name, stderr);
+    }
+}
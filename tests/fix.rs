@@ -0,0 +1,38 @@
+//! `fir fix` rewrites a file with its fixes applied, and running it again
+//! leaves the file as it is.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn fix(path: &Path) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_fir"))
+        .arg("fix")
+        .arg(path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn fixing_twice_changes_nothing() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("fix");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("main.fir");
+    fs::write(
+        &path,
+        "fn main() {\n    let x = 1\n    if (x = 1) {\n        print(x;\n    }\n",
+    )
+    .unwrap();
+
+    assert!(fix(&path).starts_with("applied 4 fixes"));
+    let fixed = fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        fixed,
+        "fn main() {\n    let x = 1;\n    if (x == 1) {\n        print(x);\n    }}\n"
+    );
+    assert!(fix(&path).starts_with("applied 0 fixes"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), fixed);
+    assert!(!dir.join("main.fir.fixing").exists());
+}
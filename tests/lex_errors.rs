@@ -0,0 +1,98 @@
+//! What the lexer can not make sense of is reported, not panicked on, by
+//! every command that reads source.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn fir(name: &str, text: &str, args: &[&str]) -> Output {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("lex_errors")
+        .join(name);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("main.fir");
+    fs::write(&path, text).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_fir"))
+        .args(args)
+        .arg(&path)
+        .output()
+        .unwrap()
+}
+
+/// Each input and the code of the error it must give.
+const CASES: &[(&str, &str, &str)] = &[
+    (
+        "unexpected_character",
+        "fn main() {\n    let x = 1 $ 2;\n}\n",
+        "E015",
+    ),
+    (
+        "unterminated_string",
+        "fn main() {\n    let x = \"abc;\n}\n",
+        "E016",
+    ),
+    (
+        "invalid_escape",
+        "fn main() {\n    print(\"a\\qb\");\n}\n",
+        "E017",
+    ),
+    (
+        "exponent_without_digits",
+        "fn main() {\n    let x = 1e;\n}\n",
+        "E018",
+    ),
+    (
+        "unterminated_comment",
+        "fn main() {\n    /* not closed\n}\n",
+        "E019",
+    ),
+    (
+        "int_too_large",
+        "fn main() {\n    let x = 99999999999999999999999;\n}\n",
+        "E034",
+    ),
+];
+
+#[test]
+fn check_reports_the_error() {
+    for (name, text, code) in CASES {
+        let output = fir(name, text, &["check", "--message-format=json"]);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(!stderr.contains("panicked"), "{}: {}", name, stderr);
+        assert_eq!(output.status.code(), Some(1), "{}", name);
+        assert!(
+            stdout.contains(&format!("\"code\":\"{}\"", code)),
+            "{}: expected {} in {}",
+            name,
+            code,
+            stdout
+        );
+    }
+}
+
+#[test]
+fn run_reports_the_error() {
+    for (name, text, code) in CASES {
+        let output = fir(name, text, &["run"]);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(!stderr.contains("panicked"), "{}: {}", name, stderr);
+        assert_eq!(output.status.code(), Some(1), "{}", name);
+        assert!(
+            stderr.contains(&format!("[{}]", code)),
+            "{}: expected {} in {}",
+            name,
+            code,
+            stderr
+        );
+    }
+}
+
+#[test]
+fn parse_prints_what_it_can() {
+    for (name, text, _) in CASES {
+        let output = fir(name, text, &["parse", "--emit=fir"]);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(!stderr.contains("panicked"), "{}: {}", name, stderr);
+    }
+}
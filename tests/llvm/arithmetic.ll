@@ -0,0 +1,143 @@
+declare void @fir_print_int(i32)
+declare void @fir_print_float(double)
+declare void @fir_print_bool(i1)
+declare void @fir_print_char(i8)
+declare void @fir_print_str(i8*)
+declare void @fir_print_newline()
+declare void @fir_assert(i1, i8*)
+declare void @fir_assert_eq_int(i32, i32, i8*, i8*)
+declare void @fir_assert_eq_float(double, double, i8*, i8*)
+declare void @fir_assert_eq_bool(i1, i1, i8*, i8*)
+declare void @fir_assert_eq_char(i8, i8, i8*, i8*)
+declare void @fir_assert_eq_str(i8*, i8*, i8*, i8*)
+declare i8* @fir_str_concat(i8*, i8*)
+declare i1 @fir_str_eq(i8*, i8*)
+declare i32 @fir_pow_int(i32, i32)
+declare double @llvm.pow.f64(double, double)
+@.str.0 = private unnamed_addr constant [2 x i8] c" \00"
+@.str.1 = private unnamed_addr constant [2 x i8] c" \00"
+@.str.2 = private unnamed_addr constant [2 x i8] c" \00"
+
+define i32 @clamp(i32 %arg0, i32 %arg1, i32 %arg2) {
+entry:
+  %_0 = alloca i32
+  store i32 %arg0, i32* %_0
+  %_1 = alloca i32
+  store i32 %arg1, i32* %_1
+  %_2 = alloca i32
+  store i32 %arg2, i32* %_2
+  %_3 = alloca i1
+  %_4 = alloca i1
+  br label %bb0
+bb0:
+  %t1 = load i32, i32* %_0
+  %t2 = load i32, i32* %_1
+  %t3 = icmp slt i32 %t1, %t2
+  store i1 %t3, i1* %_3
+  %t4 = load i1, i1* %_3
+  br i1 %t4, label %bb1, label %bb2
+bb1:
+  %t5 = load i32, i32* %_1
+  ret i32 %t5
+bb2:
+  %t6 = load i32, i32* %_0
+  %t7 = load i32, i32* %_2
+  %t8 = icmp sgt i32 %t6, %t7
+  store i1 %t8, i1* %_4
+  %t9 = load i1, i1* %_4
+  br i1 %t9, label %bb3, label %bb4
+bb3:
+  %t10 = load i32, i32* %_2
+  ret i32 %t10
+bb4:
+  %t11 = load i32, i32* %_0
+  ret i32 %t11
+}
+
+define i32 @main() {
+entry:
+  %_0 = alloca i32
+  %_1 = alloca i32
+  %_2 = alloca i32
+  %_3 = alloca i32
+  %_4 = alloca i32
+  %_5 = alloca i32
+  %_6 = alloca i32
+  %_7 = alloca i32
+  %_8 = alloca i32
+  %_9 = alloca double
+  %_10 = alloca double
+  %_11 = alloca i1
+  %_12 = alloca i1
+  %_13 = alloca i32
+  br label %bb0
+bb0:
+  store i32 7, i32* %_0
+  %t1 = load i32, i32* %_0
+  %t2 = sub i32 0, %t1
+  store i32 %t2, i32* %_1
+  %t3 = load i32, i32* %_1
+  %t4 = mul i32 %t3, 3
+  store i32 %t4, i32* %_2
+  %t5 = call i32 @fir_pow_int(i32 2, i32 4)
+  store i32 %t5, i32* %_3
+  %t6 = load i32, i32* %_2
+  %t7 = load i32, i32* %_3
+  %t8 = add i32 %t6, %t7
+  store i32 %t8, i32* %_4
+  %t9 = load i32, i32* %_4
+  store i32 %t9, i32* %_5
+  %t10 = load i32, i32* %_0
+  %t11 = sdiv i32 %t10, 2
+  store i32 %t11, i32* %_6
+  %t12 = load i32, i32* %_6
+  store i32 %t12, i32* %_7
+  %t13 = load i32, i32* %_4
+  %t14 = call i32 @clamp(i32 %t13, i32 0, i32 5)
+  store i32 %t14, i32* %_8
+  %t15 = load i32, i32* %_5
+  call void @fir_print_int(i32 %t15)
+  call void @fir_print_str(i8* getelementptr inbounds ([2 x i8], [2 x i8]* @.str.0, i64 0, i64 0))
+  %t16 = load i32, i32* %_7
+  call void @fir_print_int(i32 %t16)
+  call void @fir_print_str(i8* getelementptr inbounds ([2 x i8], [2 x i8]* @.str.1, i64 0, i64 0))
+  %t17 = load i32, i32* %_8
+  call void @fir_print_int(i32 %t17)
+  call void @fir_print_newline()
+  %t18 = fmul double 0x3FF8000000000000, 0x4008000000000000
+  store double %t18, double* %_9
+  %t19 = load double, double* %_9
+  %t20 = fsub double %t19, 0x3FE0000000000000
+  store double %t20, double* %_10
+  %t21 = load double, double* %_10
+  %t22 = fcmp oge double %t21, 0x4010000000000000
+  store i1 %t22, i1* %_11
+  %t23 = load i1, i1* %_11
+  br i1 %t23, label %bb1, label %bb2
+bb1:
+  %t24 = load i32, i32* %_0
+  %t25 = icmp ne i32 %t24, 7
+  store i1 %t25, i1* %_11
+  br label %bb2
+bb2:
+  %t26 = load i1, i1* %_11
+  store i1 %t26, i1* %_12
+  %t27 = load i1, i1* %_12
+  br i1 %t27, label %bb4, label %bb3
+bb3:
+  %t28 = sub i32 0, 5
+  store i32 %t28, i32* %_13
+  %t29 = load i32, i32* %_4
+  %t30 = load i32, i32* %_13
+  %t31 = icmp eq i32 %t29, %t30
+  store i1 %t31, i1* %_12
+  br label %bb4
+bb4:
+  %t32 = load double, double* %_10
+  call void @fir_print_float(double %t32)
+  call void @fir_print_str(i8* getelementptr inbounds ([2 x i8], [2 x i8]* @.str.2, i64 0, i64 0))
+  %t33 = load i1, i1* %_12
+  call void @fir_print_bool(i1 %t33)
+  call void @fir_print_newline()
+  ret i32 0
+}
@@ -0,0 +1,42 @@
+declare void @fir_print_int(i32)
+declare void @fir_print_float(double)
+declare void @fir_print_bool(i1)
+declare void @fir_print_char(i8)
+declare void @fir_print_str(i8*)
+declare void @fir_print_newline()
+declare void @fir_assert(i1, i8*)
+declare void @fir_assert_eq_int(i32, i32, i8*, i8*)
+declare void @fir_assert_eq_float(double, double, i8*, i8*)
+declare void @fir_assert_eq_bool(i1, i1, i8*, i8*)
+declare void @fir_assert_eq_char(i8, i8, i8*, i8*)
+declare void @fir_assert_eq_str(i8*, i8*, i8*, i8*)
+declare i8* @fir_str_concat(i8*, i8*)
+declare i1 @fir_str_eq(i8*, i8*)
+declare i32 @fir_pow_int(i32, i32)
+declare double @llvm.pow.f64(double, double)
+@.str.0 = private unnamed_addr constant [2 x i8] c"!\00"
+@.str.1 = private unnamed_addr constant [4 x i8] c"hey\00"
+
+define void @shout(i8** %_0) {
+entry:
+  br label %bb0
+bb0:
+  %t1 = load i8*, i8** %_0
+  %t2 = call i8* @fir_str_concat(i8* %t1, i8* getelementptr inbounds ([2 x i8], [2 x i8]* @.str.0, i64 0, i64 0))
+  store i8* %t2, i8** %_0
+  ret void
+}
+
+define i32 @main() {
+entry:
+  %_0 = alloca i8*
+  br label %bb0
+bb0:
+  store i8* getelementptr inbounds ([4 x i8], [4 x i8]* @.str.1, i64 0, i64 0), i8** %_0
+  call void @shout(i8** %_0)
+  call void @shout(i8** %_0)
+  %t1 = load i8*, i8** %_0
+  call void @fir_print_str(i8* %t1)
+  call void @fir_print_newline()
+  ret i32 0
+}
@@ -0,0 +1,76 @@
+declare void @fir_print_int(i32)
+declare void @fir_print_float(double)
+declare void @fir_print_bool(i1)
+declare void @fir_print_char(i8)
+declare void @fir_print_str(i8*)
+declare void @fir_print_newline()
+declare void @fir_assert(i1, i8*)
+declare void @fir_assert_eq_int(i32, i32, i8*, i8*)
+declare void @fir_assert_eq_float(double, double, i8*, i8*)
+declare void @fir_assert_eq_bool(i1, i1, i8*, i8*)
+declare void @fir_assert_eq_char(i8, i8, i8*, i8*)
+declare void @fir_assert_eq_str(i8*, i8*, i8*, i8*)
+declare i8* @fir_str_concat(i8*, i8*)
+declare i1 @fir_str_eq(i8*, i8*)
+declare i32 @fir_pow_int(i32, i32)
+declare double @llvm.pow.f64(double, double)
+@.str.0 = private unnamed_addr constant [7 x i8] c"hello \00"
+@.str.1 = private unnamed_addr constant [4 x i8] c"fir\00"
+@.str.2 = private unnamed_addr constant [10 x i8] c"hello fir\00"
+@.str.3 = private unnamed_addr constant [24 x i8] c"greeting == \22hello fir\22\00"
+@.str.4 = private unnamed_addr constant [1 x i8] c"\00"
+@.str.5 = private unnamed_addr constant [2 x i8] c"!\00"
+@.str.6 = private unnamed_addr constant [8 x i8] c"hello !\00"
+@.str.7 = private unnamed_addr constant [16 x i8] c"greet(\22\22) + \22!\22\00"
+@.str.8 = private unnamed_addr constant [10 x i8] c"\22hello !\22\00"
+@.str.9 = private unnamed_addr constant [6 x i8] c"hello\00"
+
+define i8* @greet(i8* %arg0) {
+entry:
+  %_0 = alloca i8*
+  store i8* %arg0, i8** %_0
+  %_1 = alloca i8*
+  br label %bb0
+bb0:
+  %t1 = load i8*, i8** %_0
+  %t2 = call i8* @fir_str_concat(i8* getelementptr inbounds ([7 x i8], [7 x i8]* @.str.0, i64 0, i64 0), i8* %t1)
+  store i8* %t2, i8** %_1
+  %t3 = load i8*, i8** %_1
+  ret i8* %t3
+}
+
+define i32 @main() {
+entry:
+  %_0 = alloca i8*
+  %_1 = alloca i1
+  %_2 = alloca i8*
+  %_3 = alloca i8*
+  %_4 = alloca i1
+  br label %bb0
+bb0:
+  %t1 = call i8* @greet(i8* getelementptr inbounds ([4 x i8], [4 x i8]* @.str.1, i64 0, i64 0))
+  store i8* %t1, i8** %_0
+  %t2 = load i8*, i8** %_0
+  call void @fir_print_str(i8* %t2)
+  call void @fir_print_newline()
+  %t3 = load i8*, i8** %_0
+  %t4 = call i1 @fir_str_eq(i8* %t3, i8* getelementptr inbounds ([10 x i8], [10 x i8]* @.str.2, i64 0, i64 0))
+  store i1 %t4, i1* %_1
+  %t5 = load i1, i1* %_1
+  call void @fir_assert(i1 %t5, i8* getelementptr inbounds ([24 x i8], [24 x i8]* @.str.3, i64 0, i64 0))
+  %t6 = call i8* @greet(i8* getelementptr inbounds ([1 x i8], [1 x i8]* @.str.4, i64 0, i64 0))
+  store i8* %t6, i8** %_2
+  %t7 = load i8*, i8** %_2
+  %t8 = call i8* @fir_str_concat(i8* %t7, i8* getelementptr inbounds ([2 x i8], [2 x i8]* @.str.5, i64 0, i64 0))
+  store i8* %t8, i8** %_3
+  %t9 = load i8*, i8** %_3
+  call void @fir_assert_eq_str(i8* %t9, i8* getelementptr inbounds ([8 x i8], [8 x i8]* @.str.6, i64 0, i64 0), i8* getelementptr inbounds ([16 x i8], [16 x i8]* @.str.7, i64 0, i64 0), i8* getelementptr inbounds ([10 x i8], [10 x i8]* @.str.8, i64 0, i64 0))
+  %t10 = load i8*, i8** %_0
+  %t11 = call i1 @fir_str_eq(i8* %t10, i8* getelementptr inbounds ([6 x i8], [6 x i8]* @.str.9, i64 0, i64 0))
+  %t12 = xor i1 %t11, true
+  store i1 %t12, i1* %_4
+  %t13 = load i1, i1* %_4
+  call void @fir_print_bool(i1 %t13)
+  call void @fir_print_newline()
+  ret i32 0
+}
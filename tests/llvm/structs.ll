@@ -0,0 +1,87 @@
+%Point = type { i32, double }
+
+declare void @fir_print_int(i32)
+declare void @fir_print_float(double)
+declare void @fir_print_bool(i1)
+declare void @fir_print_char(i8)
+declare void @fir_print_str(i8*)
+declare void @fir_print_newline()
+declare void @fir_assert(i1, i8*)
+declare void @fir_assert_eq_int(i32, i32, i8*, i8*)
+declare void @fir_assert_eq_float(double, double, i8*, i8*)
+declare void @fir_assert_eq_bool(i1, i1, i8*, i8*)
+declare void @fir_assert_eq_char(i8, i8, i8*, i8*)
+declare void @fir_assert_eq_str(i8*, i8*, i8*, i8*)
+declare i8* @fir_str_concat(i8*, i8*)
+declare i1 @fir_str_eq(i8*, i8*)
+declare i32 @fir_pow_int(i32, i32)
+declare double @llvm.pow.f64(double, double)
+@.str.0 = private unnamed_addr constant [2 x i8] c" \00"
+@.str.1 = private unnamed_addr constant [2 x i8] c" \00"
+
+define %Point @moved(%Point %arg0, i32 %arg1) {
+entry:
+  %_0 = alloca %Point
+  store %Point %arg0, %Point* %_0
+  %_1 = alloca i32
+  store i32 %arg1, i32* %_1
+  %_2 = alloca double
+  %_3 = alloca i32
+  %_4 = alloca i32
+  %_5 = alloca %Point
+  br label %bb0
+bb0:
+  %t1 = load %Point, %Point* %_0
+  %t2 = extractvalue %Point %t1, 1
+  store double %t2, double* %_2
+  %t3 = load %Point, %Point* %_0
+  %t4 = extractvalue %Point %t3, 0
+  store i32 %t4, i32* %_3
+  %t5 = load i32, i32* %_3
+  %t6 = load i32, i32* %_1
+  %t7 = add i32 %t5, %t6
+  store i32 %t7, i32* %_4
+  %t8 = load i32, i32* %_4
+  %t9 = insertvalue %Point undef, i32 %t8, 0
+  %t10 = load double, double* %_2
+  %t11 = insertvalue %Point %t9, double %t10, 1
+  store %Point %t11, %Point* %_5
+  %t12 = load %Point, %Point* %_5
+  ret %Point %t12
+}
+
+define i32 @main() {
+entry:
+  %_0 = alloca %Point
+  %_1 = alloca %Point
+  %_2 = alloca i32
+  %_3 = alloca double
+  %_4 = alloca i32
+  br label %bb0
+bb0:
+  %t1 = insertvalue %Point undef, i32 1, 0
+  %t2 = insertvalue %Point %t1, double 0x4004000000000000, 1
+  store %Point %t2, %Point* %_0
+  %t3 = load %Point, %Point* %_0
+  %t4 = call %Point @moved(%Point %t3, i32 3)
+  store %Point %t4, %Point* %_1
+  %t5 = load %Point, %Point* %_1
+  %t6 = extractvalue %Point %t5, 0
+  store i32 %t6, i32* %_2
+  %t7 = load %Point, %Point* %_1
+  %t8 = extractvalue %Point %t7, 1
+  store double %t8, double* %_3
+  %t9 = load %Point, %Point* %_0
+  %t10 = extractvalue %Point %t9, 0
+  store i32 %t10, i32* %_4
+  %t11 = load i32, i32* %_2
+  call void @fir_print_int(i32 %t11)
+  call void @fir_print_str(i8* getelementptr inbounds ([2 x i8], [2 x i8]* @.str.0, i64 0, i64 0))
+  %t12 = load double, double* %_3
+  call void @fir_print_float(double %t12)
+  call void @fir_print_str(i8* getelementptr inbounds ([2 x i8], [2 x i8]* @.str.1, i64 0, i64 0))
+  %t13 = load i32, i32* %_4
+  call void @fir_print_int(i32 %t13)
+  call void @fir_print_newline()
+  ret i32 0
+}
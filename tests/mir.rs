@@ -0,0 +1,68 @@
+//! Snapshots of the MIR of the programs in `tests/mir` at each `-O` level,
+//! `-O0` being the MIR before any optimization. Each `<name>.fir` is built
+//! with `--emit=mir -O<level>` and compared with `<name>.O<level>.mir`.
+//! Setting `UPDATE_SNAPSHOTS=1` writes the snapshots instead.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+fn snapshot(name: &str) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/mir");
+    let source = dir.join(format!("{}.fir", name));
+    let update = env::var_os("UPDATE_SNAPSHOTS").is_some();
+    for level in 0..=2 {
+        let output = Command::new(env!("CARGO_BIN_EXE_fir"))
+            .args(["build", "--emit=mir", &format!("-O{}", level)])
+            .arg(&source)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{} -O{}: {}",
+            name,
+            level,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let mir = String::from_utf8(output.stdout).unwrap();
+        let path = dir.join(format!("{}.O{}.mir", name, level));
+        if update {
+            fs::write(&path, &mir).unwrap();
+        } else {
+            let expected = fs::read_to_string(&path)
+                .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+            assert_eq!(mir, expected, "{} -O{}", name, level);
+        }
+    }
+}
+
+#[test]
+fn fold() {
+    snapshot("fold");
+}
+
+#[test]
+fn propagate() {
+    snapshot("propagate");
+}
+
+#[test]
+fn branches() {
+    snapshot("branches");
+}
+
+#[test]
+fn eliminate_common() {
+    snapshot("cse");
+}
+
+#[test]
+fn inline() {
+    snapshot("inline");
+}
+
+#[test]
+fn copy() {
+    snapshot("copy");
+}
@@ -0,0 +1,79 @@
+//! Errors found after type checking, by a backend or while running, point at
+//! the code they are about, not at the start of the file.
+
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn fir(name: &str, text: &str, args: &[&str]) -> Output {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("spans")
+        .join(name);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("main.fir");
+    fs::write(&path, text).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_fir"))
+        .args(args)
+        .arg(&path)
+        .output()
+        .unwrap()
+}
+
+/// The code and the span of the error the command failed with, which are
+/// printed as `error[E044]: Division by zero (43..48)`.
+fn error(output: &Output) -> (String, Range<usize>) {
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let line = stderr
+        .lines()
+        .find(|line| line.starts_with("error"))
+        .unwrap();
+    let code = line[line.find('[').unwrap() + 1..line.find(']').unwrap()].to_string();
+    let span = &line[line.rfind('(').unwrap() + 1..line.len() - 1];
+    let (start, end) = span.split_once("..").unwrap();
+    (code, start.parse().unwrap()..end.parse().unwrap())
+}
+
+fn assert_points_at(text: &str, output: &Output, code: &str, source: &str) {
+    let (found, span) = error(output);
+    assert_eq!(found, code);
+    assert_eq!(&text[span], source);
+}
+
+#[test]
+fn runtime_errors_point_at_their_code() {
+    let text = "fn main() {
+    let x = 0;
+    print(div(4, x));
+}
+
+fn div(a: int, b: int) -> int {
+    return a / b;
+}
+";
+    // Functions are lowered on their own, and their spans moved to where
+    // they are in the file. At -O2 `div` is inlined into `main`, and the
+    // division keeps its span.
+    for level in ["-O0", "-O1", "-O2"] {
+        let output = fir("division", text, &["run", level]);
+        assert_points_at(text, &output, "E044", "a / b");
+    }
+
+    let text = "fn one() -> int {\n    return 1;\n}\n\nfn main() {\n    assert(one() == 2);\n}\n";
+    let output = fir("assertion", text, &["run"]);
+    assert_points_at(text, &output, "E045", "assert(one() == 2)");
+}
+
+#[test]
+fn backend_errors_point_at_their_code() {
+    let text = "fn one() -> int {\n    return 1;\n}\n\nfn main() {\n    print(3!);\n}\n";
+    for emit in ["--emit=rust", "--emit=mir"] {
+        let output = fir("postfix", text, &["build", emit]);
+        assert_points_at(text, &output, "E003", "3!");
+    }
+
+    let text = "fn one() -> int {\n    return 1;\n}\n\nfn main() {\n    print(hash(2));\n}\n";
+    let output = fir("hash", text, &["build", "--emit=llvm"]);
+    assert_points_at(text, &output, "E041", "hash(2)");
+}